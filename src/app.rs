@@ -5,11 +5,47 @@ use rand::Rng;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::ops::Deref;
+use crate::campaign::{self, CampaignFiles, FileAction};
+use crate::challenge_rating::{CreatureRating, Difficulty, EncounterDifficulty};
+use crate::character::{self, Ability, ClassLevel, Feature, PlayerCharacter, SpellSlot};
+use crate::combat::{CombatTracker, Combatant, DamageModifier, LifeStatus, TurnStatus};
+use crate::conditions::{Condition, Tick};
+use crate::encounter_builder;
 use crate::formulaic_dice_roll::{DiceRollEquationNode, parse_equation, tokenize_equation};
-use crate::structure::{Creature, CreatureMenu, DangerRating, DiceMenu, Interface, NextId, Note, Place, Size, Skill, Spell};
+use crate::generation::{self, Archetype, Distribution, Stat};
+use crate::history::{History, State};
+use crate::interchange::{CampaignDocument, CampaignParts, DocumentFormat, ImportMode, TransferMenu};
+use crate::inventory::{Currency, Inventory, Item, Rarity, MAX_ATTUNED};
+use crate::level_up::{self, ClassLibrary, HpMethod, LevelUpMenu};
+use crate::loot::{LootKind, LootLevel, LootLibrary};
+use crate::names::{NameGenerator, NameList, NameModel};
+use crate::notes::{self, Block, LinkTarget, NotesMenu, Span, SpanStyle};
+use crate::random_encounters::{self, EncounterEntry, TimeOfDay, Weather};
+use crate::random_tables::{self, RandomTable, RowRoll, TableLibrary, TableRow};
+use crate::schema::{self, SCHEMA_VERSION};
+use crate::spells::SpellCompendium;
+use crate::srd_import::{self, SrdImport};
+use crate::stat_block::{self, ExportFormat};
+use crate::structure::{
+    find_creature_mut, find_place, find_place_mut, find_template, is_within, place_path,
+    remove_place, update_instances, BatchMenu, Connection, Creature, CreatureMenu, DangerRating,
+    DiceMenu, EncounterBuilderMenu, Interface, NextId, Note, Party, Place, PlaceKind, Size, Skill,
+    Spell,
+};
 
 // use ::egui::*;
 
+/// The storage key the campaign file settings are saved under, apart from the campaign itself.
+const CAMPAIGN_KEY: &str = "campaign_files";
+
+/// The storage key the app state is saved under as versioned json. older builds saved it under
+/// `eframe::APP_KEY`, which is still read when there is nothing here.
+const STATE_KEY: &str = "app_state";
+
+/// The storage key state that couldn't be read is kept under.
+const QUARANTINE_KEY: &str = "quarantined_state";
+
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -19,56 +55,129 @@ use crate::structure::{Creature, CreatureMenu, DangerRating, DiceMenu, Interface
 ///
 /// * `places`: Vec<Place> - This is a vector of Place structs. Each Place struct represents a place in
 /// the world.
-/// * `selected_place_index`: The index of the place that is currently selected.
-/// * `open_place_windows_indexes`: This is a vector of indexes of the places that are currently open.
+/// * `selected_place_id`: The id of the place that is currently selected.
+/// * `open_place_windows`: This is a vector of ids of the places that are currently open.
 /// * `open_interface`: This is the interface that is currently open.
 /// * `dice_windows`: All of the dice windows that are currently open.
 /// * `id_next`: The next id to be used to allocate a window id.,
 /// * `notes`: A vector of all the notes that have been created.
+/// * `party`: The party that encounter difficulty is calculated against.
+/// * `encounter_builder_windows`: All of the encounter builder windows that are currently open.
+/// * `combat`: The initiative tracker.
+/// * `connections`: The routes between places.
+/// * `time_of_day`: The time of day random encounters are rolled at.
+/// * `weather`: The weather random encounters are rolled in.
+/// * `random_tables`: The random tables and the state of their interface.
+/// * `bestiary`: The creature templates that creatures in places are instances of.
+/// * `srd_import`: The state of the SRD monster import menu.
+/// * `names`: The name lists random names are made from.
+/// * `batch_windows`: All of the batch creature generation windows that are currently open.
+/// * `characters`: The player character sheets.
+/// * `selected_character_id`: The id of the character open in the character editor.
+/// * `classes`: The classes characters level up in.
+/// * `level_up_windows`: All of the level up windows that are currently open.
+/// * `spells`: The spell compendium spellbooks refer to.
+/// * `loot`: The treasure tables loot is generated from.
+/// * `campaign`: The open campaign file, recent files and autosave settings. not part of campaigns.
+/// * `schema_version`: The version of the save format, see `schema::SCHEMA_VERSION`.
+/// * `load_error`: Why the saved state couldn't be read when the app started, and where it was kept.
+/// * `quarantined`: The saved state that couldn't be read, kept in storage until the app closes.
+/// * `save_error`: Why the app state couldn't be saved the last time it was.
+/// * `transfer`: The export and import window.
+/// * `history`: The changes that can be undone and redone, for this session only.
+/// * `notes_menu`: The state of the notes interface.
 pub struct DndTool {
     places: Vec<Place>,
-    selected_place_index: usize,
-    open_place_windows_indexes: Vec<usize>,
+    #[serde(alias = "selected_place_index")]
+    selected_place_id: usize,
+    #[serde(alias = "open_place_windows_indexes")]
+    open_place_windows: Vec<usize>,
     open_interface: Interface,
     // creating_creatcher: bool
     creature_creation_windows: Vec<CreatureMenu>,
     dice_windows: Vec<DiceMenu>,
     id_next: NextId,
     notes: Vec<Note>,
+    party: Party,
+    encounter_builder_windows: Vec<EncounterBuilderMenu>,
+    combat: CombatTracker,
+    connections: Vec<Connection>,
+    time_of_day: TimeOfDay,
+    weather: Weather,
+    random_tables: TableLibrary,
+    bestiary: Vec<Creature>,
+    srd_import: SrdImport,
+    names: NameGenerator,
+    batch_windows: Vec<BatchMenu>,
+    characters: Vec<PlayerCharacter>,
+    selected_character_id: Option<usize>,
+    classes: ClassLibrary,
+    level_up_windows: Vec<LevelUpMenu>,
+    spells: SpellCompendium,
+    loot: LootLibrary,
+    #[serde(skip)]
+    campaign: CampaignFiles,
+    schema_version: u32,
+    #[serde(skip)]
+    load_error: Option<String>,
+    #[serde(skip)]
+    quarantined: Option<String>,
+    #[serde(skip)]
+    save_error: Option<String>,
+    transfer: TransferMenu,
+    #[serde(skip)]
+    history: History,
+    notes_menu: NotesMenu,
 }
 
 impl Default for DndTool {
     fn default() -> Self {
+        let mut id_next = NextId::new();
+        let places = vec![
+            Place::new(id_next.next(), "Scorched Lands"),
+            Place::new(id_next.next(), "Desolate Lands"),
+            Place::new(id_next.next(), "Hollow"),
+            Place::new(id_next.next(), "Grove"),
+            Place::new(id_next.next(), "Province"),
+        ];
+        let mut names = NameGenerator::default();
+        for list in names.lists.iter_mut() {
+            list.id = id_next.next();
+        }
         Self {
-            places: vec![
-                Place {
-                    name: "Scorched Lands".to_string(),
-                    creatures: vec![],
-                },
-                Place {
-                    name: "Desolate Lands".to_string(),
-                    creatures: vec![],
-                },
-                Place {
-                    name: "Hollow".to_string(),
-                    creatures: vec![],
-                },
-                Place {
-                    name: "Grove".to_string(),
-                    creatures: vec![],
-                },
-                Place {
-                    name: "Province".to_string(),
-                    creatures: vec![],
-                },
-            ],
-            selected_place_index: 1,
-            open_place_windows_indexes: vec![],
+            selected_place_id: places[1].id,
+            places,
+            open_place_windows: vec![],
             open_interface: Interface::DiceRolling,
             creature_creation_windows: vec![],
             dice_windows: vec![],
-            id_next: NextId::new(),
+            id_next,
             notes: vec![],
+            party: Party::default(),
+            encounter_builder_windows: vec![],
+            combat: CombatTracker::default(),
+            connections: vec![],
+            time_of_day: TimeOfDay::Any,
+            weather: Weather::Any,
+            random_tables: TableLibrary::default(),
+            bestiary: vec![],
+            srd_import: SrdImport::default(),
+            names,
+            batch_windows: vec![],
+            characters: vec![],
+            selected_character_id: None,
+            classes: ClassLibrary::default(),
+            level_up_windows: vec![],
+            spells: SpellCompendium::default(),
+            loot: LootLibrary::default(),
+            campaign: CampaignFiles::default(),
+            schema_version: SCHEMA_VERSION,
+            load_error: None,
+            quarantined: None,
+            save_error: None,
+            transfer: TransferMenu::default(),
+            history: History::default(),
+            notes_menu: NotesMenu::default(),
         }
     }
 }
@@ -83,34 +192,677 @@ impl DndTool {
         // Note that you must enable the `persistence` feature for this to work.
         cc.egui_ctx.set_visuals(egui::Visuals::dark());
         if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let mut app = match (storage.get_string(STATE_KEY), storage.get_string(eframe::APP_KEY)) {
+                (Some(state), _) => schema::load::<Self>(&state).unwrap_or_else(|err| Self::quarantined(state, err)),
+                // state from before versioned saves
+                (None, Some(legacy)) => schema::load_ron::<Self>(&legacy).unwrap_or_else(|err| Self::quarantined(legacy, err)),
+                (None, None) => Self::default(),
+            };
+            app.migrate_ids();
+            app.campaign = eframe::get_value(storage, CAMPAIGN_KEY).unwrap_or_default();
+            return app;
         }
 
         Default::default()
     }
+
+    /// A fresh app, holding on to saved state that couldn't be read so it isn't lost. on native
+    /// builds the state is also written to a file in the working directory.
+    fn quarantined(state: String, err: String) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let kept = std::env::current_dir()
+            .map_err(|err| err.to_string())
+            .and_then(|dir| schema::quarantine(&dir, &state))
+            .map(|path| path.display().to_string());
+        #[cfg(target_arch = "wasm32")]
+        let kept: Result<String, String> = Err("there are no files on the web".to_string());
+
+        let kept = match kept {
+            Ok(path) => format!("{}, and in the app's storage under `{}`", path, QUARANTINE_KEY),
+            Err(_) => format!("the app's storage under `{}`", QUARANTINE_KEY),
+        };
+        Self {
+            load_error: Some(format!(
+                "The saved state couldn't be read ({}), so the app started over. It was kept in {}.",
+                err, kept
+            )),
+            quarantined: Some(state),
+            ..Default::default()
+        }
+    }
+
+    /// Runs a File menu action, whether or not that loses unsaved changes. `now` is the time in
+    /// seconds since the app started.
+    fn file_action(&mut self, action: FileAction, now: u64) {
+        match action {
+            FileAction::New => {
+                let campaign = std::mem::take(&mut self.campaign);
+                let mut history = std::mem::take(&mut self.history);
+                history.clear();
+                *self = Self::default();
+                self.history = history;
+                self.campaign = CampaignFiles {
+                    path: None,
+                    message: Some("started a new campaign".to_string()),
+                    unsaved: false,
+                    ..campaign
+                };
+            }
+            FileAction::Open(path) => match campaign::read(&path).and_then(schema::from_value::<Self>) {
+                Ok(mut app) => {
+                    app.migrate_ids();
+                    app.campaign = std::mem::take(&mut self.campaign);
+                    app.history = std::mem::take(&mut self.history);
+                    app.history.clear();
+                    *self = app;
+                    self.campaign.remember(path.trim());
+                    self.campaign.last_saved = Some(now);
+                    self.campaign.message = Some(format!("opened {}", path.trim()));
+                    self.campaign.unsaved = false;
+                }
+                Err(err) => self.campaign.message = Some(format!("error: {}", err)),
+            },
+            FileAction::Save => match self.campaign.path.clone() {
+                Some(path) => self.save_campaign(&path, now),
+                None => self.campaign.message = Some("error: use Save As to pick a file first".to_string()),
+            },
+            FileAction::SaveAs(path) => self.save_campaign(path.trim(), now),
+            FileAction::Export => match self.transfer.format.write(self) {
+                Ok(text) => {
+                    self.transfer.text = text;
+                    self.transfer.messages = vec!["exported the whole campaign".to_string()];
+                }
+                Err(err) => self.transfer.messages = vec![format!("error: {}", err)],
+            },
+            FileAction::Replace => match self.transfer.format.read::<Self>(&self.transfer.text) {
+                Ok(mut app) => {
+                    app.migrate_ids();
+                    app.campaign = std::mem::take(&mut self.campaign);
+                    app.transfer = TransferMenu {
+                        text: std::mem::take(&mut self.transfer.text),
+                        messages: vec!["replaced the campaign".to_string()],
+                        ..self.transfer.clone()
+                    };
+                    app.history = std::mem::take(&mut self.history);
+                    app.history.clear();
+                    *self = app;
+                    // the campaign file still has what was replaced
+                    self.campaign.unsaved = true;
+                }
+                Err(err) => self.transfer.messages = vec![format!("error: {}", err)],
+            },
+        }
+    }
+
+    /// The fields undo covers: the campaign, without which windows are open, what is selected,
+    /// or the spells, classes, loot and names that come from files.
+    fn tracked_state(&self) -> State {
+        let fields = [
+            ("places", serde_json::to_value(&self.places)),
+            ("creature_creation_windows", serde_json::to_value(&self.creature_creation_windows)),
+            ("dice_windows", serde_json::to_value(&self.dice_windows)),
+            ("id_next", serde_json::to_value(&self.id_next)),
+            ("notes", serde_json::to_value(&self.notes)),
+            ("party", serde_json::to_value(&self.party)),
+            ("encounter_builder_windows", serde_json::to_value(&self.encounter_builder_windows)),
+            ("combat", serde_json::to_value(&self.combat)),
+            ("connections", serde_json::to_value(&self.connections)),
+            ("time_of_day", serde_json::to_value(self.time_of_day)),
+            ("weather", serde_json::to_value(self.weather)),
+            ("random_tables", serde_json::to_value(&self.random_tables)),
+            ("bestiary", serde_json::to_value(&self.bestiary)),
+            ("batch_windows", serde_json::to_value(&self.batch_windows)),
+            ("characters", serde_json::to_value(&self.characters)),
+            ("level_up_windows", serde_json::to_value(&self.level_up_windows)),
+        ];
+        fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.ok()?)))
+            .collect()
+    }
+
+    /// Puts back the fields undo covers, leaving the rest as it is. nothing changes if the state
+    /// can't be read.
+    fn restore(&mut self, state: State) -> Result<(), String> {
+        let mut full = match serde_json::to_value(&*self) {
+            Ok(serde_json::Value::Object(full)) => full,
+            Ok(_) => State::new(),
+            Err(err) => return Err(err.to_string()),
+        };
+        full.extend(state);
+        let mut app = serde_json::from_value::<Self>(serde_json::Value::Object(full)).map_err(|err| err.to_string())?;
+        app.campaign = std::mem::take(&mut self.campaign);
+        app.history = std::mem::take(&mut self.history);
+        app.load_error = self.load_error.take();
+        app.quarantined = self.quarantined.take();
+        app.save_error = self.save_error.take();
+        *self = app;
+        Ok(())
+    }
+
+    /// Undoes or redoes until `steps` commands are done. when that fails the app and the history
+    /// stay as they are, and the history window says why.
+    fn go_to(&mut self, steps: usize) {
+        let result = self.history.state_at(steps).and_then(|state| match state {
+            Some(state) => self.restore(state.clone()).map(|()| Some(state)),
+            None => Ok(None),
+        });
+        match result {
+            Ok(Some(state)) => {
+                self.history.moved_to(steps, state);
+                self.campaign.unsaved = true;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                self.history.error = Some(format!("couldn't go to that change: {}", err));
+                self.history.open = true;
+            }
+        }
+    }
+
+    fn save_campaign(&mut self, path: &str, now: u64) {
+        match campaign::write(path, self) {
+            Ok(()) => {
+                self.campaign.remember(path);
+                self.campaign.last_saved = Some(now);
+                self.campaign.message = Some(format!("saved {}", path));
+                self.campaign.unsaved = false;
+            }
+            Err(err) => self.campaign.message = Some(format!("error: {}", err)),
+        }
+    }
+
+    /// Saves from before places and creatures had ids addressed them by index. this gives
+    /// everything without an id one, and turns the old indexes into ids.
+    fn migrate_ids(&mut self) {
+        let legacy = self.places.iter().any(|place| place.id == 0);
+
+        for list in self.names.lists.iter_mut() {
+            if list.id == 0 {
+                list.id = self.id_next.next();
+            }
+        }
+        for template in self.bestiary.iter_mut() {
+            if template.id == 0 {
+                template.id = self.id_next.next();
+            }
+        }
+        for place in self.places.iter_mut() {
+            if place.id == 0 {
+                place.id = self.id_next.next();
+            }
+            for creature in place.creatures.iter_mut() {
+                if creature.id == 0 {
+                    creature.id = self.id_next.next();
+                }
+            }
+        }
+
+        if !legacy {
+            return;
+        }
+        let places = &self.places;
+        let place_id = |index: usize| places.get(index).map_or(0, |place| place.id);
+
+        self.selected_place_id = place_id(self.selected_place_id);
+        self.open_place_windows = self
+            .open_place_windows
+            .iter()
+            .map(|index| place_id(*index))
+            .collect();
+        self.combat.selected_place_id = place_id(self.combat.selected_place_id);
+        for window in self.encounter_builder_windows.iter_mut() {
+            window.place_id = place_id(window.place_id);
+        }
+        for window in self.creature_creation_windows.iter_mut() {
+            window.place_id = place_id(window.place_id);
+            window.editing_id = window
+                .legacy_editing
+                .take()
+                .and_then(|(place, creature)| places.get(place)?.creatures.get(creature))
+                .map(|creature| creature.id);
+        }
+    }
+}
+
+/// Draws the places inside of `parent_id` as a tree. clicking a place selects it, double clicking
+/// opens its window.
+fn place_tree(
+    ui: &mut egui::Ui,
+    places: &[Place],
+    parent_id: Option<usize>,
+    selected_id: &mut usize,
+    open_place_windows: &mut Vec<usize>,
+) {
+    // places whose parent is gone show up at the top level
+    let parent_of = |place: &Place| place.parent_id.filter(|id| find_place(places, *id).is_some());
+    for place in places.iter().filter(|place| parent_of(place) == parent_id) {
+        let mut label = |ui: &mut egui::Ui| {
+            let response = ui.selectable_label(
+                *selected_id == place.id,
+                format!("{} ({:?})", place.name, place.kind),
+            );
+            if response.clicked() {
+                *selected_id = place.id;
+            }
+            if response.double_clicked() {
+                open_place_windows.push(place.id);
+            }
+        };
+        if places.iter().any(|child| parent_of(child) == Some(place.id)) {
+            egui::collapsing_header::CollapsingState::load_with_default_open(
+                ui.ctx(),
+                Id::new(format!("{}tree", place.id)),
+                true,
+            )
+            .show_header(ui, |ui| label(ui))
+            .body(|ui| place_tree(ui, places, Some(place.id), selected_id, open_place_windows));
+        } else {
+            label(ui);
+        }
+    }
+}
+
+/// A menu for copying `name` as a stat block, or saving it to a file in the working directory.
+/// `render` lays the stat block out in a format.
+fn export_menu(ui: &mut egui::Ui, id: Id, name: &str, render: impl Fn(ExportFormat) -> String) {
+    ui.menu_button("export", |ui| {
+        for format in ExportFormat::ALL {
+            ui.horizontal(|ui| {
+                ui.label(format!("{:?}:", format));
+                if ui.small_button("copy").clicked() {
+                    ui.output().copied_text = render(format);
+                    ui.data().insert_temp(id, format!("copied {:?}", format));
+                }
+                if ui.small_button("save").clicked() {
+                    let file_name = stat_block::file_name(name, format);
+                    let message = match std::fs::write(&file_name, render(format)) {
+                        Ok(()) => format!("saved {}", file_name),
+                        Err(err) => format!("error: {}", err),
+                    };
+                    ui.data().insert_temp(id, message);
+                }
+            });
+        }
+        let message = ui.data().get_temp::<String>(id);
+        if let Some(message) = message {
+            ui.label(message);
+        }
+    });
+}
+
+/// A combo box for picking a place by id. picks the first place if the selected one is gone.
+fn place_combo_box(ui: &mut egui::Ui, label: &str, places: &[Place], selected_id: &mut usize) {
+    if find_place(places, *selected_id).is_none() {
+        if let Some(place) = places.first() {
+            *selected_id = place.id;
+        }
+    }
+    egui::ComboBox::from_label(label)
+        .selected_text(find_place(places, *selected_id).map_or("", |place| &place.name))
+        .show_ui(ui, |ui| {
+            for place in places {
+                ui.selectable_value(selected_id, place.id, place.name.clone());
+            }
+        });
+}
+
+/// A button for removals that asks again before going ahead.
+///
+/// Returns:
+///
+/// Whether the removal was confirmed.
+fn confirm_button(ui: &mut egui::Ui, id: Id, text: &str) -> bool {
+    if !ui.data().get_temp::<bool>(id).unwrap_or(false) {
+        if ui.button(text).clicked() {
+            ui.data().insert_temp(id, true);
+        }
+        return false;
+    }
+    ui.label(format!("{}?", text));
+    let confirmed = ui.button("yes").clicked();
+    if confirmed || ui.button("no").clicked() {
+        ui.data().insert_temp(id, false);
+    }
+    confirmed
+}
+
+/// Draws the markdown of a note.
+///
+/// Returns:
+///
+/// The place or creature of the `[[Name]]` link that was clicked.
+fn markdown_view(ui: &mut egui::Ui, text: &str, places: &[Place]) -> Option<LinkTarget> {
+    let mut followed = None;
+    for block in notes::parse_markdown(text) {
+        let (prefix, spans, size) = match block {
+            Block::Gap => {
+                ui.add_space(6.0);
+                continue;
+            }
+            Block::Rule => {
+                ui.separator();
+                continue;
+            }
+            Block::Heading(level, spans) => (String::new(), spans, Some(26.0 - 3.0 * level as f32)),
+            Block::Bullet(spans) => ("• ".to_string(), spans, None),
+            Block::Numbered(number, spans) => (format!("{}. ", number), spans, None),
+            Block::Quote(spans) => ("▌ ".to_string(), spans, None),
+            Block::Paragraph(spans) => (String::new(), spans, None),
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            if !prefix.is_empty() {
+                ui.label(egui::RichText::new(prefix).weak());
+            }
+            for Span { text, style } in spans {
+                let mut rich = egui::RichText::new(&text);
+                if let Some(size) = size {
+                    rich = rich.size(size).strong();
+                }
+                match style {
+                    SpanStyle::Plain => {
+                        ui.label(rich);
+                    }
+                    SpanStyle::Bold => {
+                        ui.label(rich.strong());
+                    }
+                    SpanStyle::Italic => {
+                        ui.label(rich.italics());
+                    }
+                    SpanStyle::Code => {
+                        ui.label(rich.code());
+                    }
+                    SpanStyle::Link => match notes::resolve(places, &text) {
+                        Some(target) => {
+                            if ui.link(rich).clicked() {
+                                followed = Some(target);
+                            }
+                        }
+                        None => {
+                            ui.label(rich.weak())
+                                .on_hover_text(format!("there is no place or creature called {}", text));
+                        }
+                    },
+                }
+            }
+        });
+    }
+    followed
+}
+
+/// Lists the notes that link to `name`. clicking one puts it in `open_note`.
+fn backlinks_view(ui: &mut egui::Ui, id: Id, notes: &[Note], name: &str, open_note: &mut Option<usize>) {
+    let linking = notes::backlinks(notes, name);
+    if linking.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new(format!("Notes ({})", linking.len()))
+        .id_source(id)
+        .show(ui, |ui| {
+            for note in linking {
+                if ui.link(note.name()).clicked() {
+                    *open_note = Some(note.id);
+                }
+            }
+        });
+}
+
+/// Lists the spells in `spellbook`, with their details on hover, and a menu for adding spells from
+/// the compendium.
+fn spellbook_editor(ui: &mut egui::Ui, spellbook: &mut Vec<String>, compendium: &SpellCompendium) {
+    let mut spells_to_remove: Vec<usize> = vec![];
+    for (i, name) in spellbook.iter().enumerate() {
+        ui.horizontal(|ui| {
+            match compendium.get(name) {
+                Some(spell) => ui
+                    .label(format!("{} ({})", spell.name, spell.level_label()))
+                    .on_hover_text(spell.details()),
+                None => ui.label(format!("{} (not in the compendium)", name)),
+            };
+            if ui.button("remove").clicked() {
+                spells_to_remove.push(i);
+            }
+        });
+    }
+    for i in spells_to_remove.into_iter().rev() {
+        spellbook.remove(i);
+    }
+    ui.menu_button("add spell", |ui| {
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            let missing: Vec<_> = compendium
+                .spells
+                .iter()
+                .filter(|spell| !spellbook.contains(&spell.name))
+                .collect();
+            for spell in missing {
+                let button = ui.button(format!("{} ({})", spell.name, spell.level_label()));
+                if button.on_hover_text(spell.details()).clicked() {
+                    spellbook.push(spell.name.clone());
+                    ui.close_menu();
+                }
+            }
+        });
+    });
+}
+
+/// Edits the coins and items of an inventory. `carrier` is the strength and size of whoever
+/// carries it, for encumbrance. `id` keeps the messages of different inventories apart.
+fn inventory_editor(ui: &mut egui::Ui, id: Id, inventory: &mut Inventory, carrier: Option<(i32, &Size)>) {
+    let coins = &mut inventory.coins;
+    ui.horizontal(|ui| {
+        for (coin, name) in [
+            (&mut coins.pp, " pp"),
+            (&mut coins.gp, " gp"),
+            (&mut coins.ep, " ep"),
+            (&mut coins.sp, " sp"),
+            (&mut coins.cp, " cp"),
+        ] {
+            ui.add(egui::DragValue::new(coin).suffix(name));
+        }
+        if ui.button("consolidate").clicked() {
+            coins.consolidate();
+        }
+    });
+    ui.horizontal(|ui| {
+        let spend_id = id.with("spend");
+        let mut cost = ui.data().get_temp::<Currency>(spend_id).unwrap_or_default();
+        for (coin, name) in [(&mut cost.gp, " gp"), (&mut cost.sp, " sp"), (&mut cost.cp, " cp")] {
+            ui.add(egui::DragValue::new(coin).suffix(name));
+        }
+        if ui.button("spend").clicked() {
+            let message = match coins.spend(cost.total_cp()) {
+                Ok(()) => format!("spent {}", cost),
+                Err(err) => format!("error: {}", err),
+            };
+            ui.data().insert_temp(id, message);
+        }
+        ui.data().insert_temp(spend_id, cost);
+    });
+
+    let weight = format!("{:.1} lb", inventory.total_weight() as f64 / 10.0);
+    match carrier {
+        Some((strength, size)) => ui.label(format!(
+            "Worth {}, weighs {}, {:?}, {}/{} attuned",
+            inventory.total_value(),
+            weight,
+            inventory.encumbrance(strength, size),
+            inventory.attuned(),
+            MAX_ATTUNED
+        )),
+        None => ui.label(format!("Worth {}, weighs {}", inventory.total_value(), weight)),
+    };
+
+    let mut items_to_remove: Vec<usize> = vec![];
+    let mut to_attune: Option<usize> = None;
+    for (i, item) in inventory.items.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(format!("{} x{}", item.name, item.quantity))
+            .id_source(id.with(i))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut item.name);
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut item.quantity).prefix("x"));
+                    let mut pounds = item.weight as f64 / 10.0;
+                    if ui.add(egui::DragValue::new(&mut pounds).speed(0.1).suffix(" lb each")).changed() {
+                        item.weight = (pounds.max(0.0) * 10.0).round() as u32;
+                    }
+                    ui.label("worth");
+                    for (coin, name) in [(&mut item.value.gp, " gp"), (&mut item.value.sp, " sp"), (&mut item.value.cp, " cp")] {
+                        ui.add(egui::DragValue::new(coin).suffix(name));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source(id.with(("rarity", i)))
+                        .selected_text(format!("{:?}", item.rarity))
+                        .show_ui(ui, |ui| {
+                            for rarity in Rarity::ALL {
+                                ui.selectable_value(&mut item.rarity, rarity, format!("{:?}", rarity));
+                            }
+                        });
+                    ui.checkbox(&mut item.requires_attunement, "requires attunement");
+                    if item.requires_attunement {
+                        if item.attuned {
+                            if ui.button("end attunement").clicked() {
+                                item.attuned = false;
+                            }
+                        } else if ui.button("attune").clicked() {
+                            to_attune = Some(i);
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Charges:");
+                    ui.add(egui::DragValue::new(&mut item.charges).clamp_range(0..=item.max_charges));
+                    ui.add(egui::DragValue::new(&mut item.max_charges).prefix("of "));
+                    ui.add(egui::TextEdit::singleline(&mut item.recharge).desired_width(60.0).hint_text("1d6+1"));
+                    if item.max_charges > 0 && ui.button("recharge").clicked() {
+                        let message = match item.recharge() {
+                            Ok(regained) => format!("{} regained {} charges", item.name, regained),
+                            Err(err) => format!("error: {}", err),
+                        };
+                        ui.data().insert_temp(id, message);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Effect:");
+                    ui.add(egui::TextEdit::singleline(&mut item.effect).desired_width(60.0).hint_text("2d4+2"));
+                    if ui.button("use").clicked() {
+                        let message = match item.use_item() {
+                            Ok(used) => used,
+                            Err(err) => format!("error: {}", err),
+                        };
+                        ui.data().insert_temp(id, message);
+                    }
+                });
+                ui.text_edit_multiline(&mut item.description);
+                if ui.button("remove").clicked() {
+                    items_to_remove.push(i);
+                }
+            });
+    }
+    if let Some(i) = to_attune {
+        if let Err(err) = inventory.attune(i) {
+            ui.data().insert_temp(id, format!("error: {}", err));
+        }
+    }
+    for i in items_to_remove.into_iter().rev() {
+        inventory.items.remove(i);
+    }
+    if ui.button("add item").clicked() {
+        inventory.items.push(Item::new("New item"));
+    }
+
+    let message = ui.data().get_temp::<String>(id);
+    if let Some(message) = message {
+        ui.label(message);
+    }
 }
 
 impl eframe::App for DndTool {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        match serde_json::to_string(self) {
+            Ok(state) => {
+                storage.set_string(STATE_KEY, state);
+                self.save_error = None;
+            }
+            Err(err) => self.save_error = Some(format!("The app state couldn't be saved: {}", err)),
+        }
+        if let Some(quarantined) = &self.quarantined {
+            storage.set_string(QUARANTINE_KEY, quarantined.clone());
+        }
+        eframe::set_value(storage, CAMPAIGN_KEY, &self.campaign);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // text fields have undo of their own
+        if ctx.memory().focus().is_none() {
+            let redo = egui::Modifiers {
+                shift: true,
+                ..egui::Modifiers::COMMAND
+            };
+            let mut input = ctx.input_mut();
+            let done = self.history.undo.len();
+            let steps = if input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z) {
+                done.checked_sub(1)
+            } else if input.consume_key(egui::Modifiers::COMMAND, egui::Key::Y) || input.consume_key(redo, egui::Key::Z) {
+                Some(done + 1)
+            } else {
+                None
+            };
+            drop(input);
+            if let Some(steps) = steps {
+                self.go_to(steps);
+            }
+        }
+
         let Self {
             places,
-            selected_place_index,
-            open_place_windows_indexes,
+            selected_place_id,
+            open_place_windows,
             open_interface,
             dice_windows,
             id_next,
             notes,
             creature_creation_windows,
+            party,
+            encounter_builder_windows,
+            combat,
+            connections,
+            time_of_day,
+            weather,
+            random_tables,
+            bestiary,
+            srd_import,
+            names,
+            batch_windows,
+            characters,
+            selected_character_id,
+            classes,
+            level_up_windows,
+            spells: compendium,
+            loot,
+            campaign,
+            load_error,
+            save_error,
+            transfer,
+            history,
+            notes_menu,
             ..
         } = self;
 
+        let mut file_action: Option<FileAction> = None;
+        // how many changes should be done once the frame is drawn, from the Edit menu or history
+        let mut history_step: Option<usize> = None;
+        // a note to open in the notes interface, and a place or creature a note links to
+        let mut open_note: Option<usize> = None;
+        let mut followed_link: Option<LinkTarget> = None;
+
         // Examples of how to create different panels and windows.
         // Pick whichever suits you.
         // Tip: a good default choice is to just keep the `CentralPanel`.
@@ -120,10 +872,73 @@ impl eframe::App for DndTool {
             // The top panel is often a good place for a menu bar:
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    if ui.button("New").clicked() {
+                        file_action = Some(FileAction::New);
+                        ui.close_menu();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("File:");
+                        ui.add(egui::TextEdit::singleline(&mut campaign.path_input).hint_text("campaign.json"));
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Open").clicked() {
+                            file_action = Some(FileAction::Open(campaign.path_input.clone()));
+                        }
+                        if ui.add_enabled(campaign.path.is_some(), egui::Button::new("Save")).clicked() {
+                            file_action = Some(FileAction::Save);
+                        }
+                        if ui.button("Save As").clicked() {
+                            file_action = Some(FileAction::SaveAs(campaign.path_input.clone()));
+                        }
+                    });
+                    ui.menu_button("Open recent", |ui| {
+                        if campaign.recent.is_empty() {
+                            ui.label("no recent files");
+                        }
+                        for path in campaign.recent.iter() {
+                            if ui.button(path).clicked() {
+                                file_action = Some(FileAction::Open(path.clone()));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Autosave every");
+                        ui.add(egui::DragValue::new(&mut campaign.autosave_minutes).clamp_range(0..=120).suffix(" min"))
+                            .on_hover_text("0 turns autosave off");
+                    });
+                    if let Some(message) = &campaign.message {
+                        ui.label(message);
+                    }
+                    ui.separator();
+                    if ui.button("Export / import").clicked() {
+                        transfer.open = true;
+                        ui.close_menu();
+                    }
                     if ui.button("Quit").clicked() {
                         frame.close();
                     }
                 });
+                ui.menu_button("Edit", |ui| {
+                    let undo = match history.undo.last() {
+                        Some(command) => format!("Undo {}", command.label),
+                        None => "Undo".to_string(),
+                    };
+                    if ui.add_enabled(!history.undo.is_empty(), egui::Button::new(undo)).on_hover_text("Ctrl+Z").clicked() {
+                        history_step = Some(history.undo.len() - 1);
+                    }
+                    let redo = match history.redo.last() {
+                        Some(command) => format!("Redo {}", command.label),
+                        None => "Redo".to_string(),
+                    };
+                    if ui.add_enabled(!history.redo.is_empty(), egui::Button::new(redo)).on_hover_text("Ctrl+Y").clicked() {
+                        history_step = Some(history.undo.len() + 1);
+                    }
+                    if ui.button("History").clicked() {
+                        history.open = true;
+                        ui.close_menu();
+                    }
+                });
                 ui.menu_button("Interface", |ui| {
                     if ui.button("Dice").clicked() {
                         *open_interface = Interface::DiceRolling;
@@ -131,10 +946,227 @@ impl eframe::App for DndTool {
                     if ui.button("Creature creation").clicked() {
                         *open_interface = Interface::CreatureCreation;
                     }
+                    if ui.button("Combat").clicked() {
+                        *open_interface = Interface::Combat;
+                    }
+                    if ui.button("Random tables").clicked() {
+                        *open_interface = Interface::RandomTables;
+                    }
+                    if ui.button("Characters").clicked() {
+                        *open_interface = Interface::Characters;
+                    }
+                    if ui.button("Notes").clicked() {
+                        *open_interface = Interface::Notes;
+                    }
                 });
+                ui.separator();
+                ui.label(campaign.title())
+                    .on_hover_text(if campaign.unsaved { "has unsaved changes" } else { "saved" });
             });
         });
 
+        let mut dismissed = false;
+        if let Some(err) = load_error {
+            egui::Window::new("Saved state could not be read").show(ctx, |ui| {
+                ui.label(err.as_str());
+                if ui.button("dismiss").clicked() {
+                    dismissed = true;
+                }
+            });
+        }
+        if dismissed {
+            *load_error = None;
+        }
+
+        // New, Open and Replace wait here while there are unsaved changes
+        let mut discard = None;
+        if campaign.pending.is_some() {
+            egui::Window::new("Discard unsaved changes?").collapsible(false).show(ctx, |ui| {
+                ui.label(format!("{} has changes that haven't been saved, and they will be lost.", campaign.name()));
+                ui.horizontal(|ui| {
+                    if ui.button("discard changes").clicked() {
+                        discard = Some(true);
+                    }
+                    if ui.button("cancel").clicked() {
+                        discard = Some(false);
+                    }
+                });
+            });
+        }
+        let confirmed = match discard {
+            Some(true) => campaign.pending.take(),
+            Some(false) => {
+                campaign.pending = None;
+                None
+            }
+            None => None,
+        };
+
+        let mut dismissed = false;
+        if let Some(err) = save_error {
+            egui::Window::new("App state could not be saved").show(ctx, |ui| {
+                ui.label(err.as_str());
+                if ui.button("dismiss").clicked() {
+                    dismissed = true;
+                }
+            });
+        }
+        if dismissed {
+            *save_error = None;
+        }
+
+        let mut history_open = history.open;
+        egui::Window::new("History")
+            .open(&mut history_open)
+            .show(ctx, |ui| {
+                if let Some(err) = &history.error {
+                    ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                }
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    if ui.selectable_label(history.undo.is_empty(), "start of the session").clicked() {
+                        history_step = Some(0);
+                    }
+                    let done = history.undo.len();
+                    for (i, command) in history.undo.iter().enumerate() {
+                        if ui.selectable_label(i + 1 == done, &command.label).clicked() {
+                            history_step = Some(i + 1);
+                        }
+                    }
+                    for (i, command) in history.redo.iter().rev().enumerate() {
+                        let label = egui::RichText::new(&command.label).weak();
+                        if ui.selectable_label(false, label).on_hover_text("undone").clicked() {
+                            history_step = Some(done + i + 1);
+                        }
+                    }
+                });
+            });
+        history.open = history_open;
+
+        let mut transfer_open = transfer.open;
+        egui::Window::new("Export / import")
+            .open(&mut transfer_open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for format in DocumentFormat::ALL {
+                        ui.selectable_value(&mut transfer.format, format, format.extension());
+                    }
+                });
+
+                ui.checkbox(&mut transfer.whole_campaign, "the whole campaign");
+                if !transfer.whole_campaign {
+                    let toggle = |ui: &mut egui::Ui, ids: &mut Vec<usize>, id: usize, label: String| {
+                        let mut selected = ids.contains(&id);
+                        if ui.checkbox(&mut selected, label).changed() {
+                            if selected {
+                                ids.push(id);
+                            } else {
+                                ids.retain(|other| *other != id);
+                            }
+                        }
+                    };
+                    ui.collapsing("Places, with the places inside them", |ui| {
+                        for place in places.iter() {
+                            toggle(ui, &mut transfer.place_ids, place.id, place_path(places, place.id));
+                        }
+                    });
+                    ui.collapsing("Creature templates", |ui| {
+                        for template in bestiary.iter() {
+                            toggle(ui, &mut transfer.template_ids, template.id, template.name.clone());
+                        }
+                    });
+                    ui.collapsing("Dice rolls", |ui| {
+                        for dice in dice_windows.iter() {
+                            let label = format!("{} {}", dice.raw_formula, dice.note);
+                            toggle(ui, &mut transfer.dice_ids, dice.id, label);
+                        }
+                    });
+                    ui.collapsing("Notes", |ui| {
+                        for note in notes.iter() {
+                            toggle(ui, &mut transfer.note_ids, note.id, note.name());
+                        }
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("export").clicked() {
+                        if transfer.whole_campaign {
+                            file_action = Some(FileAction::Export);
+                        } else {
+                            let document = CampaignDocument::select(places, connections, bestiary, dice_windows, notes, transfer);
+                            match transfer.format.write(&document) {
+                                Ok(text) => {
+                                    transfer.text = text;
+                                    transfer.messages = vec![format!(
+                                        "exported {} places, {} templates, {} dice rolls and {} notes",
+                                        document.places.len(),
+                                        document.bestiary.len(),
+                                        document.dice_windows.len(),
+                                        document.notes.len()
+                                    )];
+                                }
+                                Err(err) => transfer.messages = vec![format!("error: {}", err)],
+                            }
+                        }
+                    }
+                    ui.separator();
+                    ui.selectable_value(&mut transfer.mode, ImportMode::Merge, "merge")
+                        .on_hover_text("things with ids that are taken get new ids");
+                    ui.selectable_value(&mut transfer.mode, ImportMode::Overwrite, "overwrite")
+                        .on_hover_text("things with ids that are taken replace what is there");
+                    if ui.button("import").clicked() {
+                        history.label("import");
+                        transfer.messages = match transfer.read_document() {
+                            Ok(document) => vec![document.import(
+                                CampaignParts {
+                                    places,
+                                    connections,
+                                    bestiary,
+                                    dice_windows,
+                                    notes,
+                                },
+                                transfer.mode,
+                                id_next,
+                            )],
+                            Err(errors) => errors.into_iter().map(|err| format!("error: {}", err)).collect(),
+                        };
+                    }
+                    if ui.button("replace the campaign").clicked() {
+                        file_action = Some(FileAction::Replace);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.add(egui::TextEdit::singleline(&mut transfer.path).hint_text("campaign.yaml"));
+                    if ui.button("read").clicked() {
+                        match std::fs::read_to_string(transfer.path.trim()) {
+                            Ok(text) => {
+                                if let Some(format) = DocumentFormat::of_path(&transfer.path) {
+                                    transfer.format = format;
+                                }
+                                transfer.text = text;
+                                transfer.messages = vec![format!("read {}", transfer.path.trim())];
+                            }
+                            Err(err) => transfer.messages = vec![format!("error: {}: {}", transfer.path.trim(), err)],
+                        }
+                    }
+                    if ui.button("write").clicked() {
+                        transfer.messages = match campaign::write_text(&transfer.path, &transfer.text) {
+                            Ok(()) => vec![format!("wrote {}", transfer.path.trim())],
+                            Err(err) => vec![format!("error: {}", err)],
+                        };
+                    }
+                });
+
+                for message in transfer.messages.iter() {
+                    ui.label(message);
+                }
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut transfer.text).code_editor().desired_width(f32::INFINITY));
+                });
+            });
+        transfer.open = transfer_open;
+
         match open_interface {
             /// Render the DiceRolling interface
             Interface::DiceRolling => {
@@ -175,14 +1207,16 @@ impl eframe::App for DndTool {
                             id: id_next.next(),
                             note: String::new(),
                             rolls: vec![],
+                            roll_error: None,
                             sort: false,
                         });
                     }
 
-                    // if ui.button("create note").clicked() {
-                    //     notes.push(Note { amount: 1, size: 20, id: *dice_id_next, dice_results: vec![] });
-                    //     *dice_id_next += 1;
-                    // }
+                    if ui.button("create note").clicked() {
+                        let note = Note::new(id_next.next(), "New note");
+                        open_note = Some(note.id);
+                        notes.push(note);
+                    }
 
                     // ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
                     //     ui.horizontal(|ui| {
@@ -201,8 +1235,8 @@ impl eframe::App for DndTool {
                     ui.heading("DnD tools");
                     egui::warn_if_debug_build(ui);
                 });
-                open_place_windows_indexes.sort();
-                open_place_windows_indexes.dedup();
+                open_place_windows.sort();
+                open_place_windows.dedup();
 
                 let mut dice_windows_to_remove = vec![];
                 for (i, dice_window) in dice_windows.into_iter().enumerate() {
@@ -213,6 +1247,7 @@ impl eframe::App for DndTool {
                     .id(Id::new(format!("{}dice", &dice_window.id)))
                     .show(ctx, |ui| {
                         if ui.button("close window").clicked() {
+                            history.label(format!("close roll {}", dice_window.raw_formula));
                             dice_windows_to_remove.push(i);
                         }
 
@@ -235,24 +1270,26 @@ impl eframe::App for DndTool {
                             }
                         }
 
+                        if let Some(err) = &dice_window.roll_error {
+                            ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                        }
+
                         ui.checkbox(&mut dice_window.sort, "sort list");
 
                         if ui.button("Roll dice").clicked() {
-                            let mut dice_results = vec![];
-                            for _ in 0..dice_window.amount {
-                                dice_results.push(
-                                    if let Some(f) = &dice_window.formula {
-                                        if let Ok(formula) = f {
-                                            formula.evaluate()
-                                        } else {
-                                            0
-                                        }
-                                    } else {
-                                        0
+                            if let Some(Ok(formula)) = &dice_window.formula {
+                                let dice_results: Result<Vec<i64>, String> =
+                                    (0..dice_window.amount).map(|_| formula.evaluate()).collect();
+                                match dice_results {
+                                    Ok(dice_results) => {
+                                        dice_window.rolls.push(dice_results);
+                                        dice_window.roll_error = None;
                                     }
-                                );
+                                    Err(err) => dice_window.roll_error = Some(err),
+                                }
+                            } else {
+                                dice_window.rolls.push(vec![0; dice_window.amount]);
                             }
-                            dice_window.rolls.push(dice_results);
                         }
 
                         egui::ScrollArea::vertical().show(ui, |ui| {
@@ -307,44 +1344,222 @@ impl eframe::App for DndTool {
                 egui::SidePanel::left("side_panel").show(ctx, |ui| {
                     // TODO: put anything here
 
-                    egui::ComboBox::from_label("place:")
-                        .selected_text(format!("{}", places[*selected_place_index].name))
-                        .show_ui(ui, |ui| {
-                            for place_index in 0..places.len() {
-                                ui.selectable_value(
-                                    selected_place_index,
-                                    place_index.clone(),
-                                    places[place_index].name.clone(),
-                                );
+                    ui.label("Places:");
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        place_tree(ui, places, None, selected_place_id, open_place_windows);
+                    });
+
+                    ui.horizontal(|ui| {
+                        if ui.button("add place").clicked() {
+                            let place = Place::new(id_next.next(), "New place");
+                            *selected_place_id = place.id;
+                            places.push(place);
+                        }
+                        if ui.button("add place inside").clicked() {
+                            let mut place = Place::new(id_next.next(), "New place");
+                            place.parent_id = Some(*selected_place_id);
+                            *selected_place_id = place.id;
+                            places.push(place);
+                        }
+                        if places.len() > 1 && confirm_button(ui, Id::new(format!("{}delete", selected_place_id)), "delete place") {
+                            if let Some(place) = find_place(places, *selected_place_id) {
+                                history.label(format!("delete {}", place.name));
                             }
-                        });
+                            let parent_id = find_place(places, *selected_place_id)
+                                .and_then(|place| place.parent_id);
+                            remove_place(places, connections, *selected_place_id);
+                            *selected_place_id = parent_id.unwrap_or(places[0].id);
+                        }
+                    });
 
                     if ui.button("open place window").clicked() {
-                        open_place_windows_indexes.push(*selected_place_index)
+                        open_place_windows.push(*selected_place_id)
                     }
 
-                    if ui.button("open creature window").clicked() {
-                        creature_creation_windows.push(CreatureMenu {
-                            inner: Default::default(),
-                            id: id_next.next(),
-                            max_value: 100,
-                            selected_place_index: *selected_place_index,
-                            editing: None,
+                    // character sheets in the party take over from the size and level
+                    let in_party = characters.iter().filter(|character| character.in_party).count();
+                    if in_party > 0 {
+                        ui.label(format!("Party: {} characters from their sheets", in_party));
+                    } else {
+                        ui.horizontal(|ui| {
+                            ui.label("Party:");
+                            ui.add(egui::DragValue::new(&mut party.size).clamp_range(1..=10));
+                            ui.label("characters of level");
+                            ui.add(egui::DragValue::new(&mut party.level).clamp_range(1..=20));
                         });
                     }
 
-                    ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                        ui.horizontal(|ui| {
-                            ui.spacing_mut().item_spacing.x = 0.0;
-                            ui.label("powered by ");
-                            ui.hyperlink_to("egui", "https://github.com/emilk/egui");
-                            ui.label(" and ");
-                            ui.hyperlink_to(
-                                "eframe",
-                                "https://github.com/emilk/egui/tree/master/eframe",
-                            );
+                    if ui.button("open encounter builder").clicked() {
+                        encounter_builder_windows.push(EncounterBuilderMenu {
+                            id: id_next.next(),
+                            place_id: *selected_place_id,
+                            target: Difficulty::Medium,
+                            max_creatures: 8,
+                            slots: vec![],
                         });
-                    });
+                    }
+
+                    if ui.button("open creature window").clicked() {
+                        creature_creation_windows
+                            .push(CreatureMenu::new(id_next.next(), *selected_place_id));
+                    }
+
+                    ui.collapsing("Bestiary", |ui| {
+                        let mut templates_to_remove: Vec<usize> = vec![];
+                        for (i, template) in bestiary.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}: {}", template.name, template.challenge_rating().overall));
+                                if ui.small_button("edit").clicked() {
+                                    creature_creation_windows.push(CreatureMenu::editing(
+                                        id_next.next(),
+                                        *selected_place_id,
+                                        template.clone(),
+                                    ));
+                                }
+                                if ui.small_button("place").clicked() {
+                                    if let Some(place) = find_place_mut(places, *selected_place_id) {
+                                        place.creatures.push(template.instantiate(id_next.next()));
+                                    }
+                                }
+                                if ui.small_button("update instances").clicked() {
+                                    update_instances(places, template);
+                                }
+                                export_menu(ui, Id::new(format!("{}export", template.id)), &template.name, |format| {
+                                    stat_block::creature_block(template, format)
+                                });
+                                if ui.small_button("delete").clicked() {
+                                    templates_to_remove.push(i);
+                                }
+                            });
+                        }
+                        // instances of deleted templates are left detached
+                        for i in templates_to_remove.into_iter().rev() {
+                            let template = bestiary.remove(i);
+                            for creature in places.iter_mut().flat_map(|place| place.creatures.iter_mut()) {
+                                if creature.template_id == Some(template.id) {
+                                    creature.template_id = None;
+                                }
+                            }
+                        }
+                        if bestiary.is_empty() {
+                            ui.label("save a creature to add it to the bestiary");
+                        }
+                    });
+
+                    ui.collapsing("Import SRD monsters", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("json file:");
+                            ui.text_edit_singleline(&mut srd_import.path);
+                        });
+                        if ui.button("import into bestiary").clicked() {
+                            let report = std::fs::read_to_string(&srd_import.path)
+                                .map_err(|err| err.to_string())
+                                .and_then(|json| srd_import::import_monsters(&json));
+                            srd_import.report = match report {
+                                Ok(report) => {
+                                    let mut lines = vec![format!(
+                                        "imported {} monsters",
+                                        report.creatures.len()
+                                    )];
+                                    if !report.unmapped.is_empty() {
+                                        lines.push("fields that could not be imported:".to_string());
+                                    }
+                                    lines.extend(report.unmapped);
+                                    for mut creature in report.creatures {
+                                        creature.id = id_next.next();
+                                        bestiary.push(creature);
+                                    }
+                                    lines
+                                }
+                                Err(err) => vec![format!("error: {}", err)],
+                            };
+                        }
+                        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                            for line in srd_import.report.iter() {
+                                ui.label(line);
+                            }
+                        });
+                    });
+
+                    ui.collapsing("Name lists", |ui| {
+                        ui.horizontal(|ui| {
+                            let mut seeded = names.seed.is_some();
+                            ui.checkbox(&mut seeded, "seed");
+                            match (seeded, &mut names.seed) {
+                                (true, Some(seed)) => {
+                                    if ui.add(egui::DragValue::new(seed)).changed() {
+                                        names.rolls = 0;
+                                    }
+                                }
+                                (true, seed) => *seed = Some(0),
+                                (false, seed) => *seed = None,
+                            }
+                            if names.seed.is_some() && ui.button("restart").clicked() {
+                                names.rolls = 0;
+                            }
+                        });
+                        ui.label("lists are picked by creature type or place kind");
+
+                        let mut lists_to_remove: Vec<usize> = vec![];
+                        let mut sample: Option<(usize, String)> = None;
+                        for (i, list) in names.lists.iter_mut().enumerate() {
+                            egui::collapsing_header::CollapsingHeader::new(list.culture.clone())
+                                .id_source(format!("{}names", list.id))
+                                .show(ui, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Culture or type:");
+                                        ui.text_edit_singleline(&mut list.culture);
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.selectable_value(&mut list.model, NameModel::Markov, "Markov");
+                                        ui.selectable_value(&mut list.model, NameModel::Syllable, "Syllable");
+                                        if list.model == NameModel::Markov {
+                                            ui.add(egui::DragValue::new(&mut list.order).clamp_range(1..=4).prefix("order "));
+                                        }
+                                    });
+                                    ui.label("One name per line:");
+                                    let mut words = list.words.join("\n");
+                                    if ui.text_edit_multiline(&mut words).changed() {
+                                        list.words = words.split('\n').map(String::from).collect();
+                                    }
+                                    ui.horizontal(|ui| {
+                                        if ui.button("try").clicked() {
+                                            sample = Some((list.id, list.culture.clone()));
+                                        }
+                                        if ui.button("remove list").clicked() {
+                                            lists_to_remove.push(i);
+                                        }
+                                    });
+                                    let message = ui.data().get_temp::<String>(Id::new(format!("{}name_sample", list.id)));
+                                    if let Some(message) = message {
+                                        ui.label(message);
+                                    }
+                                });
+                        }
+                        if let Some((id, culture)) = sample {
+                            let name = names.name(&[&culture]).unwrap_or_default();
+                            ui.data().insert_temp(Id::new(format!("{}name_sample", id)), name);
+                        }
+                        for i in lists_to_remove.into_iter().rev() {
+                            names.lists.remove(i);
+                        }
+                        if ui.button("add list").clicked() {
+                            names.lists.push(NameList::new(id_next.next(), "New culture", NameModel::Markov, &[]));
+                        }
+                    });
+
+                    ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.spacing_mut().item_spacing.x = 0.0;
+                            ui.label("powered by ");
+                            ui.hyperlink_to("egui", "https://github.com/emilk/egui");
+                            ui.label(" and ");
+                            ui.hyperlink_to(
+                                "eframe",
+                                "https://github.com/emilk/egui/tree/master/eframe",
+                            );
+                        });
+                    });
 
                     let mut creature_creation_windows_to_remove: Vec<usize> = vec![];
                     for (i, mut window) in &mut creature_creation_windows.into_iter().enumerate() {
@@ -367,15 +1582,67 @@ impl eframe::App for DndTool {
                                     .text("max value"),
                             );
 
-                            if ui
-                                .button("Randomize; WARNING: RESETS NAME NOTES AND OTHER THINGS")
-                                .clicked()
-                            {
-                                window.inner = Creature::randomize(*max_value);
+                            let profile = &mut window.profile;
+                            ui.collapsing("Randomization profile", |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Distribution:");
+                                    for distribution in Distribution::ALL {
+                                        ui.selectable_value(&mut profile.distribution, distribution, format!("{:?}", distribution));
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Archetype:");
+                                    for archetype in Archetype::ALL {
+                                        ui.selectable_value(&mut profile.archetype, archetype, format!("{:?}", archetype));
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    let mut point_buy = profile.point_buy.is_some();
+                                    ui.checkbox(&mut point_buy, "point buy budget");
+                                    match (point_buy, &mut profile.point_buy) {
+                                        (true, Some(points)) => {
+                                            ui.add(egui::DragValue::new(points).clamp_range(0..=10000));
+                                        }
+                                        (true, points) => *points = Some(*max_value),
+                                        (false, points) => *points = None,
+                                    }
+                                });
+                                ui.label("Minimums:");
+                                ui.horizontal_wrapped(|ui| {
+                                    for stat in Stat::POINT_STATS {
+                                        let mut minimum = profile.minimum(stat);
+                                        ui.label(format!("{:?}", stat));
+                                        if ui.add(egui::DragValue::new(&mut minimum).clamp_range(1..=*max_value)).changed() {
+                                            profile.minimums.insert(stat, minimum);
+                                        }
+                                    }
+                                });
+                                ui.label("Locked, kept by rerolls:");
+                                ui.horizontal_wrapped(|ui| {
+                                    for stat in Stat::ALL {
+                                        let mut locked = profile.is_locked(stat);
+                                        if ui.checkbox(&mut locked, format!("{:?}", stat)).changed() {
+                                            if locked {
+                                                profile.locks.push(stat);
+                                            } else {
+                                                profile.locks.retain(|lock| *lock != stat);
+                                            }
+                                        }
+                                    }
+                                });
+                            });
+
+                            if ui.button("Randomize unlocked stats").clicked() {
+                                profile.reroll(&mut window.inner, *max_value);
+                                if !profile.is_locked(Stat::Name) {
+                                    if let Some(name) = names.name(&[&window.inner._type]) {
+                                        window.inner.name = name;
+                                    }
+                                }
                             }
 
                             if ui.button("Randimise from lvl").clicked() {
-                                window.inner.randomise_based_on_lvl(*max_value);
+                                window.inner.randomise_based_on_lvl(*max_value, profile);
                             }
 
                             let size = &mut window.inner.size;
@@ -383,6 +1650,9 @@ impl eframe::App for DndTool {
                             let _type = &mut window.inner._type;
                             let lv = &mut window.inner.lv;
                             let hp = &mut window.inner.hp;
+                            let ac = &mut window.inner.ac;
+                            let attack_bonus = &mut window.inner.attack_bonus;
+                            let damage_per_round = &mut window.inner.damage_per_round;
                             let strength = &mut window.inner.strength;
                             let speed = &mut window.inner.speed;
                             let int = &mut window.inner.int;
@@ -433,6 +1703,24 @@ impl eframe::App for DndTool {
                                 }
                             });
 
+                            ui.horizontal(|ui| {
+                                ui.label("Armour class:");
+                                ui.add(egui::Slider::new(ac, 1..=30).text("ac"));
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label("Attack bonus:");
+                                ui.add(egui::Slider::new(attack_bonus, 0..=20).text("attack"));
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label("Damage per round:");
+                                ui.add(
+                                    egui::Slider::new(damage_per_round, 0..=*max_value)
+                                        .text("damage"),
+                                );
+                            });
+
                             ui.horizontal(|ui| {
                                 ui.label("Strength:");
                                 ui.add(
@@ -478,6 +1766,11 @@ impl eframe::App for DndTool {
                             ui.horizontal(|ui| {
                                 ui.label("Name:");
                                 ui.text_edit_singleline(name);
+                                if ui.button("random name").clicked() {
+                                    if let Some(random) = names.name(&[_type.as_str()]) {
+                                        *name = random;
+                                    }
+                                }
                             });
 
                             let mut skills_to_remove: Vec<usize> = vec![];
@@ -568,6 +1861,28 @@ impl eframe::App for DndTool {
                                 spells.remove(i);
                             }
 
+                            ui.collapsing("Spellbook", |ui| {
+                                ui.label(format!(
+                                    "Spells cost 1 mana per level, {} of {} mana spent",
+                                    window.inner.mana_spent, mana
+                                ));
+                                spellbook_editor(ui, &mut window.inner.spellbook, compendium);
+                            });
+
+                            ui.collapsing("Inventory", |ui| {
+                                let carrier = Some((*strength, &*size));
+                                let id = Id::new(format!("{}creature_inventory", window.id));
+                                inventory_editor(ui, id, &mut window.inner.inventory, carrier);
+                                if ui.button("roll individual treasure").clicked() {
+                                    let rating = CreatureRating::from_stats(*hp, *ac, *damage_per_round, *attack_bonus);
+                                    let level = LootLevel::of_rating(&rating);
+                                    match loot.generate(LootKind::Individual, &level, &random_tables.tables) {
+                                        Ok(mut treasure) => window.inner.inventory.take_all(&mut treasure),
+                                        Err(err) => ui.data().insert_temp(id, format!("error: {}", err)),
+                                    }
+                                }
+                            });
+
                             let mut notes_to_remove: Vec<usize> = vec![];
                             ui.horizontal(|ui| {
                                 if ui.button("add note").clicked() {
@@ -589,14 +1904,26 @@ impl eframe::App for DndTool {
                                 notes.remove(i);
                             }
 
-                            if ui.button("save").clicked() {
+                            ui.label(format!(
+                                "Challenge rating: {}",
+                                CreatureRating::from_stats(*hp, *ac, *damage_per_round, *attack_bonus)
+                            ));
+
+                            let save = ui.button("save to bestiary").clicked();
+                            let save_and_place = window.editing_id.is_none() && ui.button("save and place").clicked();
+                            if save || save_and_place {
                                 let creature = Creature {
+                                    id: window.editing_id.unwrap_or_else(|| id_next.next()),
+                                    template_id: window.inner.template_id,
                                     name: name.clone(),
                                     size: size.clone(),
-                                    danger: Default::default(),
+                                    danger: danger.clone(),
                                     _type: _type.clone(),
                                     lv: lv.clone(),
                                     hp: hp.clone(),
+                                    ac: ac.clone(),
+                                    attack_bonus: attack_bonus.clone(),
+                                    damage_per_round: damage_per_round.clone(),
                                     strength: strength.clone(),
                                     speed: speed.clone(),
                                     int: int.clone(),
@@ -605,11 +1932,25 @@ impl eframe::App for DndTool {
                                     skills: skills.clone(),
                                     spells: spells.clone(),
                                     notes: notes.clone(),
+                                    spellbook: window.inner.spellbook.clone(),
+                                    mana_spent: window.inner.mana_spent,
+                                    inventory: window.inner.inventory.clone(),
                                 };
-                                if let Some((place_idx, creature_idx)) = window.editing {
-                                    places[place_idx].creatures[creature_idx] = creature;
+                                if save_and_place {
+                                    let instance = creature.instantiate(id_next.next());
+                                    if let Some(place) = find_place_mut(places, window.place_id) {
+                                        place.creatures.push(instance);
+                                    } else if let Some(place) = places.first_mut() {
+                                        place.creatures.push(instance);
+                                    }
+                                }
+                                // windows from old saves edit placed creatures rather than templates
+                                if let Some(existing) = bestiary.iter_mut().find(|template| template.id == creature.id) {
+                                    *existing = creature;
+                                } else if let Some(existing) = find_creature_mut(places, creature.id) {
+                                    *existing = creature;
                                 } else {
-                                    places[*selected_place_index].creatures.push(creature);
+                                    bestiary.push(creature);
                                 }
 
                                 creature_creation_windows_to_remove.push(i);
@@ -622,49 +1963,294 @@ impl eframe::App for DndTool {
                     }
 
                     // place display menu
-                    open_place_windows_indexes.sort();
-                    open_place_windows_indexes.dedup();
+                    // windows of places that no longer exist close themselves
+                    open_place_windows.retain(|id| find_place(places, *id).is_some());
+                    open_place_windows.sort();
+                    open_place_windows.dedup();
+
+                    // every template in the bestiary can be proposed by the encounter builder
+                    // or put in a random encounter table
+                    let library = bestiary.clone();
+                    let party_levels = character::party_levels(characters, party);
 
                     let mut place_windows_to_remove: Vec<usize> = vec![];
-                    for open_place_window_index in open_place_windows_indexes.clone() {
-                        egui::Window::new(places[open_place_window_index].name.clone())
-                            .id(Id::new(format!("{}place", &open_place_window_index)))
+                    for open_place_window_id in open_place_windows.clone() {
+                        // (id, path, whether it can be this place's parent) of every place
+                        let other_places: Vec<(usize, String, bool)> = places
+                            .iter()
+                            .filter(|other| other.id != open_place_window_id)
+                            .map(|other| {
+                                (
+                                    other.id,
+                                    place_path(places, other.id),
+                                    !is_within(places, other.id, open_place_window_id),
+                                )
+                            })
+                            .collect();
+                        let place = find_place_mut(places, open_place_window_id).unwrap();
+                        egui::Window::new(place.name.clone())
+                            .id(Id::new(format!("{}place", &open_place_window_id)))
                             .show(ctx, |ui| {
-                                let place = &mut places[open_place_window_index];
                                 let mut name = &mut place.name;
-                                let mut creatures = &mut place.creatures;
                                 if ui.button("close window").clicked() {
-                                    place_windows_to_remove.push(open_place_window_index);
+                                    place_windows_to_remove.push(open_place_window_id);
                                 }
                                 ui.horizontal(|ui| {
                                     ui.label("Name:");
                                     ui.text_edit_singleline(name);
+                                    if ui.button("random name").clicked() {
+                                        if let Some(random) = names.name(&[&format!("{:?}", place.kind), "Places"]) {
+                                            *name = random;
+                                        }
+                                    }
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Kind:");
+                                    for kind in PlaceKind::ALL {
+                                        ui.selectable_value(&mut place.kind, kind, format!("{:?}", kind));
+                                    }
+                                });
+
+                                let parent_name = place
+                                    .parent_id
+                                    .and_then(|id| other_places.iter().find(|other| other.0 == id))
+                                    .map_or("none".to_string(), |other| other.1.clone());
+                                egui::ComboBox::from_label("inside of")
+                                    .selected_text(parent_name)
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut place.parent_id, None, "none");
+                                        for (id, path, can_be_parent) in other_places.iter() {
+                                            if *can_be_parent {
+                                                ui.selectable_value(&mut place.parent_id, Some(*id), path);
+                                            }
+                                        }
+                                    });
+
+                                ui.label("Description:");
+                                ui.text_edit_multiline(&mut place.description);
+                                backlinks_view(ui, Id::new(format!("{}place_notes", place.id)), notes, &place.name, &mut open_note);
+
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Tags:");
+                                    let mut tags_to_remove: Vec<usize> = vec![];
+                                    for (i, tag) in place.tags.iter().enumerate() {
+                                        if ui.small_button(format!("{} x", tag)).clicked() {
+                                            tags_to_remove.push(i);
+                                        }
+                                    }
+                                    for i in tags_to_remove.into_iter().rev() {
+                                        place.tags.remove(i);
+                                    }
+                                    let new_tag_id = Id::new(format!("{}new_tag", open_place_window_id));
+                                    let mut new_tag = ui.data().get_temp_mut_or_default::<String>(new_tag_id).clone();
+                                    let response = ui.add(egui::TextEdit::singleline(&mut new_tag).desired_width(80.0).hint_text("new tag"));
+                                    if response.lost_focus() && !new_tag.trim().is_empty() {
+                                        place.tags.push(new_tag.trim().to_string());
+                                        new_tag.clear();
+                                    }
+                                    ui.data().insert_temp(new_tag_id, new_tag);
+                                });
+
+                                ui.collapsing("Routes", |ui| {
+                                    let mut connections_to_remove: Vec<usize> = vec![];
+                                    for (i, connection) in connections.iter_mut().enumerate() {
+                                        let other_id = match connection.other_end(open_place_window_id) {
+                                            Some(id) => id,
+                                            None => continue,
+                                        };
+                                        let other_name = other_places
+                                            .iter()
+                                            .find(|other| other.0 == other_id)
+                                            .map_or("?".to_string(), |other| other.1.clone());
+                                        ui.horizontal(|ui| {
+                                            ui.label(format!("to {}:", other_name));
+                                            ui.add(egui::DragValue::new(&mut connection.distance).clamp_range(0..=100000).suffix(" miles"));
+                                            ui.add(egui::DragValue::new(&mut connection.travel_hours).clamp_range(0..=100000).suffix(" hours"));
+                                            if confirm_button(ui, Id::new(format!("{}{}remove_route", open_place_window_id, i)), "remove") {
+                                                history.label(format!("remove route to {}", other_name));
+                                                connections_to_remove.push(i);
+                                            }
+                                        });
+                                    }
+                                    for i in connections_to_remove.into_iter().rev() {
+                                        connections.remove(i);
+                                    }
+
+                                    ui.menu_button("add route", |ui| {
+                                        for (id, path, _) in other_places.iter() {
+                                            if ui.button(path).clicked() {
+                                                connections.push(Connection {
+                                                    from: open_place_window_id,
+                                                    to: *id,
+                                                    distance: 0,
+                                                    travel_hours: 0,
+                                                });
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    });
+                                });
+
+                                export_menu(ui, Id::new(format!("{}place_export", open_place_window_id)), &place.name, |format| {
+                                    stat_block::place_block(place, format)
+                                });
+
+                                ui.collapsing("Random encounters", |ui| {
+                                    let mut entries_to_remove: Vec<usize> = vec![];
+                                    for (i, entry) in place.encounter_table.iter_mut().enumerate() {
+                                        let name = find_template(&library, entry.template_id).map(|template| template.name.clone());
+                                        ui.horizontal(|ui| {
+                                            ui.add(egui::TextEdit::singleline(&mut entry.quantity).desired_width(50.0));
+                                            match &name {
+                                                Some(name) => ui.label(name),
+                                                None => ui.label(egui::RichText::new("template deleted from the bestiary").underline()),
+                                            };
+                                            ui.add(egui::DragValue::new(&mut entry.weight).clamp_range(0..=100).prefix("weight "));
+                                            egui::ComboBox::from_id_source(format!("{}{}time", open_place_window_id, i))
+                                                .selected_text(format!("{:?}", entry.time))
+                                                .show_ui(ui, |ui| {
+                                                    for time in TimeOfDay::ALL {
+                                                        ui.selectable_value(&mut entry.time, time, format!("{:?}", time));
+                                                    }
+                                                });
+                                            egui::ComboBox::from_id_source(format!("{}{}weather", open_place_window_id, i))
+                                                .selected_text(format!("{:?}", entry.weather))
+                                                .show_ui(ui, |ui| {
+                                                    for weather in Weather::ALL {
+                                                        ui.selectable_value(&mut entry.weather, weather, format!("{:?}", weather));
+                                                    }
+                                                });
+                                            if confirm_button(ui, Id::new(format!("{}{}remove_entry", open_place_window_id, i)), "remove") {
+                                                history.label(format!(
+                                                    "remove {} from random encounters",
+                                                    name.as_deref().unwrap_or("a deleted template")
+                                                ));
+                                                entries_to_remove.push(i);
+                                            }
+                                        });
+                                    }
+                                    for i in entries_to_remove.into_iter().rev() {
+                                        place.encounter_table.remove(i);
+                                    }
+
+                                    ui.menu_button("add entry", |ui| {
+                                        if library.is_empty() {
+                                            ui.label("add creatures to the bestiary first");
+                                        }
+                                        for creature in library.iter() {
+                                            if ui.button(&creature.name).clicked() {
+                                                place.encounter_table.push(EncounterEntry::new(creature.id));
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        for time in TimeOfDay::ALL {
+                                            ui.selectable_value(time_of_day, time, format!("{:?}", time));
+                                        }
+                                    });
+                                    ui.horizontal(|ui| {
+                                        for option in Weather::ALL {
+                                            ui.selectable_value(weather, option, format!("{:?}", option));
+                                        }
+                                    });
+
+                                    let error_id = Id::new(format!("{}encounter_error", open_place_window_id));
+                                    let mut rolled = None;
+                                    ui.horizontal(|ui| {
+                                        if ui.button("roll into place").clicked() {
+                                            rolled = Some((random_encounters::roll_encounter(&place.encounter_table, &library, *time_of_day, *weather), false));
+                                        }
+                                        if ui.button("roll into combat").clicked() {
+                                            rolled = Some((random_encounters::roll_encounter(&place.encounter_table, &library, *time_of_day, *weather), true));
+                                        }
+                                    });
+                                    match rolled {
+                                        Some((Ok(creatures), into_combat)) => {
+                                            ui.data().remove::<String>(error_id);
+                                            for mut creature in creatures {
+                                                creature.id = id_next.next();
+                                                if into_combat {
+                                                    combat.combatants.push(Combatant::from_creature(id_next.next(), creature));
+                                                } else {
+                                                    place.creatures.push(creature);
+                                                }
+                                            }
+                                        }
+                                        Some((Err(err), _)) => ui.data().insert_temp(error_id, err),
+                                        None => {}
+                                    }
+                                    let error = ui.data().get_temp::<String>(error_id);
+                                    if let Some(err) = error {
+                                        ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                                    }
                                 });
+
+                                let mut creatures = &mut place.creatures;
                                 let mut creatures_to_remove: Vec<usize> = vec![];
+                                let mut inventories_to_drop: Vec<usize> = vec![];
+                                let mut new_templates: Vec<Creature> = vec![];
 
-                                ui.collapsing("Creatures", |ui| {
+                                let followed_id = Id::new(format!("{}followed_creature", open_place_window_id));
+                                let followed = ui.data().get_temp::<usize>(followed_id);
+                                ui.data().remove::<usize>(followed_id);
+                                egui::CollapsingHeader::new("Creatures").open(followed.map(|_| true)).show(ui, |ui| {
                                     for (i, creature) in creatures.iter_mut().enumerate() {
+                                        let is_followed = followed == Some(creature.id);
                                         // basic information on creature
                                         ui.horizontal(|ui| {
-                                            ui.label(format!(
+                                            let label = ui.label(format!(
                                                 "{}: lvl {}",
                                                 creature.name, creature.lv
                                             ));
-                                            if ui.button("edit").clicked() {
-                                                creature_creation_windows.push(CreatureMenu {
-                                                    inner: creature.clone(),
-                                                    id: id_next.next(),
-                                                    max_value: 100,
-                                                    selected_place_index: *selected_place_index,
-                                                    editing: Some((open_place_window_index, i)),
-                                                });
+                                            if is_followed {
+                                                label.scroll_to_me(Some(egui::Align::Center));
+                                            }
+                                            export_menu(ui, Id::new(format!("{}export", creature.id)), &creature.name, |format| {
+                                                stat_block::creature_block(creature, format)
+                                            });
+                                            match creature.template_id.and_then(|id| find_template(&library, id)) {
+                                                Some(template) => {
+                                                    ui.label(format!("({})", template.name));
+                                                    if ui.button("edit template").clicked() {
+                                                        creature_creation_windows.push(CreatureMenu::editing(
+                                                            id_next.next(),
+                                                            open_place_window_id,
+                                                            template.clone(),
+                                                        ));
+                                                    }
+                                                    if ui.button("reset to template").clicked() {
+                                                        creature.update_from_template(template);
+                                                    }
+                                                    if ui.button("detach").clicked() {
+                                                        creature.template_id = None;
+                                                    }
+                                                }
+                                                None => {
+                                                    if ui.button("make template").clicked() {
+                                                        let template = Creature {
+                                                            id: id_next.next(),
+                                                            template_id: None,
+                                                            ..creature.clone()
+                                                        };
+                                                        creature.template_id = Some(template.id);
+                                                        new_templates.push(template);
+                                                    }
+                                                }
                                             }
                                         });
                                         // creature details
-                                        egui::collapsing_header::CollapsingHeader::new("Details").id_source(i+1).show(ui, |ui| {
+                                        egui::collapsing_header::CollapsingHeader::new("Details").id_source(creature.id).open(is_followed.then_some(true)).show(ui, |ui| {
                                             ui.horizontal(|ui| {
                                                 ui.label("Name:");
                                                 ui.text_edit_singleline(&mut creature.name);
+                                                if ui.button("random name").clicked() {
+                                                    if let Some(random) = names.name(&[&creature._type]) {
+                                                        creature.name = random;
+                                                    }
+                                                }
                                             });
 
                                             let size = &mut creature.size;
@@ -686,56 +2272,70 @@ impl eframe::App for DndTool {
                                                 ui.label("Level:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.lv)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
                                                 );
                                             });
                                             ui.horizontal(|ui| {
                                                 ui.label("HP:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.hp)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
+                                                );
+                                            });
+                                            ui.horizontal(|ui| {
+                                                ui.label("AC:");
+                                                ui.add(
+                                                    egui::DragValue::new(&mut creature.ac)
+                                                        .speed(1),
+                                                );
+                                                ui.label("Attack:");
+                                                ui.add(
+                                                    egui::DragValue::new(&mut creature.attack_bonus)
+                                                        .speed(1),
+                                                );
+                                                ui.label("Damage:");
+                                                ui.add(
+                                                    egui::DragValue::new(&mut creature.damage_per_round)
+                                                        .speed(1),
                                                 );
                                             });
+                                            ui.label(format!(
+                                                "Challenge rating: {}",
+                                                creature.challenge_rating()
+                                            ));
                                             ui.horizontal(|ui| {
                                                 ui.label("Strength:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.strength)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
                                                 );
                                             });
                                             ui.horizontal(|ui| {
                                                 ui.label("Speed:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.speed)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
                                                 );
                                             });
                                             ui.horizontal(|ui| {
                                                 ui.label("Intelligence:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.int)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
                                                 );
                                             });
                                             ui.horizontal(|ui| {
                                                 ui.label("Mana:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.mana)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
                                                 );
                                             });
                                             ui.horizontal(|ui| {
                                                 ui.label("Vitality:");
                                                 ui.add(
                                                     egui::DragValue::new(&mut creature.vit)
-                                                        .speed(1)
-                                                        .clamp_range(0..=100),
+                                                        .speed(1),
                                                 );
                                             });
                                             ui.collapsing("Skills", |ui| {
@@ -764,41 +2364,1355 @@ impl eframe::App for DndTool {
                                                 }
                                             });
 
-
+                                            ui.collapsing("Inventory", |ui| {
+                                                let carrier = Some((creature.strength, &creature.size));
+                                                let id = Id::new(format!("{}inventory", creature.id));
+                                                inventory_editor(ui, id, &mut creature.inventory, carrier);
+                                                if ui.button("roll individual treasure").clicked() {
+                                                    let level = LootLevel::of_creature(creature);
+                                                    match loot.generate(LootKind::Individual, &level, &random_tables.tables) {
+                                                        Ok(mut treasure) => creature.inventory.take_all(&mut treasure),
+                                                        Err(err) => ui.data().insert_temp(id, format!("error: {}", err)),
+                                                    }
+                                                }
+                                            });
+                                        });
+                                        backlinks_view(ui, Id::new(format!("{}creature_notes", creature.id)), notes, &creature.name, &mut open_note);
+                                        ui.horizontal(|ui| {
+                                            if confirm_button(ui, Id::new(format!("{}remove", creature.id)), "remove") {
+                                                history.label(format!("remove {}", creature.name));
+                                                creatures_to_remove.push(i);
+                                            }
+                                            if !creature.inventory.is_empty() && ui.button("drop inventory as loot").clicked() {
+                                                inventories_to_drop.push(creature.id);
+                                            }
                                         });
-                                        if ui.button("remove").clicked() {
-                                            creatures_to_remove.push(i);
-                                        }
                                     }
                                 });
+                                ui.collapsing("Encounter difficulty", |ui| {
+                                    ui.label(
+                                        EncounterDifficulty::of_creatures(creatures, &party_levels)
+                                            .to_string(),
+                                    );
+                                });
                                 if ui.button("add creature").clicked() {
-                                    creature_creation_windows.push(CreatureMenu {
-                                        inner: Default::default(),
+                                    creature_creation_windows
+                                        .push(CreatureMenu::new(id_next.next(), open_place_window_id));
+                                }
+                                if ui.button("generate creatures").clicked() {
+                                    batch_windows.push(BatchMenu::new(id_next.next(), open_place_window_id));
+                                }
+                                if ui.button("build encounter").clicked() {
+                                    encounter_builder_windows.push(EncounterBuilderMenu {
                                         id: id_next.next(),
-                                        max_value: 100,
-                                        selected_place_index: *selected_place_index,
-                                        editing: None,
+                                        place_id: open_place_window_id,
+                                        target: Difficulty::Medium,
+                                        max_creatures: 8,
+                                        slots: vec![],
                                     });
                                 }
-                                for c in creatures_to_remove {
-                                    places[open_place_window_index].creatures.remove(c);
+                                for c in creatures_to_remove.into_iter().rev() {
+                                    creatures.remove(c);
+                                }
+                                for creature_id in inventories_to_drop {
+                                    place.drop_inventory(creature_id);
                                 }
+                                ui.collapsing("Loot", |ui| {
+                                    let id = Id::new(format!("{}loot", open_place_window_id));
+                                    // hoards default to the challenge rating of the strongest creature
+                                    let level = ui.data().get_temp::<LootLevel>(id.with("level"));
+                                    let mut level = level.unwrap_or_else(|| {
+                                        place
+                                            .creatures
+                                            .iter()
+                                            .map(LootLevel::of_creature)
+                                            .max()
+                                            .unwrap_or(LootLevel::ChallengeRating(0))
+                                    });
+                                    ui.horizontal(|ui| {
+                                        if ui.selectable_label(matches!(level, LootLevel::ChallengeRating(_)), "by CR").clicked() {
+                                            level = LootLevel::ChallengeRating(0);
+                                        }
+                                        if ui.selectable_label(matches!(level, LootLevel::Danger(_)), "by danger").clicked() {
+                                            level = LootLevel::Danger(DangerRating::default());
+                                        }
+                                        match &mut level {
+                                            LootLevel::ChallengeRating(cr) => {
+                                                ui.add(egui::DragValue::new(cr).clamp_range(0..=30).prefix("CR "));
+                                            }
+                                            LootLevel::Danger(danger) => {
+                                                for option in DangerRating::ALL {
+                                                    let label = format!("{:?}", option);
+                                                    ui.selectable_value(danger, option, label);
+                                                }
+                                            }
+                                        }
+                                    });
+                                    let mut kind = None;
+                                    ui.horizontal(|ui| {
+                                        if ui.button("roll hoard").clicked() {
+                                            kind = Some(LootKind::Hoard);
+                                        }
+                                        if ui.button("roll individual treasure").clicked() {
+                                            kind = Some(LootKind::Individual);
+                                        }
+                                    });
+                                    if let Some(kind) = kind {
+                                        match loot.generate(kind, &level, &random_tables.tables) {
+                                            Ok(mut treasure) => {
+                                                ui.data().insert_temp(id, format!("rolled {}", treasure.total_value()));
+                                                place.loot.take_all(&mut treasure);
+                                            }
+                                            Err(err) => ui.data().insert_temp(id, format!("error: {}", err)),
+                                        }
+                                    }
+                                    ui.data().insert_temp(id.with("level"), level);
+                                    inventory_editor(ui, id, &mut place.loot, None);
+                                });
+                                bestiary.append(&mut new_templates);
                             });
                     }
                     for window in place_windows_to_remove {
-                        let index = open_place_windows_indexes
-                            .iter()
-                            .position(|x| *x == window)
-                            .unwrap();
-                        open_place_windows_indexes.remove(index);
+                        open_place_windows.retain(|id| *id != window);
                     }
-                });
-                egui::CentralPanel::default().show(ctx, |ui| {
-                    // The central panel the region left after adding TopPanels and SidePanels
 
-                    ui.heading("DnD tools");
-                    egui::warn_if_debug_build(ui);
-                });
+                    let mut encounter_builder_windows_to_remove: Vec<usize> = vec![];
+                    for (i, window) in encounter_builder_windows.iter_mut().enumerate() {
+                        egui::Window::new("Encounter builder")
+                            .id(Id::new(format!("{}encounter", &window.id)))
+                            .show(ctx, |ui| {
+                                if ui.button("close window").clicked() {
+                                    encounter_builder_windows_to_remove.push(i);
+                                }
+
+                                place_combo_box(ui, "place", places, &mut window.place_id);
+
+                                let target = &mut window.target;
+                                ui.horizontal(|ui| {
+                                    ui.label("Target:");
+                                    ui.selectable_value(target, Difficulty::Easy, "Easy");
+                                    ui.selectable_value(target, Difficulty::Medium, "Medium");
+                                    ui.selectable_value(target, Difficulty::Hard, "Hard");
+                                    ui.selectable_value(target, Difficulty::Deadly, "Deadly");
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Max creatures:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut window.max_creatures)
+                                            .clamp_range(1..=30),
+                                    );
+                                });
+
+                                let (min, max) =
+                                    encounter_builder::xp_budget(&party_levels, window.target);
+                                ui.label(format!("XP budget: {} - {} adjusted XP", min, max));
+
+                                if library.is_empty() {
+                                    ui.label("add creatures to the bestiary to build encounters from them");
+                                }
+
+                                if ui.button("Reroll").clicked() {
+                                    window.slots = encounter_builder::propose(
+                                        &window.slots,
+                                        &library,
+                                        &party_levels,
+                                        window.target,
+                                        window.max_creatures,
+                                    );
+                                }
+
+                                let mut slots_to_remove: Vec<usize> = vec![];
+                                let mut slot_to_swap: Option<usize> = None;
+                                for (slot_index, slot) in window.slots.iter_mut().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        ui.checkbox(&mut slot.locked, "lock");
+                                        ui.label(format!(
+                                            "{}: {}",
+                                            slot.creature.name,
+                                            slot.creature.challenge_rating().overall
+                                        ));
+                                        if ui.button("swap").clicked() {
+                                            slot_to_swap = Some(slot_index);
+                                        }
+                                        if ui.button("remove").clicked() {
+                                            slots_to_remove.push(slot_index);
+                                        }
+                                    });
+                                }
+                                if let Some(slot_index) = slot_to_swap {
+                                    encounter_builder::swap(
+                                        &mut window.slots,
+                                        slot_index,
+                                        &library,
+                                        &party_levels,
+                                        window.target,
+                                    );
+                                }
+                                for slot_index in slots_to_remove.into_iter().rev() {
+                                    window.slots.remove(slot_index);
+                                }
+
+                                ui.collapsing("Encounter difficulty", |ui| {
+                                    ui.label(
+                                        encounter_builder::rate_slots(&window.slots, &party_levels)
+                                            .to_string(),
+                                    );
+                                });
+
+                                if ui.button("commit to place").clicked() {
+                                    if let Some(place) = find_place_mut(places, window.place_id) {
+                                        // every committed creature is a new instance, with its own id
+                                        for slot in window.slots.iter() {
+                                            place.creatures.push(slot.creature.instantiate(id_next.next()));
+                                        }
+                                    }
+                                    encounter_builder_windows_to_remove.push(i);
+                                }
+                            });
+                    }
+                    for window in encounter_builder_windows_to_remove.into_iter().rev() {
+                        encounter_builder_windows.remove(window);
+                    }
+
+                    let mut batch_windows_to_remove: Vec<usize> = vec![];
+                    for (i, window) in batch_windows.iter_mut().enumerate() {
+                        egui::Window::new("Generate creatures")
+                            .id(Id::new(format!("{}batch", &window.id)))
+                            .show(ctx, |ui| {
+                                if ui.button("close window").clicked() {
+                                    batch_windows_to_remove.push(i);
+                                }
+
+                                place_combo_box(ui, "place", places, &mut window.place_id);
+
+                                ui.horizontal(|ui| {
+                                    ui.label("How many:");
+                                    ui.add(egui::TextEdit::singleline(&mut window.count).desired_width(60.0));
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Name:");
+                                    ui.add(egui::TextEdit::singleline(&mut window.name).hint_text("random names"));
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Type:");
+                                    ui.text_edit_singleline(&mut window._type);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Levels:");
+                                    ui.add(egui::DragValue::new(&mut window.levels.0).clamp_range(1..=window.max_value));
+                                    ui.label("-");
+                                    ui.add(egui::DragValue::new(&mut window.levels.1).clamp_range(1..=window.max_value));
+                                    ui.label("max value:");
+                                    ui.add(egui::DragValue::new(&mut window.max_value).clamp_range(2..=10000));
+                                });
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Sizes (none for any):");
+                                    for size in Size::ALL {
+                                        let mut picked = window.sizes.contains(&size);
+                                        if ui.checkbox(&mut picked, format!("{:?}", size)).changed() {
+                                            if picked {
+                                                window.sizes.push(size);
+                                            } else {
+                                                window.sizes.retain(|other| *other != size);
+                                            }
+                                        }
+                                    }
+                                });
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Danger (none for any):");
+                                    for danger in DangerRating::ALL {
+                                        let mut picked = window.dangers.contains(&danger);
+                                        if ui.checkbox(&mut picked, format!("{:?}", danger)).changed() {
+                                            if picked {
+                                                window.dangers.push(danger);
+                                            } else {
+                                                window.dangers.retain(|other| *other != danger);
+                                            }
+                                        }
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Profile:");
+                                    ui.checkbox(&mut window.from_level, "stats from level");
+                                    for distribution in Distribution::ALL {
+                                        ui.selectable_value(&mut window.profile.distribution, distribution, format!("{:?}", distribution));
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Archetype:");
+                                    for archetype in Archetype::ALL {
+                                        ui.selectable_value(&mut window.profile.archetype, archetype, format!("{:?}", archetype));
+                                    }
+                                });
+
+                                if ui.button("preview").clicked() {
+                                    match generation::generate_batch(window, names) {
+                                        Ok(creatures) => {
+                                            window.preview = creatures;
+                                            window.error = None;
+                                        }
+                                        Err(err) => window.error = Some(err),
+                                    }
+                                }
+                                if let Some(err) = &window.error {
+                                    ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                                }
+
+                                let mut preview_to_remove: Vec<usize> = vec![];
+                                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                                    egui::Grid::new(format!("{}batch_preview", window.id)).striped(true).show(ui, |ui| {
+                                        for (i, creature) in window.preview.iter().enumerate() {
+                                            ui.label(&creature.name);
+                                            ui.label(format!("lvl {}", creature.lv));
+                                            ui.label(format!("{:?}", creature.size));
+                                            ui.label(format!("{:?}", creature.danger));
+                                            ui.label(format!("hp {}", creature.hp));
+                                            ui.label(creature.challenge_rating().overall.to_string());
+                                            if ui.small_button("remove").clicked() {
+                                                preview_to_remove.push(i);
+                                            }
+                                            ui.end_row();
+                                        }
+                                    });
+                                });
+                                for i in preview_to_remove.into_iter().rev() {
+                                    window.preview.remove(i);
+                                }
+
+                                if !window.preview.is_empty() && ui.button(format!("add {} creatures to place", window.preview.len())).clicked() {
+                                    if let Some(place) = find_place_mut(places, window.place_id) {
+                                        for mut creature in window.preview.drain(..) {
+                                            creature.id = id_next.next();
+                                            place.creatures.push(creature);
+                                        }
+                                        batch_windows_to_remove.push(i);
+                                    }
+                                }
+                            });
+                    }
+                    for window in batch_windows_to_remove.into_iter().rev() {
+                        batch_windows.remove(window);
+                    }
+                });
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    // The central panel the region left after adding TopPanels and SidePanels
+
+                    ui.heading("DnD tools");
+                    egui::warn_if_debug_build(ui);
+                });
+            }
+            Interface::Combat => {
+                // N moves to the next turn, as long as nothing is being typed into
+                if ctx.memory().focus().is_none() && ctx.input().key_pressed(egui::Key::N) {
+                    combat.next_turn();
+                }
+
+                egui::SidePanel::left("side_panel").show(ctx, |ui| {
+                    place_combo_box(ui, "place:", places, &mut combat.selected_place_id);
+
+                    if ui.button("add creatures from place").clicked() {
+                        let creatures = find_place(places, combat.selected_place_id)
+                            .map_or(vec![], |place| place.creatures.clone());
+                        for creature in creatures {
+                            combat
+                                .combatants
+                                .push(Combatant::from_creature(id_next.next(), creature));
+                        }
+                    }
+                    if ui.button("add party characters").clicked() {
+                        for character in characters.iter().filter(|character| character.in_party) {
+                            let in_combat = combat
+                                .combatants
+                                .iter()
+                                .any(|combatant| combatant.character_id == Some(character.id));
+                            if !in_combat {
+                                combat
+                                    .combatants
+                                    .push(Combatant::from_character(id_next.next(), character));
+                            }
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut combat.new_player_name);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Initiative bonus:");
+                        ui.add(egui::DragValue::new(&mut combat.new_player_bonus));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Max hp:");
+                        ui.add(egui::DragValue::new(&mut combat.new_player_hp).clamp_range(1..=1000));
+                    });
+                    if ui.button("add player character").clicked() {
+                        let name = std::mem::take(&mut combat.new_player_name);
+                        combat.combatants.push(Combatant::player(
+                            id_next.next(),
+                            name,
+                            combat.new_player_bonus,
+                            combat.new_player_hp,
+                        ));
+                    }
+
+                    ui.separator();
+
+                    if ui.button("roll initiative").clicked() {
+                        combat.error = combat.roll_initiative().err();
+                    }
+                    if ui.button("sort by initiative").clicked() {
+                        combat.sort();
+                    }
+                    if ui.button("next turn (N)").clicked() {
+                        combat.next_turn();
+                    }
+                    if ui.button("end combat").clicked() {
+                        combat.end(characters);
+                    }
+                    if ui.button("rest").on_hover_text("restores everyone's spell slots and mana").clicked() {
+                        combat.rest();
+                    }
+                    if ui.button("clear combatants").clicked() {
+                        *combat = CombatTracker::default();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("undo last hp change").clicked() {
+                        combat.undo();
+                    }
+                    ui.collapsing("Combat log", |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            for change in combat.log.iter().rev() {
+                                ui.label(format!("round {}: {}", change.round, change.description));
+                            }
+                        });
+                    });
+                });
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    if combat.round == 0 {
+                        ui.heading("Combat not started");
+                    } else {
+                        ui.heading(format!("Round {}", combat.round));
+                    }
+
+                    if let Some(err) = &combat.error {
+                        ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                    }
+
+                    for reminder in combat.reminders.iter() {
+                        ui.colored_label(Color32::LIGHT_YELLOW, reminder);
+                    }
+
+                    let active = combat.active().map(|combatant| combatant.id);
+                    let mut to_remove: Vec<usize> = vec![];
+                    let mut to_resume: Vec<usize> = vec![];
+                    // (id, amount, action) where action is one of "damage", "heal" and "temp"
+                    let mut hp_actions: Vec<(usize, String, &str)> = vec![];
+                    // (id, spell name)
+                    let mut casts: Vec<(usize, String)> = vec![];
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        egui::Grid::new("initiative_order").striped(true).show(ui, |ui| {
+                            ui.label("Initiative");
+                            ui.label("Name");
+                            ui.label("HP");
+                            ui.label("Damage / healing");
+                            ui.label("Effects");
+                            ui.label("Spells");
+                            ui.label("Status");
+                            ui.end_row();
+
+                            for combatant in combat.combatants.iter_mut() {
+                                ui.add(egui::DragValue::new(&mut combatant.initiative));
+                                if active == Some(combatant.id) {
+                                    ui.colored_label(
+                                        Color32::YELLOW,
+                                        format!("> {}", combatant.name),
+                                    );
+                                } else {
+                                    ui.label(&combatant.name);
+                                }
+
+                                let hp = &combatant.hit_points;
+                                let mut hp_text = format!("{}/{}", hp.current, hp.max);
+                                if hp.temp > 0 {
+                                    hp_text.push_str(&format!(" +{} temp", hp.temp));
+                                }
+                                match hp.status {
+                                    LifeStatus::Conscious => ui.label(hp_text),
+                                    LifeStatus::Unconscious => ui.colored_label(
+                                        Color32::LIGHT_BLUE,
+                                        format!("{} unconscious", hp_text),
+                                    ),
+                                    LifeStatus::Dead => ui.colored_label(
+                                        Color32::RED,
+                                        format!("{} dead", hp_text),
+                                    ),
+                                };
+
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut combatant.amount)
+                                            .desired_width(60.0)
+                                            .hint_text("1d6+2"),
+                                    );
+                                    egui::ComboBox::from_id_source(format!(
+                                        "{}modifier",
+                                        combatant.id
+                                    ))
+                                    .selected_text(format!("{:?}", combatant.damage_modifier))
+                                    .show_ui(ui, |ui| {
+                                        let modifier = &mut combatant.damage_modifier;
+                                        ui.selectable_value(modifier, DamageModifier::Normal, "Normal");
+                                        ui.selectable_value(modifier, DamageModifier::Resistant, "Resistant");
+                                        ui.selectable_value(modifier, DamageModifier::Vulnerable, "Vulnerable");
+                                        ui.selectable_value(modifier, DamageModifier::Immune, "Immune");
+                                    });
+                                    for action in ["damage", "heal", "temp"] {
+                                        if ui.button(action).clicked() {
+                                            hp_actions.push((
+                                                combatant.id,
+                                                combatant.amount.clone(),
+                                                action,
+                                            ));
+                                        }
+                                    }
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut combatant.concentrating, "concentrating");
+                                    let mut effects_to_remove: Vec<usize> = vec![];
+                                    for (i, effect) in combatant.effects.iter().enumerate() {
+                                        if ui
+                                            .small_button(format!("{} x", effect))
+                                            .on_hover_text("remove")
+                                            .clicked()
+                                        {
+                                            effects_to_remove.push(i);
+                                        }
+                                    }
+                                    for i in effects_to_remove.into_iter().rev() {
+                                        combatant.effects.remove(i);
+                                    }
+
+                                    ui.menu_button("+", |ui| {
+                                        let effect = &mut combatant.new_effect;
+                                        egui::ComboBox::from_label("condition")
+                                            .selected_text(match &effect.condition {
+                                                Condition::Custom(_) => "Custom".to_string(),
+                                                condition => condition.to_string(),
+                                            })
+                                            .show_ui(ui, |ui| {
+                                                for condition in Condition::STANDARD {
+                                                    let text = condition.to_string();
+                                                    ui.selectable_value(&mut effect.condition, condition, text);
+                                                }
+                                                if ui.selectable_label(matches!(effect.condition, Condition::Custom(_)), "Custom").clicked() {
+                                                    effect.condition = Condition::Custom(String::new());
+                                                }
+                                            });
+                                        if let Condition::Custom(name) = &mut effect.condition {
+                                            ui.horizontal(|ui| {
+                                                ui.label("Name:");
+                                                ui.text_edit_singleline(name);
+                                            });
+                                        }
+
+                                        ui.horizontal(|ui| {
+                                            let mut lasts = effect.rounds.is_some();
+                                            ui.checkbox(&mut lasts, "rounds:");
+                                            if lasts {
+                                                let rounds = effect.rounds.get_or_insert(1);
+                                                ui.add(egui::DragValue::new(rounds).clamp_range(1..=100));
+                                            } else {
+                                                effect.rounds = None;
+                                            }
+                                        });
+
+                                        ui.horizontal(|ui| {
+                                            ui.label("Counts down at:");
+                                            ui.selectable_value(&mut effect.tick, Tick::StartOfTurn, "start of turn");
+                                            ui.selectable_value(&mut effect.tick, Tick::EndOfTurn, "end of turn");
+                                        });
+
+                                        ui.horizontal(|ui| {
+                                            ui.label("Repeated save:");
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut effect.save)
+                                                    .hint_text("WIS DC 13"),
+                                            );
+                                        });
+
+                                        if ui.button("add effect").clicked() {
+                                            combatant.effects.push(combatant.new_effect.clone());
+                                            ui.close_menu();
+                                        }
+                                    });
+                                });
+
+                                if combatant.spellbook().is_empty() {
+                                    ui.label("");
+                                } else {
+                                    ui.menu_button(combatant.spell_resources(), |ui| {
+                                        for name in combatant.spellbook() {
+                                            let label = compendium
+                                                .get(name)
+                                                .map_or(name.clone(), |spell| format!("{} ({})", spell.name, spell.level_label()));
+                                            if ui.button(label).clicked() {
+                                                casts.push((combatant.id, name.clone()));
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    });
+                                }
+
+                                ui.horizontal(|ui| {
+                                    match &mut combatant.status {
+                                        TurnStatus::Normal => {
+                                            if ui.button("delay").clicked() {
+                                                combatant.status = TurnStatus::Delaying;
+                                            }
+                                            if ui.button("ready").clicked() {
+                                                combatant.status =
+                                                    TurnStatus::Readied(String::new());
+                                            }
+                                        }
+                                        TurnStatus::Delaying => {
+                                            ui.label("delaying");
+                                            if ui.button("act now").clicked() {
+                                                to_resume.push(combatant.id);
+                                            }
+                                        }
+                                        TurnStatus::Readied(trigger) => {
+                                            ui.label("readied, trigger:");
+                                            ui.text_edit_singleline(trigger);
+                                            if ui.button("use").clicked() {
+                                                combatant.status = TurnStatus::Normal;
+                                            }
+                                        }
+                                    }
+                                    if ui.button("remove").clicked() {
+                                        to_remove.push(combatant.id);
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    for (id, amount, action) in hp_actions {
+                        history.label(format!("{} {}", action, amount));
+                        let result = match action {
+                            "damage" => combat.damage(id, &amount),
+                            "heal" => combat.heal(id, &amount),
+                            _ => combat.add_temp_hp(id, &amount),
+                        };
+                        combat.error = result.err();
+                    }
+                    for (id, name) in casts {
+                        combat.error = match compendium.get(&name) {
+                            Some(spell) => combat.cast(id, spell).err(),
+                            None => Some(format!("{} isn't in the spell compendium", name)),
+                        };
+                    }
+                    for id in to_resume {
+                        combat.resume(id);
+                    }
+                    for id in to_remove {
+                        combat.remove(id);
+                    }
+                });
+            }
+            Interface::RandomTables => {
+                egui::SidePanel::left("side_panel").show(ctx, |ui| {
+                    for table in random_tables.tables.iter() {
+                        if ui
+                            .selectable_label(random_tables.selected_id == Some(table.id), &table.name)
+                            .clicked()
+                        {
+                            random_tables.selected_id = Some(table.id);
+                        }
+                    }
+
+                    ui.separator();
+
+                    if ui.button("new table").clicked() {
+                        let table = RandomTable::new(id_next.next(), "New table");
+                        random_tables.selected_id = Some(table.id);
+                        random_tables.tables.push(table);
+                    }
+                    if let Some(selected_id) = random_tables.selected_id {
+                        if ui.button("delete table").clicked() {
+                            random_tables.tables.retain(|table| table.id != selected_id);
+                            random_tables.selected_id = None;
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.label("CSV or a pasted table, e.g. `01-15: Bandits`:");
+                    ui.add(egui::TextEdit::multiline(&mut random_tables.text).code_editor());
+                    if ui.button("import as new table").clicked() {
+                        match RandomTable::from_csv(id_next.next(), "Imported table", &random_tables.text) {
+                            Ok(table) => {
+                                random_tables.selected_id = Some(table.id);
+                                random_tables.tables.push(table);
+                                random_tables.error = None;
+                            }
+                            Err(err) => random_tables.error = Some(err),
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.label("Loot data file (empty for the built in loot):");
+                    ui.text_edit_singleline(&mut loot.path);
+                    if ui.button("load loot").clicked() {
+                        loot.load();
+                    }
+                    if let Some(err) = &loot.error {
+                        ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                    }
+                    ui.label("Tables of your own with the same name as a loot table, like `Gems 10 gp`, replace it.");
+                });
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    if let Some(err) = &random_tables.error {
+                        ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                    }
+
+                    let TableLibrary {
+                        tables,
+                        selected_id,
+                        text,
+                        formula,
+                        results,
+                        error,
+                    } = random_tables;
+
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(formula)
+                                .hint_text("{1d6} gold and a roll on [Trinkets]"),
+                        );
+                        if ui.button("resolve").clicked() {
+                            match random_tables::resolve(tables, formula) {
+                                Ok(result) => {
+                                    results.push(result);
+                                    *error = None;
+                                }
+                                Err(err) => *error = Some(err),
+                            }
+                        }
+                    });
+
+                    ui.separator();
+                    let table = match tables.iter_mut().find(|table| Some(table.id) == *selected_id) {
+                        Some(table) => table,
+                        None => {
+                            ui.heading("Select or make a table");
+                            return;
+                        }
+                    };
+
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut table.name);
+                    });
+                    match table.die() {
+                        Some(die) => ui.label(format!("rolled with a d{}", die)),
+                        None => ui.label("picked by weight"),
+                    };
+
+                    let mut rows_to_remove: Vec<usize> = vec![];
+                    egui::Grid::new("random_table").striped(true).show(ui, |ui| {
+                        for (i, row) in table.rows.iter_mut().enumerate() {
+                            match &mut row.roll {
+                                RowRoll::Range(low, high) => {
+                                    ui.horizontal(|ui| {
+                                        ui.add(egui::DragValue::new(low).clamp_range(1..=1000));
+                                        ui.label("-");
+                                        ui.add(egui::DragValue::new(high).clamp_range(1..=1000));
+                                    });
+                                }
+                                RowRoll::Weight(weight) => {
+                                    ui.add(egui::DragValue::new(weight).clamp_range(0..=1000).prefix("weight "));
+                                }
+                            }
+                            ui.add(egui::TextEdit::singleline(&mut row.result).desired_width(300.0));
+                            if ui.button("remove").clicked() {
+                                rows_to_remove.push(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    for i in rows_to_remove.into_iter().rev() {
+                        table.rows.remove(i);
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.button("add range row").clicked() {
+                            let next = table.die().map_or(1, |die| die + 1);
+                            table.rows.push(TableRow {
+                                roll: RowRoll::Range(next, next),
+                                result: String::new(),
+                            });
+                        }
+                        if ui.button("add weighted row").clicked() {
+                            table.rows.push(TableRow {
+                                roll: RowRoll::Weight(1),
+                                result: String::new(),
+                            });
+                        }
+                    });
+                    ui.label("`{1d6}` in a result rolls dice, `[Table name]` rolls on another table");
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if ui.button("export to text box").clicked() {
+                            *text = table.to_csv();
+                        }
+                        if ui.button("copy as CSV").clicked() {
+                            ui.output().copied_text = table.to_csv();
+                        }
+                    });
+
+                    let name = table.name.clone();
+                    if ui.button("roll").clicked() {
+                        match random_tables::roll_on(tables, &name) {
+                            Ok(result) => {
+                                results.push(format!("{}: {}", name, result));
+                                *error = None;
+                            }
+                            Err(err) => *error = Some(err),
+                        }
+                    }
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for result in results.iter().rev() {
+                            ui.label(result);
+                        }
+                    });
+                });
+            }
+            Interface::Characters => {
+                egui::SidePanel::left("side_panel").show(ctx, |ui| {
+                    for character in characters.iter() {
+                        if ui
+                            .selectable_label(
+                                *selected_character_id == Some(character.id),
+                                character.to_string(),
+                            )
+                            .clicked()
+                        {
+                            *selected_character_id = Some(character.id);
+                        }
+                    }
+
+                    ui.separator();
+
+                    if ui.button("new character").clicked() {
+                        let character = PlayerCharacter::new(id_next.next(), "New character");
+                        *selected_character_id = Some(character.id);
+                        characters.push(character);
+                    }
+                    if let Some(selected_id) = *selected_character_id {
+                        if ui.button("delete character").clicked() {
+                            characters.retain(|character| character.id != selected_id);
+                            *selected_character_id = None;
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.label(format!(
+                        "Encounters are rated against levels {:?}",
+                        character::party_levels(characters, party)
+                    ));
+
+                    ui.separator();
+
+                    ui.label("Class data file (empty for the built in classes):");
+                    ui.text_edit_singleline(&mut classes.path);
+                    if ui.button("load classes").clicked() {
+                        classes.load();
+                    }
+                    if let Some(err) = &classes.error {
+                        ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                    }
+
+                    ui.label("Spell data file (empty for the built in spells):");
+                    ui.text_edit_singleline(&mut compendium.path);
+                    if ui.button("load spells").clicked() {
+                        compendium.load();
+                    }
+                    if let Some(err) = &compendium.error {
+                        ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                    }
+                });
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let character = match characters
+                        .iter_mut()
+                        .find(|character| Some(character.id) == *selected_character_id)
+                    {
+                        Some(character) => character,
+                        None => {
+                            ui.heading("Select or make a character");
+                            return;
+                        }
+                    };
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        egui::Grid::new("character_identity").show(ui, |ui| {
+                            for (label, value) in [
+                                ("Name:", &mut character.name),
+                                ("Player:", &mut character.player),
+                                ("Race:", &mut character.race),
+                                ("Background:", &mut character.background),
+                            ] {
+                                ui.label(label);
+                                ui.text_edit_singleline(value);
+                                ui.end_row();
+                            }
+                        });
+                        ui.checkbox(&mut character.in_party, "in the party");
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            ui.heading(format!(
+                                "Level {}, proficiency bonus {:+}",
+                                character.level(),
+                                character.proficiency_bonus()
+                            ));
+                            if ui.button("level up").clicked() {
+                                level_up_windows.push(LevelUpMenu::new(id_next.next(), character));
+                            }
+                        });
+                        let multiclassed = character.classes.len() > 1;
+                        let mut classes_to_remove: Vec<usize> = vec![];
+                        egui::Grid::new("character_classes").show(ui, |ui| {
+                            for (i, class) in character.classes.iter_mut().enumerate() {
+                                ui.text_edit_singleline(&mut class.class);
+                                ui.add(egui::DragValue::new(&mut class.level).clamp_range(1..=20).prefix("level "));
+                                ui.add(egui::DragValue::new(&mut class.hit_die).clamp_range(4..=12).prefix("hit die d"));
+                                if multiclassed && ui.button("remove").clicked() {
+                                    classes_to_remove.push(i);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        for i in classes_to_remove.into_iter().rev() {
+                            character.classes.remove(i);
+                        }
+                        if ui.button("add class").clicked() {
+                            character.classes.push(ClassLevel {
+                                class: String::new(),
+                                level: 1,
+                                hit_die: 8,
+                            });
+                        }
+
+                        ui.separator();
+
+                        egui::Grid::new("character_abilities").striped(true).show(ui, |ui| {
+                            for ability in Ability::ALL {
+                                ui.label(ability.short());
+                                ui.add(egui::DragValue::new(character.abilities.score_mut(ability)).clamp_range(1..=30));
+                                ui.label(format!("{:+}", character.abilities.modifier(ability)));
+                                let mut proficient = character.saving_throws.contains(&ability);
+                                let save = format!("save {:+}", character.saving_throw(ability));
+                                if ui.checkbox(&mut proficient, save).changed() {
+                                    if proficient {
+                                        character.saving_throws.push(ability);
+                                    } else {
+                                        character.saving_throws.retain(|save| *save != ability);
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            ui.label("AC:");
+                            ui.add(egui::DragValue::new(&mut character.ac).clamp_range(0..=40));
+                            ui.label("Speed:");
+                            ui.add(egui::DragValue::new(&mut character.speed).clamp_range(0..=200));
+                            ui.label(format!("Initiative: {:+}", character.initiative_bonus()));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("HP:");
+                            let max_hp = character.max_hp;
+                            ui.add(egui::DragValue::new(&mut character.current_hp).clamp_range(0..=max_hp));
+                            ui.label("/");
+                            ui.add(egui::DragValue::new(&mut character.max_hp).clamp_range(1..=1000));
+                            ui.label("Temp:");
+                            ui.add(egui::DragValue::new(&mut character.temp_hp).clamp_range(0..=1000));
+                        });
+
+                        let hit_die_id = Id::new(format!("{}hit_die", character.id));
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Hit dice: {} of {} left",
+                                character.level() - character.hit_dice_used,
+                                character.level()
+                            ));
+                            if ui.button("spend hit die").clicked() {
+                                let message = match character.spend_hit_die() {
+                                    Ok(healed) => format!("healed {}", healed),
+                                    Err(err) => format!("error: {}", err),
+                                };
+                                ui.data().insert_temp(hit_die_id, message);
+                            }
+                        });
+                        let message = ui.data().get_temp::<String>(hit_die_id);
+                        if let Some(message) = message {
+                            ui.label(message);
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label("Death saves:");
+                            ui.add(egui::DragValue::new(&mut character.death_saves.successes).clamp_range(0..=3).suffix(" successes"));
+                            ui.add(egui::DragValue::new(&mut character.death_saves.failures).clamp_range(0..=3).suffix(" failures"));
+                        });
+
+                        if ui.button("long rest").clicked() {
+                            character.long_rest();
+                        }
+
+                        ui.separator();
+
+                        ui.collapsing("Spells", |ui| {
+                            ui.label(format!("Spell save DC {}", character.spell_save_dc()));
+                            let mut slots_to_remove: Vec<usize> = vec![];
+                            egui::Grid::new("character_spell_slots").show(ui, |ui| {
+                                for (i, slot) in character.spell_slots.iter_mut().enumerate() {
+                                    ui.label(format!("level {}", slot.level));
+                                    ui.add(egui::DragValue::new(&mut slot.used).clamp_range(0..=slot.max).suffix(" used"));
+                                    ui.add(egui::DragValue::new(&mut slot.max).clamp_range(0..=10).prefix("of "));
+                                    if ui.button("remove").clicked() {
+                                        slots_to_remove.push(i);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                            for i in slots_to_remove.into_iter().rev() {
+                                character.spell_slots.remove(i);
+                            }
+                            let next = character.spell_slots.iter().map(|slot| slot.level).max().unwrap_or(0) + 1;
+                            if next <= 9 && ui.button("add spell level").clicked() {
+                                character.spell_slots.push(SpellSlot {
+                                    level: next,
+                                    max: 1,
+                                    used: 0,
+                                });
+                            }
+
+                            ui.separator();
+                            spellbook_editor(ui, &mut character.spellbook, compendium);
+                        });
+
+                        ui.collapsing("Inventory", |ui| {
+                            let strength = character.abilities.score(Ability::Strength);
+                            let id = Id::new(format!("{}character_inventory", character.id));
+                            inventory_editor(ui, id, &mut character.inventory, Some((strength, &Size::Medium)));
+                        });
+
+                        ui.collapsing("Proficiencies", |ui| {
+                            let mut proficiencies_to_remove: Vec<usize> = vec![];
+                            for (i, proficiency) in character.proficiencies.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.text_edit_singleline(proficiency);
+                                    if ui.button("remove").clicked() {
+                                        proficiencies_to_remove.push(i);
+                                    }
+                                });
+                            }
+                            for i in proficiencies_to_remove.into_iter().rev() {
+                                character.proficiencies.remove(i);
+                            }
+                            if ui.button("add").clicked() {
+                                character.proficiencies.push(String::new());
+                            }
+                        });
+
+                        ui.collapsing("Level history", |ui| {
+                            for record in character.history.iter() {
+                                ui.label(record.summary());
+                            }
+                            if !character.history.is_empty() && ui.button("undo last level").clicked() {
+                                // the history isn't empty, so this can't fail
+                                let _ = level_up::undo_level(character);
+                            }
+                        });
+
+                        ui.collapsing("Features", |ui| {
+                            let mut features_to_remove: Vec<usize> = vec![];
+                            for (i, feature) in character.features.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.text_edit_singleline(&mut feature.name);
+                                    if ui.button("remove").clicked() {
+                                        features_to_remove.push(i);
+                                    }
+                                });
+                                ui.text_edit_multiline(&mut feature.description);
+                            }
+                            for i in features_to_remove.into_iter().rev() {
+                                character.features.remove(i);
+                            }
+                            if ui.button("add feature").clicked() {
+                                character.features.push(Feature {
+                                    name: String::new(),
+                                    description: String::new(),
+                                });
+                            }
+                        });
+                    });
+                });
+
+                let mut level_up_windows_to_remove: Vec<usize> = vec![];
+                for (i, window) in level_up_windows.iter_mut().enumerate() {
+                    let character = match characters
+                        .iter_mut()
+                        .find(|character| character.id == window.character_id)
+                    {
+                        Some(character) => character,
+                        None => {
+                            level_up_windows_to_remove.push(i);
+                            continue;
+                        }
+                    };
+                    egui::Window::new(format!("Level up {}", character.name))
+                        .id(Id::new(format!("{}level_up", window.id)))
+                        .show(ctx, |ui| {
+                            egui::ComboBox::from_label("class")
+                                .selected_text(window.class.clone())
+                                .show_ui(ui, |ui| {
+                                    for class in character.classes.iter() {
+                                        ui.selectable_value(
+                                            &mut window.class,
+                                            class.class.clone(),
+                                            format!("{} {}", class.class, class.level),
+                                        );
+                                    }
+                                    ui.separator();
+                                    for class in classes.classes.iter() {
+                                        ui.selectable_value(&mut window.class, class.name.clone(), &class.name);
+                                    }
+                                });
+
+                            let class_level = level_up::next_class_level(character, &window.class);
+                            let data = classes.get(&window.class);
+                            ui.label(format!(
+                                "Character level {}, {} level {}",
+                                character.level() + 1,
+                                window.class,
+                                class_level
+                            ));
+
+                            ui.horizontal(|ui| {
+                                ui.label("Hit points:");
+                                ui.selectable_value(&mut window.hp_method, HpMethod::Average, "average");
+                                ui.selectable_value(&mut window.hp_method, HpMethod::Roll, "roll");
+                            });
+
+                            if classes.improves_at(&window.class, class_level) {
+                                ui.horizontal(|ui| {
+                                    ui.label("Ability score improvement:");
+                                    for (n, increase) in window.increases.iter_mut().enumerate() {
+                                        egui::ComboBox::from_id_source(format!("{}increase{}", window.id, n))
+                                            .selected_text(format!("+1 {}", increase.short()))
+                                            .show_ui(ui, |ui| {
+                                                for ability in Ability::ALL {
+                                                    ui.selectable_value(increase, ability, ability.short());
+                                                }
+                                            });
+                                    }
+                                });
+                            }
+
+                            for feature in data.into_iter().flat_map(|data| data.features_at(class_level)) {
+                                ui.label(format!("{}: {}", feature.name, feature.description));
+                            }
+
+                            if let Some(err) = &window.error {
+                                ui.label(egui::RichText::new(format!("error: {}", err)).underline());
+                            }
+
+                            ui.horizontal(|ui| {
+                                if ui.button("level up").clicked() {
+                                    match level_up::level_up(
+                                        character,
+                                        classes,
+                                        &window.class,
+                                        window.hp_method,
+                                        &window.increases,
+                                    ) {
+                                        Ok(()) => level_up_windows_to_remove.push(i),
+                                        Err(err) => window.error = Some(err),
+                                    }
+                                }
+                                if ui.button("cancel").clicked() {
+                                    level_up_windows_to_remove.push(i);
+                                }
+                            });
+                        });
+                }
+                for i in level_up_windows_to_remove.into_iter().rev() {
+                    level_up_windows.remove(i);
+                }
+            }
+            Interface::Notes => {
+                egui::SidePanel::left("side_panel").show(ctx, |ui| {
+                    if ui.button("new note").clicked() {
+                        let note = Note::new(id_next.next(), "New note");
+                        notes_menu.selected_id = Some(note.id);
+                        notes_menu.editing = true;
+                        notes.push(note);
+                    }
+                    ui.add(egui::TextEdit::singleline(&mut notes_menu.search).hint_text("search, #tag"));
+                    ui.horizontal_wrapped(|ui| {
+                        ui.selectable_value(&mut notes_menu.tag, None, "all");
+                        for tag in notes::all_tags(notes) {
+                            ui.selectable_value(&mut notes_menu.tag, Some(tag.clone()), tag);
+                        }
+                    });
+
+                    ui.separator();
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for note in notes_menu.listed(notes) {
+                            let label = if note.pinned {
+                                format!("📌 {}", note.name())
+                            } else {
+                                note.name()
+                            };
+                            if ui.selectable_label(notes_menu.selected_id == Some(note.id), label).clicked() {
+                                notes_menu.selected_id = Some(note.id);
+                            }
+                        }
+                    });
+                });
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let mut note_to_delete: Option<usize> = None;
+                    let note = match notes.iter_mut().find(|note| Some(note.id) == notes_menu.selected_id) {
+                        Some(note) => note,
+                        None => {
+                            ui.heading("Select or make a note");
+                            return;
+                        }
+                    };
+
+                    ui.horizontal(|ui| {
+                        ui.label("Title:");
+                        ui.text_edit_singleline(&mut note.title);
+                        ui.checkbox(&mut note.pinned, "pinned");
+                        ui.selectable_value(&mut notes_menu.editing, false, "read");
+                        ui.selectable_value(&mut notes_menu.editing, true, "edit");
+                        if confirm_button(ui, Id::new(format!("{}delete_note", note.id)), "delete note") {
+                            history.label(format!("delete {}", note.name()));
+                            note_to_delete = Some(note.id);
+                        }
+                    });
+
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Tags:");
+                        let mut tags_to_remove: Vec<usize> = vec![];
+                        for (i, tag) in note.tags.iter().enumerate() {
+                            if ui.small_button(format!("{} x", tag)).clicked() {
+                                tags_to_remove.push(i);
+                            }
+                        }
+                        for i in tags_to_remove.into_iter().rev() {
+                            note.tags.remove(i);
+                        }
+                        let new_tag_id = Id::new(format!("{}new_note_tag", note.id));
+                        let mut new_tag = ui.data().get_temp_mut_or_default::<String>(new_tag_id).clone();
+                        let response = ui.add(egui::TextEdit::singleline(&mut new_tag).desired_width(80.0).hint_text("new tag"));
+                        if response.lost_focus() && !new_tag.trim().is_empty() {
+                            if !note.tags.contains(&new_tag.trim().to_string()) {
+                                note.tags.push(new_tag.trim().to_string());
+                            }
+                            new_tag.clear();
+                        }
+                        ui.data().insert_temp(new_tag_id, new_tag);
+                    });
+
+                    ui.separator();
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        if notes_menu.editing {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut note.text)
+                                    .desired_width(f32::INFINITY)
+                                    .desired_rows(20)
+                                    .hint_text("markdown: # headings, **bold**, *italic*, `code`, - lists, and [[Name]] to link a place or creature"),
+                            );
+                        } else if let Some(target) = markdown_view(ui, &note.text, places) {
+                            followed_link = Some(target);
+                        }
+                    });
+
+                    if let Some(id) = note_to_delete {
+                        notes.retain(|note| note.id != id);
+                        notes_menu.selected_id = None;
+                    }
+                });
+            }
+        }
+
+        if let Some(id) = open_note {
+            notes_menu.selected_id = Some(id);
+            *open_interface = Interface::Notes;
+        }
+        if let Some(target) = followed_link {
+            let place_id = match target {
+                LinkTarget::Place(place_id) => place_id,
+                LinkTarget::Creature { place_id, .. } => place_id,
+            };
+            *selected_place_id = place_id;
+            if !open_place_windows.contains(&place_id) {
+                open_place_windows.push(place_id);
+            }
+            if let LinkTarget::Creature { creature_id, .. } = target {
+                // the place window opens this creature's details the next time it's shown
+                ctx.data().insert_temp(Id::new(format!("{}followed_creature", place_id)), creature_id);
+            }
+            *open_interface = Interface::CreatureCreation;
+        }
+
+        if let Some(steps) = history_step {
+            self.go_to(steps);
+        }
+        let now = ctx.input().time as u64;
+        if let Some(action) = file_action.and_then(|action| self.campaign.go_ahead(action)).or(confirmed) {
+            self.file_action(action, now);
+        }
+        if self.campaign.autosave_due(now) {
+            self.file_action(FileAction::Save, now);
+        }
+        if self.campaign.autosave_minutes > 0 {
+            ctx.request_repaint_after(std::time::Duration::from_secs(60));
+        }
+
+        // comparing means serializing the whole campaign, so it's only done on frames with input
+        // that aren't in the middle of a drag or of typing. a drag or an edit in a text field is
+        // compared once it's over, as one change
+        let had_input = ctx.input().events.iter().any(|event| {
+            !matches!(event, egui::Event::PointerMoved(_) | egui::Event::Scroll(_))
+        });
+        let busy = ctx.memory().is_anything_being_dragged() || ctx.memory().focus().is_some();
+        if (had_input && !busy) || !self.history.started() {
+            let state = self.tracked_state();
+            if self.history.record(state) {
+                self.campaign.unsaved = true;
             }
         }
     }
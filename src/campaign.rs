@@ -0,0 +1,217 @@
+// campaign files: saving and opening whole campaigns, recent files and autosave
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// How many recent files the File menu remembers.
+const MAX_RECENT: usize = 8;
+
+/// How many older versions of a campaign file are kept next to it, `campaign.json.1` being the
+/// newest.
+pub const BACKUPS: usize = 3;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// Something to do with campaign files, picked in the File menu.
+pub enum FileAction {
+    New,
+    Open(String),
+    Save,
+    SaveAs(String),
+    /// Writes the whole campaign into the export window.
+    Export,
+    /// Replaces the whole campaign with the document in the export window.
+    Replace,
+}
+
+impl FileAction {
+    /// Whether the action puts another campaign in place of the open one.
+    pub fn replaces_campaign(&self) -> bool {
+        matches!(
+            self,
+            FileAction::New | FileAction::Open(_) | FileAction::Replace
+        )
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The campaign file being worked on and the File menu settings, which are kept apart from the
+/// campaigns themselves.
+///
+/// Properties:
+///
+/// * `path`: The campaign file, `None` until the campaign is saved or one is opened.
+/// * `path_input`: The path typed into the File menu for Open and Save As.
+/// * `recent`: Recently opened or saved files, newest first.
+/// * `autosave_minutes`: How often the campaign is saved to its file, 0 to never autosave.
+/// * `last_saved`: When the campaign was last saved, in seconds since the app started.
+/// * `message`: What happened on the latest save or open.
+/// * `unsaved`: Whether the campaign changed since it was last saved or opened.
+/// * `pending`: An action that would lose unsaved changes, waiting for them to be discarded.
+pub struct CampaignFiles {
+    pub path: Option<String>,
+    pub path_input: String,
+    pub recent: Vec<String>,
+    pub autosave_minutes: u64,
+    #[serde(skip)]
+    pub last_saved: Option<u64>,
+    pub message: Option<String>,
+    pub unsaved: bool,
+    #[serde(skip)]
+    pub pending: Option<FileAction>,
+}
+
+impl CampaignFiles {
+    /// The file name of the campaign, for the menu bar.
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => std::path::Path::new(path)
+                .file_name()
+                .map_or(path.clone(), |name| name.to_string_lossy().to_string()),
+            None => "unsaved campaign".to_string(),
+        }
+    }
+
+    /// The name of the campaign with a `*` when it has unsaved changes.
+    pub fn title(&self) -> String {
+        match self.unsaved {
+            true => format!("{} *", self.name()),
+            false => self.name(),
+        }
+    }
+
+    /// Whether `action` can be run now. actions that would lose unsaved changes are kept in
+    /// `pending` until the changes are discarded, and `None` is returned.
+    pub fn go_ahead(&mut self, action: FileAction) -> Option<FileAction> {
+        if self.unsaved && action.replaces_campaign() {
+            self.pending = Some(action);
+            return None;
+        }
+        Some(action)
+    }
+
+    /// Makes `path` the open campaign file and the newest recent file.
+    pub fn remember(&mut self, path: &str) {
+        self.path = Some(path.to_string());
+        self.path_input = path.to_string();
+        self.recent.retain(|recent| recent != path);
+        self.recent.insert(0, path.to_string());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Whether it is time to autosave unsaved changes, `now` being seconds since the app started.
+    /// the first check starts the clock, and a due autosave restarts it even if saving fails, so
+    /// a failing save is only retried at the next interval.
+    pub fn autosave_due(&mut self, now: u64) -> bool {
+        let last_saved = *self.last_saved.get_or_insert(now);
+        let due = self.path.is_some()
+            && self.unsaved
+            && self.autosave_minutes > 0
+            && now >= last_saved + self.autosave_minutes * 60;
+        if due {
+            self.last_saved = Some(now);
+        }
+        due
+    }
+}
+
+/// Reads a campaign file.
+pub fn read<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let json = std::fs::read_to_string(path.trim()).map_err(|err| format!("{}: {}", path, err))?;
+    serde_json::from_str(&json).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Writes a campaign file, keeping the versions it replaces as backups.
+pub fn write<T: Serialize>(path: &str, campaign: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(campaign).map_err(|err| err.to_string())?;
+    write_text(path, &json)
+}
+
+/// Writes text to a file the same way campaigns are written.
+pub fn write_text(path: &str, text: &str) -> Result<(), String> {
+    write_file(path.trim(), text).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// The path of the `n`th backup of `path`.
+pub fn backup_path(path: &str, n: usize) -> String {
+    format!("{}.{}", path, n)
+}
+
+/// Writes to a temporary file first and renames it over `path`, so a crash mid write never leaves
+/// half a campaign. the old file moves down the backups, and the oldest backup is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_file(path: &str, contents: &str) -> std::io::Result<()> {
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    let temporary = format!("{}.tmp", path);
+    {
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+    if Path::new(path).exists() {
+        for n in (1..BACKUPS).rev() {
+            if Path::new(&backup_path(path, n)).exists() {
+                fs::rename(backup_path(path, n), backup_path(path, n + 1))?;
+            }
+        }
+        fs::copy(path, backup_path(path, 1))?;
+    }
+    fs::rename(&temporary, path)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_file(path: &str, contents: &str) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+#[test]
+fn test_campaign_files() {
+    let dir = std::env::temp_dir().join(format!("dnd_tool_campaign_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("campaign.json").to_string_lossy().to_string();
+
+    for version in 1..=5 {
+        write(&path, &vec![version]).unwrap();
+    }
+    assert_eq!(read::<Vec<i32>>(&path).unwrap(), vec![5]);
+    assert_eq!(read::<Vec<i32>>(&backup_path(&path, 1)).unwrap(), vec![4]);
+    assert_eq!(read::<Vec<i32>>(&backup_path(&path, 3)).unwrap(), vec![2]);
+    assert!(!std::path::Path::new(&backup_path(&path, 4)).exists());
+    assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+    assert!(read::<Vec<i32>>(&dir.join("missing.json").to_string_lossy()).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut files = CampaignFiles {
+        autosave_minutes: 5,
+        ..Default::default()
+    };
+    files.remember("a.json");
+    files.remember("b.json");
+    files.remember("a.json");
+    assert_eq!(files.recent, vec!["a.json", "b.json"]);
+    assert_eq!(files.name(), "a.json");
+    assert!(!files.autosave_due(10));
+    // without changes there is nothing to autosave
+    assert!(!files.autosave_due(310));
+    files.unsaved = true;
+    assert!(!files.autosave_due(309));
+    assert!(files.autosave_due(310));
+    // a save that failed waits for the next interval
+    assert!(!files.autosave_due(311));
+    assert!(files.autosave_due(610));
+    files.unsaved = false;
+
+    // opening another campaign waits until unsaved changes are discarded
+    assert_eq!(files.go_ahead(FileAction::New), Some(FileAction::New));
+    files.unsaved = true;
+    assert_eq!(files.title(), "a.json *");
+    let open = FileAction::Open("b.json".to_string());
+    assert_eq!(files.go_ahead(open.clone()), None);
+    assert_eq!(files.pending, Some(open));
+    assert_eq!(files.go_ahead(FileAction::Save), Some(FileAction::Save));
+}
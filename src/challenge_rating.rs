@@ -0,0 +1,407 @@
+// challenge rating and encounter difficulty maths, following the tables in the 5e dungeon masters
+// guide
+
+use crate::structure::Creature;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// A row of the "monster statistics by challenge rating" table.
+///
+/// Properties:
+///
+/// * `label`: How the challenge rating is written, e.g. `1/4`.
+/// * `value`: The numeric value of the challenge rating, used when averaging.
+/// * `proficiency_bonus`: The proficiency bonus of a creature with this rating.
+/// * `ac`: The expected armour class.
+/// * `hp`: The inclusive hit point range.
+/// * `attack_bonus`: The expected attack bonus.
+/// * `damage_per_round`: The inclusive damage per round range.
+/// * `save_dc`: The expected save dc.
+/// * `xp`: The experience the creature is worth.
+pub struct CrRow {
+    pub label: &'static str,
+    pub value: f32,
+    pub proficiency_bonus: i32,
+    pub ac: i32,
+    pub hp: (i32, i32),
+    pub attack_bonus: i32,
+    pub damage_per_round: (i32, i32),
+    pub save_dc: i32,
+    pub xp: u32,
+}
+
+macro_rules! cr_row {
+    (
+        $label:expr, $value:expr, $prof:expr, $ac:expr, $hp:expr, $atk:expr, $dpr:expr, $dc:expr,
+        $xp:expr
+    ) => {
+        CrRow {
+            label: $label,
+            value: $value,
+            proficiency_bonus: $prof,
+            ac: $ac,
+            hp: $hp,
+            attack_bonus: $atk,
+            damage_per_round: $dpr,
+            save_dc: $dc,
+            xp: $xp,
+        }
+    };
+}
+
+pub const CR_TABLE: [CrRow; 34] = [
+    cr_row!("0", 0.0, 2, 13, (1, 6), 3, (0, 1), 13, 10),
+    cr_row!("1/8", 0.125, 2, 13, (7, 35), 3, (2, 3), 13, 25),
+    cr_row!("1/4", 0.25, 2, 13, (36, 49), 3, (4, 5), 13, 50),
+    cr_row!("1/2", 0.5, 2, 13, (50, 70), 3, (6, 8), 13, 100),
+    cr_row!("1", 1.0, 2, 13, (71, 85), 3, (9, 14), 13, 200),
+    cr_row!("2", 2.0, 2, 13, (86, 100), 3, (15, 20), 13, 450),
+    cr_row!("3", 3.0, 2, 13, (101, 115), 4, (21, 26), 13, 700),
+    cr_row!("4", 4.0, 2, 14, (116, 130), 5, (27, 32), 14, 1100),
+    cr_row!("5", 5.0, 3, 15, (131, 145), 6, (33, 38), 15, 1800),
+    cr_row!("6", 6.0, 3, 15, (146, 160), 6, (39, 44), 15, 2300),
+    cr_row!("7", 7.0, 3, 15, (161, 175), 6, (45, 50), 15, 2900),
+    cr_row!("8", 8.0, 3, 16, (176, 190), 7, (51, 56), 16, 3900),
+    cr_row!("9", 9.0, 4, 16, (191, 205), 7, (57, 62), 16, 5000),
+    cr_row!("10", 10.0, 4, 17, (206, 220), 7, (63, 68), 16, 5900),
+    cr_row!("11", 11.0, 4, 17, (221, 235), 8, (69, 74), 17, 7200),
+    cr_row!("12", 12.0, 4, 17, (236, 250), 8, (75, 80), 17, 8400),
+    cr_row!("13", 13.0, 5, 18, (251, 265), 8, (81, 86), 18, 10000),
+    cr_row!("14", 14.0, 5, 18, (266, 280), 8, (87, 92), 18, 11500),
+    cr_row!("15", 15.0, 5, 18, (281, 295), 8, (93, 98), 18, 13000),
+    cr_row!("16", 16.0, 5, 18, (296, 310), 9, (99, 104), 18, 15000),
+    cr_row!("17", 17.0, 6, 19, (311, 325), 10, (105, 110), 19, 18000),
+    cr_row!("18", 18.0, 6, 19, (326, 340), 10, (111, 116), 19, 20000),
+    cr_row!("19", 19.0, 6, 19, (341, 355), 10, (117, 122), 19, 22000),
+    cr_row!("20", 20.0, 6, 19, (356, 400), 10, (123, 140), 19, 25000),
+    cr_row!("21", 21.0, 7, 19, (401, 445), 11, (141, 158), 20, 33000),
+    cr_row!("22", 22.0, 7, 19, (446, 490), 11, (159, 176), 20, 41000),
+    cr_row!("23", 23.0, 7, 19, (491, 535), 11, (177, 194), 20, 50000),
+    cr_row!("24", 24.0, 7, 19, (536, 580), 12, (195, 212), 21, 62000),
+    cr_row!("25", 25.0, 8, 19, (581, 625), 12, (213, 230), 21, 75000),
+    cr_row!("26", 26.0, 8, 19, (626, 670), 12, (231, 248), 21, 90000),
+    cr_row!("27", 27.0, 8, 19, (671, 715), 13, (249, 266), 22, 105000),
+    cr_row!("28", 28.0, 8, 19, (716, 760), 13, (267, 284), 22, 120000),
+    cr_row!("29", 29.0, 9, 19, (761, 805), 13, (285, 302), 22, 135000),
+    cr_row!("30", 30.0, 9, 19, (806, 850), 14, (303, 320), 23, 155000),
+];
+
+/// XP thresholds per character level, as `[easy, medium, hard, deadly]`. index 0 is level 1.
+pub const XP_THRESHOLDS: [[u32; 4]; 20] = [
+    [25, 50, 75, 100],
+    [50, 100, 150, 200],
+    [75, 150, 225, 400],
+    [125, 250, 375, 500],
+    [250, 500, 750, 1100],
+    [300, 600, 900, 1400],
+    [350, 750, 1100, 1700],
+    [450, 900, 1400, 2100],
+    [550, 1100, 1600, 2400],
+    [600, 1200, 1900, 2800],
+    [800, 1600, 2400, 3600],
+    [1000, 2000, 3000, 4500],
+    [1100, 2200, 3400, 5100],
+    [1250, 2500, 3800, 5700],
+    [1400, 2800, 4300, 6400],
+    [1600, 3200, 4800, 7200],
+    [2000, 3900, 5900, 8800],
+    [2100, 4200, 6300, 9500],
+    [2400, 4900, 7300, 10900],
+    [2800, 5700, 8500, 12700],
+];
+
+/// Encounter multipliers in tenths, so `15` is x1.5. the first and last entries are only reachable
+/// through the party size adjustment.
+const ENCOUNTER_MULTIPLIERS: [u32; 8] = [5, 10, 15, 20, 25, 30, 40, 50];
+
+#[derive(Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq)]
+/// A challenge rating, stored as an index into `CR_TABLE`.
+pub struct ChallengeRating(usize);
+
+impl ChallengeRating {
+    pub fn row(&self) -> &'static CrRow {
+        &CR_TABLE[self.0]
+    }
+
+    pub fn label(&self) -> &'static str {
+        self.row().label
+    }
+
+    pub fn xp(&self) -> u32 {
+        self.row().xp
+    }
+
+    /// Moves the rating up (or down for negative steps) the table, staying inside it.
+    fn shifted(&self, steps: i32) -> Self {
+        Self((self.0 as i32 + steps).clamp(0, CR_TABLE.len() as i32 - 1) as usize)
+    }
+
+    /// The rating whose numeric value is closest to `value`.
+    fn nearest(value: f32) -> Self {
+        let mut best = 0;
+        for (i, row) in CR_TABLE.iter().enumerate() {
+            if (row.value - value).abs() < (CR_TABLE[best].value - value).abs() {
+                best = i;
+            }
+        }
+        Self(best)
+    }
+
+    /// The first rating whose range (picked by `range`) reaches `amount`.
+    fn by_range(amount: i32, range: fn(&CrRow) -> (i32, i32)) -> Self {
+        Self(
+            CR_TABLE
+                .iter()
+                .position(|row| amount <= range(row).1)
+                .unwrap_or(CR_TABLE.len() - 1),
+        )
+    }
+}
+
+impl Display for ChallengeRating {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CR {}", self.label())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq)]
+/// The estimated challenge rating of a creature.
+///
+/// Properties:
+///
+/// * `defensive`: The rating from hit points, adjusted by armour class.
+/// * `offensive`: The rating from damage per round, adjusted by attack bonus.
+/// * `overall`: The average of the defensive and offensive ratings.
+pub struct CreatureRating {
+    pub defensive: ChallengeRating,
+    pub offensive: ChallengeRating,
+    pub overall: ChallengeRating,
+}
+
+impl CreatureRating {
+    pub fn new(creature: &Creature) -> Self {
+        Self::from_stats(
+            creature.hp,
+            creature.ac,
+            creature.damage_per_round,
+            creature.attack_bonus,
+        )
+    }
+
+    pub fn from_stats(hp: i32, ac: i32, damage_per_round: i32, attack_bonus: i32) -> Self {
+        // every 2 points of ac or attack bonus away from the expected value moves the rating by one
+        // step
+        let defensive = ChallengeRating::by_range(hp, |row| row.hp);
+        let defensive = defensive.shifted((ac - defensive.row().ac) / 2);
+
+        let offensive = ChallengeRating::by_range(damage_per_round, |row| row.damage_per_round);
+        let offensive = offensive.shifted((attack_bonus - offensive.row().attack_bonus) / 2);
+
+        let overall =
+            ChallengeRating::nearest((defensive.row().value + offensive.row().value) / 2.0);
+
+        Self {
+            defensive,
+            offensive,
+            overall,
+        }
+    }
+}
+
+impl Display for CreatureRating {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} XP); defensive {}, offensive {}",
+            self.overall,
+            self.overall.xp(),
+            self.defensive.label(),
+            self.offensive.label()
+        )
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// How hard an encounter is for a party.
+pub enum Difficulty {
+    Trivial,
+    Easy,
+    Medium,
+    Hard,
+    Deadly,
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Trivial => write!(f, "Trivial"),
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Medium => write!(f, "Medium"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Deadly => write!(f, "Deadly"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The result of an encounter difficulty calculation, kept around so the maths can be shown.
+///
+/// Properties:
+///
+/// * `monster_xp`: The xp of every creature in the encounter.
+/// * `party_levels`: The level of every character in the party.
+/// * `base_xp`: The sum of `monster_xp`.
+/// * `multiplier`: The encounter multiplier in tenths.
+/// * `adjusted_xp`: `base_xp` times the multiplier.
+/// * `thresholds`: The party's summed `[easy, medium, hard, deadly]` thresholds.
+/// * `difficulty`: The resulting difficulty.
+pub struct EncounterDifficulty {
+    pub monster_xp: Vec<u32>,
+    pub party_levels: Vec<i32>,
+    pub base_xp: u32,
+    pub multiplier: u32,
+    pub adjusted_xp: u32,
+    pub thresholds: [u32; 4],
+    pub difficulty: Difficulty,
+}
+
+impl EncounterDifficulty {
+    pub fn calculate(monster_xp: Vec<u32>, party_levels: Vec<i32>) -> Self {
+        let base_xp = monster_xp.iter().sum();
+        let multiplier = encounter_multiplier(monster_xp.len(), party_levels.len());
+        let adjusted_xp = base_xp * multiplier / 10;
+
+        let mut thresholds = [0; 4];
+        for level in &party_levels {
+            let row = XP_THRESHOLDS[(*level).clamp(1, 20) as usize - 1];
+            for (threshold, xp) in thresholds.iter_mut().zip(row) {
+                *threshold += xp;
+            }
+        }
+
+        let difficulty = if adjusted_xp >= thresholds[3] {
+            Difficulty::Deadly
+        } else if adjusted_xp >= thresholds[2] {
+            Difficulty::Hard
+        } else if adjusted_xp >= thresholds[1] {
+            Difficulty::Medium
+        } else if adjusted_xp >= thresholds[0] {
+            Difficulty::Easy
+        } else {
+            Difficulty::Trivial
+        };
+
+        Self {
+            monster_xp,
+            party_levels,
+            base_xp,
+            multiplier,
+            adjusted_xp,
+            thresholds,
+            difficulty,
+        }
+    }
+
+    /// Rates the creatures of a place (or any other group) against the levels of a party.
+    pub fn of_creatures(creatures: &[Creature], party_levels: &[i32]) -> Self {
+        Self::calculate(
+            creatures
+                .iter()
+                .map(|creature| creature.challenge_rating().overall.xp())
+                .collect(),
+            party_levels.to_vec(),
+        )
+    }
+}
+
+impl Display for EncounterDifficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let xp = self
+            .monster_xp
+            .iter()
+            .map(|xp| xp.to_string())
+            .collect::<Vec<_>>()
+            .join(" + ");
+        writeln!(
+            f,
+            "{} creatures: {} = {} XP",
+            self.monster_xp.len(),
+            if xp.is_empty() { "0".to_string() } else { xp },
+            self.base_xp
+        )?;
+        writeln!(
+            f,
+            "x{}.{} encounter multiplier = {} adjusted XP",
+            self.multiplier / 10,
+            self.multiplier % 10,
+            self.adjusted_xp
+        )?;
+        writeln!(
+            f,
+            "party of {} (levels {:?}): easy {}, medium {}, hard {}, deadly {}",
+            self.party_levels.len(),
+            self.party_levels,
+            self.thresholds[0],
+            self.thresholds[1],
+            self.thresholds[2],
+            self.thresholds[3]
+        )?;
+        write!(f, "difficulty: {}", self.difficulty)
+    }
+}
+
+/// The encounter multiplier (in tenths) for a number of creatures, shifted up a step for parties
+/// smaller than 3 and down a step for parties of 6 or more.
+pub fn encounter_multiplier(creature_count: usize, party_size: usize) -> u32 {
+    let step: usize = match creature_count {
+        0 | 1 => 1,
+        2 => 2,
+        3..=6 => 3,
+        7..=10 => 4,
+        11..=14 => 5,
+        _ => 6,
+    };
+    let step = if party_size < 3 {
+        step + 1
+    } else if party_size >= 6 {
+        step - 1
+    } else {
+        step
+    };
+    ENCOUNTER_MULTIPLIERS[step]
+}
+
+#[test]
+fn test_creature_rating() {
+    let creature = Creature {
+        hp: 110,
+        ac: 13,
+        damage_per_round: 22,
+        attack_bonus: 4,
+        ..Default::default()
+    };
+    let rating = CreatureRating::new(&creature);
+    assert_eq!(rating.defensive.label(), "3");
+    assert_eq!(rating.offensive.label(), "3");
+    assert_eq!(rating.overall.xp(), 700);
+
+    // high armour class pushes the defensive rating up
+    let creature = Creature { ac: 17, ..creature };
+    assert_eq!(CreatureRating::new(&creature).defensive.label(), "5");
+}
+
+#[test]
+fn test_encounter_difficulty() {
+    // 4 level 3 characters against 3 creatures worth 100 xp each
+    let encounter = EncounterDifficulty::calculate(vec![100, 100, 100], vec![3; 4]);
+    assert_eq!(encounter.base_xp, 300);
+    assert_eq!(encounter.multiplier, 20);
+    assert_eq!(encounter.adjusted_xp, 600);
+    assert_eq!(encounter.thresholds, [300, 600, 900, 1600]);
+    assert_eq!(encounter.difficulty, Difficulty::Medium);
+
+    assert_eq!(encounter_multiplier(1, 2), 15);
+    assert_eq!(encounter_multiplier(15, 6), 30);
+}
@@ -0,0 +1,386 @@
+// player character sheets
+
+use crate::formulaic_dice_roll::roll;
+use crate::inventory::Inventory;
+use crate::level_up::LevelRecord;
+use crate::structure::Party;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// The six ability scores.
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+impl Ability {
+    pub const ALL: [Ability; 6] = [
+        Ability::Strength,
+        Ability::Dexterity,
+        Ability::Constitution,
+        Ability::Intelligence,
+        Ability::Wisdom,
+        Ability::Charisma,
+    ];
+
+    /// The three letter abbreviation, e.g. `STR`.
+    pub fn short(&self) -> &'static str {
+        match self {
+            Ability::Strength => "STR",
+            Ability::Dexterity => "DEX",
+            Ability::Constitution => "CON",
+            Ability::Intelligence => "INT",
+            Ability::Wisdom => "WIS",
+            Ability::Charisma => "CHA",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A character's ability scores, in the order of `Ability::ALL`.
+pub struct AbilityScores(pub [i32; 6]);
+
+impl Default for AbilityScores {
+    fn default() -> Self {
+        Self([10; 6])
+    }
+}
+
+impl AbilityScores {
+    pub fn score(&self, ability: Ability) -> i32 {
+        self.0[ability as usize]
+    }
+
+    pub fn score_mut(&mut self, ability: Ability) -> &mut i32 {
+        &mut self.0[ability as usize]
+    }
+
+    /// The modifier of a score, e.g. +2 for 14 and -1 for 8.
+    pub fn modifier(&self, ability: Ability) -> i32 {
+        (self.score(ability) - 10).div_euclid(2)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The levels a character has in one class.
+///
+/// Properties:
+///
+/// * `class`: The name of the class.
+/// * `level`: How many levels the character has in the class.
+/// * `hit_die`: The size of the class' hit die, e.g. 10 for a d10.
+pub struct ClassLevel {
+    pub class: String,
+    pub level: i32,
+    pub hit_die: i32,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+/// The death saving throws of a character at 0 hp.
+pub struct DeathSaves {
+    pub successes: u8,
+    pub failures: u8,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The spell slots of one spell level.
+///
+/// Properties:
+///
+/// * `level`: The spell level, 1 to 9.
+/// * `max`: How many slots a long rest restores.
+/// * `used`: How many slots have been spent.
+pub struct SpellSlot {
+    pub level: u32,
+    pub max: u32,
+    pub used: u32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A class feature, racial trait or feat.
+///
+/// Properties:
+///
+/// * `name`: The name of the feature.
+/// * `description`: What the feature does.
+pub struct Feature {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
+/// A player character's sheet.
+///
+/// Properties:
+///
+/// * `id`: The id of the character.
+/// * `name`: The name of the character.
+/// * `player`: The name of the player.
+/// * `race`: The race of the character.
+/// * `background`: The background of the character.
+/// * `classes`: Every class the character has levels in, the first one being the starting class.
+/// * `abilities`: The ability scores.
+/// * `saving_throws`: The abilities the character is proficient in saving throws of.
+/// * `proficiencies`: Skills, tools, weapons, armour and languages.
+/// * `ac`: Armour class.
+/// * `speed`: Walking speed in feet.
+/// * `max_hp`: Maximum hit points.
+/// * `current_hp`: Current hit points.
+/// * `temp_hp`: Temporary hit points.
+/// * `hit_dice_used`: How many hit dice have been spent since the last long rest.
+/// * `death_saves`: The death saving throws made since dropping to 0 hp.
+/// * `spell_slots`: The spell slots of each spell level the character has.
+/// * `spellbook`: The names of the compendium spells the character knows.
+/// * `inventory`: The items and coins the character carries.
+/// * `features`: Class features, racial traits and feats.
+/// * `in_party`: Whether the character counts towards encounter difficulty.
+/// * `history`: Every level gained with the level up wizard, oldest first.
+pub struct PlayerCharacter {
+    pub id: usize,
+    pub name: String,
+    pub player: String,
+    pub race: String,
+    pub background: String,
+    pub classes: Vec<ClassLevel>,
+    pub abilities: AbilityScores,
+    pub saving_throws: Vec<Ability>,
+    pub proficiencies: Vec<String>,
+    pub ac: i32,
+    pub speed: i32,
+    pub max_hp: i32,
+    pub current_hp: i32,
+    pub temp_hp: i32,
+    pub hit_dice_used: i32,
+    pub death_saves: DeathSaves,
+    pub spell_slots: Vec<SpellSlot>,
+    pub spellbook: Vec<String>,
+    pub inventory: Inventory,
+    pub features: Vec<Feature>,
+    pub in_party: bool,
+    pub history: Vec<LevelRecord>,
+}
+
+impl Default for PlayerCharacter {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            player: String::new(),
+            race: "Human".to_string(),
+            background: String::new(),
+            classes: vec![ClassLevel {
+                class: "Fighter".to_string(),
+                level: 1,
+                hit_die: 10,
+            }],
+            abilities: AbilityScores::default(),
+            saving_throws: vec![],
+            proficiencies: vec![],
+            ac: 10,
+            speed: 30,
+            max_hp: 10,
+            current_hp: 10,
+            temp_hp: 0,
+            hit_dice_used: 0,
+            death_saves: DeathSaves::default(),
+            spell_slots: vec![],
+            spellbook: vec![],
+            inventory: Inventory::default(),
+            features: vec![],
+            in_party: true,
+            history: vec![],
+        }
+    }
+}
+
+impl PlayerCharacter {
+    pub fn new(id: usize, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The total level over every class.
+    pub fn level(&self) -> i32 {
+        self.classes
+            .iter()
+            .map(|class| class.level)
+            .sum::<i32>()
+            .max(1)
+    }
+
+    /// The proficiency bonus, which goes up every 4 levels.
+    pub fn proficiency_bonus(&self) -> i32 {
+        2 + (self.level() - 1) / 4
+    }
+
+    pub fn initiative_bonus(&self) -> i32 {
+        self.abilities.modifier(Ability::Dexterity)
+    }
+
+    /// The bonus to saving throws of `ability`, including proficiency.
+    pub fn saving_throw(&self, ability: Ability) -> i32 {
+        let proficiency = if self.saving_throws.contains(&ability) {
+            self.proficiency_bonus()
+        } else {
+            0
+        };
+        self.abilities.modifier(ability) + proficiency
+    }
+
+    /// Spends a hit die, healing by a roll of it plus the constitution modifier. the biggest hit
+    /// die is spent first.
+    ///
+    /// Returns:
+    ///
+    /// The hit points healed, or an error when every hit die is spent.
+    pub fn spend_hit_die(&mut self) -> Result<i32, String> {
+        if self.hit_dice_used >= self.level() {
+            return Err(format!("{} has no hit dice left", self.name));
+        }
+        let die = self
+            .classes
+            .iter()
+            .map(|class| class.hit_die)
+            .max()
+            .unwrap_or(8);
+        let healed = (roll(&format!("1d{}", die))?
+            + self.abilities.modifier(Ability::Constitution) as i64)
+            .max(0) as i32;
+        self.hit_dice_used += 1;
+        self.current_hp = (self.current_hp + healed).min(self.max_hp);
+        if self.current_hp > 0 {
+            self.death_saves = DeathSaves::default();
+        }
+        Ok(healed)
+    }
+
+    /// 8 + proficiency bonus + the best of the intelligence, wisdom and charisma modifiers.
+    pub fn spell_save_dc(&self) -> i32 {
+        let casting = [Ability::Intelligence, Ability::Wisdom, Ability::Charisma]
+            .into_iter()
+            .map(|ability| self.abilities.modifier(ability))
+            .max()
+            .unwrap_or(0);
+        8 + self.proficiency_bonus() + casting
+    }
+
+    /// Restores hit points, spell slots and half of the spent hit dice.
+    pub fn long_rest(&mut self) {
+        self.current_hp = self.max_hp;
+        self.temp_hp = 0;
+        self.hit_dice_used = (self.hit_dice_used - (self.level() / 2).max(1)).max(0);
+        self.death_saves = DeathSaves::default();
+        for slot in self.spell_slots.iter_mut() {
+            slot.used = 0;
+        }
+    }
+}
+
+impl Display for PlayerCharacter {
+    /// e.g. `Tordek, Dwarf Fighter 3 / Cleric 1`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let classes: Vec<String> = self
+            .classes
+            .iter()
+            .map(|class| format!("{} {}", class.class, class.level))
+            .collect();
+        write!(f, "{}, {} {}", self.name, self.race, classes.join(" / "))
+    }
+}
+
+/// Spends the lowest unused spell slot of at least `level`. cantrips don't need one.
+///
+/// Returns:
+///
+/// The level of the slot spent, or an error when there is none left.
+pub fn spend_slot(slots: &mut [SpellSlot], level: u32) -> Result<u32, String> {
+    if level == 0 {
+        return Ok(0);
+    }
+    let mut open: Vec<&mut SpellSlot> = slots
+        .iter_mut()
+        .filter(|slot| slot.level >= level && slot.used < slot.max)
+        .collect();
+    open.sort_by_key(|slot| slot.level);
+    match open.into_iter().next() {
+        Some(slot) => {
+            slot.used += 1;
+            Ok(slot.level)
+        }
+        None => Err(format!("no spell slots of level {} or higher left", level)),
+    }
+}
+
+/// The levels encounters are measured against: every character in the party, or the party size
+/// and level settings when there are no characters in it.
+pub fn party_levels(characters: &[PlayerCharacter], party: &Party) -> Vec<i32> {
+    let levels: Vec<i32> = characters
+        .iter()
+        .filter(|character| character.in_party)
+        .map(|character| character.level())
+        .collect();
+    if levels.is_empty() {
+        party.levels()
+    } else {
+        levels
+    }
+}
+
+#[test]
+fn test_player_character() {
+    let mut tordek = PlayerCharacter::new(1, "Tordek");
+    tordek.race = "Dwarf".to_string();
+    tordek.classes[0].level = 3;
+    tordek.classes.push(ClassLevel {
+        class: "Cleric".to_string(),
+        level: 2,
+        hit_die: 8,
+    });
+    *tordek.abilities.score_mut(Ability::Constitution) = 16;
+    *tordek.abilities.score_mut(Ability::Dexterity) = 9;
+    tordek.saving_throws = vec![Ability::Constitution];
+    tordek.max_hp = 44;
+    tordek.current_hp = 10;
+
+    assert_eq!(tordek.to_string(), "Tordek, Dwarf Fighter 3 / Cleric 2");
+    assert_eq!((tordek.level(), tordek.proficiency_bonus()), (5, 3));
+    assert_eq!(tordek.initiative_bonus(), -1);
+    assert_eq!(tordek.saving_throw(Ability::Constitution), 6);
+
+    let healed = tordek.spend_hit_die().unwrap();
+    assert!((4..=13).contains(&healed));
+    assert_eq!(tordek.current_hp, 10 + healed);
+    tordek.hit_dice_used = 5;
+    assert!(tordek.spend_hit_die().is_err());
+    tordek.long_rest();
+    assert_eq!((tordek.current_hp, tordek.hit_dice_used), (44, 3));
+
+    let mut benched = PlayerCharacter::new(2, "Lidda");
+    benched.in_party = false;
+    let party = Party::default();
+    assert_eq!(party_levels(&[benched.clone()], &party), vec![1; 4]);
+    assert_eq!(party_levels(&[tordek.clone(), benched], &party), vec![5]);
+
+    // combat damage ends up back on the sheet
+    let mut tracker = crate::combat::CombatTracker::default();
+    tracker
+        .combatants
+        .push(crate::combat::Combatant::from_character(7, &tordek));
+    tracker.damage(7, "10").unwrap();
+    let mut characters = vec![tordek];
+    tracker.end(&mut characters);
+    assert_eq!(characters[0].current_hp, 34);
+}
@@ -0,0 +1,754 @@
+// initiative order and turn tracking for combat
+
+use crate::character::{spend_slot, PlayerCharacter, SpellSlot};
+use crate::conditions::{concentration_dc, tick_effects, Effect, Tick};
+use crate::formulaic_dice_roll::roll;
+use crate::spells::SpellData;
+use crate::structure::Creature;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// What a combatant is doing with its turn.
+pub enum TurnStatus {
+    Normal,
+    /// Skipped in the turn order until it resumes.
+    Delaying,
+    /// Holding an action until the trigger happens. cleared at the start of its next turn.
+    Readied(String),
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+/// Whether a combatant is still in the fight.
+pub enum LifeStatus {
+    #[default]
+    Conscious,
+    Unconscious,
+    Dead,
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// How a combatant takes a hit of damage.
+pub enum DamageModifier {
+    #[default]
+    Normal,
+    Resistant,
+    Vulnerable,
+    Immune,
+}
+
+impl DamageModifier {
+    pub fn apply(&self, damage: i32) -> i32 {
+        match self {
+            DamageModifier::Normal => damage,
+            DamageModifier::Resistant => damage / 2,
+            DamageModifier::Vulnerable => damage * 2,
+            DamageModifier::Immune => 0,
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+/// The hit points of a combatant.
+///
+/// Properties:
+///
+/// * `current`: The hit points left.
+/// * `max`: The most hit points the combatant can be healed to.
+/// * `temp`: Temporary hit points, lost before `current`.
+/// * `status`: Whether the combatant is conscious, unconscious or dead.
+pub struct HitPoints {
+    pub current: i32,
+    pub max: i32,
+    pub temp: i32,
+    pub status: LifeStatus,
+}
+
+impl HitPoints {
+    pub fn new(max: i32) -> Self {
+        Self {
+            current: max,
+            max,
+            temp: 0,
+            status: LifeStatus::Conscious,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// An entry in the combat log, with enough to undo it.
+///
+/// Properties:
+///
+/// * `combatant_id`: The id of the combatant whose hit points changed.
+/// * `round`: The round the change happened in.
+/// * `description`: What happened, shown in the log.
+/// * `before`: The hit points before the change.
+pub struct HpChange {
+    pub combatant_id: usize,
+    pub round: u32,
+    pub description: String,
+    pub before: HitPoints,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// Someone taking part in combat.
+///
+/// Properties:
+///
+/// * `id`: The id of the combatant.
+/// * `name`: The name shown in the turn order.
+/// * `initiative_bonus`: Added to the initiative roll, and used to break initiative ties.
+/// * `initiative`: The rolled initiative.
+/// * `status`: Whether the combatant is delaying or readying.
+/// * `creature`: The creature this combatant is an instance of, `None` for player characters.
+/// * `hit_points`: The live hit points of the combatant.
+/// * `amount`: The damage/healing formula typed into the combatant's row.
+/// * `damage_modifier`: The resistance to apply to the next hit of damage.
+/// * `effects`: The conditions on the combatant.
+/// * `concentrating`: Whether the combatant is concentrating on a spell.
+/// * `new_effect`: The effect being set up in the "add effect" menu.
+/// * `character_id`: The id of the character sheet this combatant was added from, if any.
+/// * `spellbook`: The spells a character can cast. creatures cast from their own spellbook.
+/// * `spell_slots`: The spell slots of a character, written back to its sheet when combat ends.
+/// * `spell_save_dc`: The DC targets of the combatant's spells save against.
+pub struct Combatant {
+    pub id: usize,
+    pub name: String,
+    pub initiative_bonus: i64,
+    pub initiative: i64,
+    pub status: TurnStatus,
+    pub creature: Option<Creature>,
+    #[serde(default)]
+    pub hit_points: HitPoints,
+    #[serde(default)]
+    pub amount: String,
+    #[serde(default)]
+    pub damage_modifier: DamageModifier,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    #[serde(default)]
+    pub concentrating: bool,
+    #[serde(default)]
+    pub new_effect: Effect,
+    #[serde(default)]
+    pub character_id: Option<usize>,
+    #[serde(default)]
+    pub spellbook: Vec<String>,
+    #[serde(default)]
+    pub spell_slots: Vec<SpellSlot>,
+    #[serde(default)]
+    pub spell_save_dc: i32,
+}
+
+impl Combatant {
+    pub fn from_creature(id: usize, creature: Creature) -> Self {
+        let row = creature.challenge_rating().overall.row();
+        // creatures don't have dexterity. an attack bonus is proficiency plus the ability the
+        // creature fights with, which is as close as it gets
+        let initiative_bonus = (creature.attack_bonus - row.proficiency_bonus).clamp(-5, 5);
+        Self {
+            id,
+            name: creature.name.clone(),
+            initiative_bonus: initiative_bonus as i64,
+            initiative: 0,
+            status: TurnStatus::Normal,
+            hit_points: HitPoints::new(creature.hp),
+            creature: Some(creature),
+            amount: String::new(),
+            damage_modifier: DamageModifier::Normal,
+            effects: vec![],
+            concentrating: false,
+            new_effect: Effect::default(),
+            character_id: None,
+            spellbook: vec![],
+            spell_slots: vec![],
+            spell_save_dc: row.save_dc,
+        }
+    }
+
+    /// A combatant with the hit points of a character sheet, which `CombatTracker::end` writes
+    /// them back to.
+    pub fn from_character(id: usize, character: &PlayerCharacter) -> Self {
+        let mut combatant = Self::player(
+            id,
+            character.name.clone(),
+            character.initiative_bonus() as i64,
+            character.max_hp,
+        );
+        combatant.hit_points.current = character.current_hp.clamp(0, combatant.hit_points.max);
+        combatant.hit_points.temp = character.temp_hp;
+        if combatant.hit_points.current == 0 {
+            combatant.hit_points.status = LifeStatus::Unconscious;
+        }
+        combatant.character_id = Some(character.id);
+        combatant.spellbook = character.spellbook.clone();
+        combatant.spell_slots = character.spell_slots.clone();
+        combatant.spell_save_dc = character.spell_save_dc();
+        combatant
+    }
+
+    pub fn player(id: usize, name: String, initiative_bonus: i64, max_hp: i32) -> Self {
+        Self {
+            id,
+            name,
+            initiative_bonus,
+            initiative: 0,
+            status: TurnStatus::Normal,
+            creature: None,
+            hit_points: HitPoints::new(max_hp.max(1)),
+            amount: String::new(),
+            damage_modifier: DamageModifier::Normal,
+            effects: vec![],
+            concentrating: false,
+            new_effect: Effect::default(),
+            character_id: None,
+            spellbook: vec![],
+            spell_slots: vec![],
+            spell_save_dc: 0,
+        }
+    }
+
+    /// The names of the spells the combatant can cast.
+    pub fn spellbook(&self) -> &[String] {
+        match &self.creature {
+            Some(creature) => &creature.spellbook,
+            None => &self.spellbook,
+        }
+    }
+
+    /// What the combatant has left to cast with, e.g. `mana 3/8` or `slots 1st 2/3, 2nd 0/1`.
+    pub fn spell_resources(&self) -> String {
+        match &self.creature {
+            Some(creature) => format!(
+                "mana {}/{}",
+                creature.mana - creature.mana_spent,
+                creature.mana
+            ),
+            None => {
+                let slots: Vec<String> = self
+                    .spell_slots
+                    .iter()
+                    .map(|slot| {
+                        format!(
+                            "level {} {}/{}",
+                            slot.level,
+                            slot.max - slot.used.min(slot.max),
+                            slot.max
+                        )
+                    })
+                    .collect();
+                format!("slots {}", slots.join(", "))
+            }
+        }
+    }
+
+    /// Player characters fall unconscious at 0 hp, everything else dies.
+    pub fn is_player(&self) -> bool {
+        self.creature.is_none()
+    }
+
+    /// Takes damage, temporary hit points first.
+    fn take_damage(&mut self, damage: i32) {
+        let is_player = self.is_player();
+        let hp = &mut self.hit_points;
+        let absorbed = damage.min(hp.temp);
+        hp.temp -= absorbed;
+        let damage = damage - absorbed;
+        if damage == 0 {
+            return;
+        }
+
+        let overflow = damage - hp.current;
+        hp.current = (hp.current - damage).max(0);
+        if hp.current == 0 {
+            // damage left over after dropping to 0 that is at least the max hp kills outright
+            hp.status = if !is_player || overflow >= hp.max {
+                LifeStatus::Dead
+            } else {
+                LifeStatus::Unconscious
+            };
+        }
+    }
+
+    /// Heals up to the max hp. the dead stay dead.
+    fn heal(&mut self, healing: i32) {
+        let hp = &mut self.hit_points;
+        if hp.status == LifeStatus::Dead {
+            return;
+        }
+        hp.current = (hp.current + healing).min(hp.max);
+        if hp.current > 0 {
+            hp.status = LifeStatus::Conscious;
+        }
+    }
+
+    /// Rolls a d20 plus the initiative bonus with the dice engine.
+    pub fn roll_initiative(&mut self) -> Result<(), String> {
+        let formula = if self.initiative_bonus < 0 {
+            format!("1d20-{}", -self.initiative_bonus)
+        } else {
+            format!("1d20+{}", self.initiative_bonus)
+        };
+        self.initiative = roll(&formula)?;
+        Ok(())
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The state of the current combat.
+///
+/// Properties:
+///
+/// * `combatants`: Everyone in the fight, in turn order once initiative is rolled.
+/// * `round`: The current round, starting at 1. 0 means combat hasn't started.
+/// * `turn`: The index of the combatant whose turn it is.
+/// * `new_player_name`: The name typed into the "add player character" field.
+/// * `new_player_bonus`: The initiative bonus typed into the "add player character" field.
+/// * `new_player_hp`: The max hp typed into the "add player character" field.
+/// * `selected_place_id`: The id of the place creatures are pulled in from.
+/// * `log`: Every hit point change, oldest first.
+/// * `error`: The last error, e.g. from a bad damage formula.
+/// * `reminders`: What the DM needs to do this turn, like concentration checks and saves.
+pub struct CombatTracker {
+    pub combatants: Vec<Combatant>,
+    pub round: u32,
+    pub turn: usize,
+    pub new_player_name: String,
+    pub new_player_bonus: i64,
+    pub new_player_hp: i32,
+    #[serde(alias = "selected_place_index")]
+    pub selected_place_id: usize,
+    pub log: Vec<HpChange>,
+    pub error: Option<String>,
+    pub reminders: Vec<String>,
+}
+
+impl CombatTracker {
+    /// Rolls initiative for everyone and starts combat at round 1.
+    pub fn roll_initiative(&mut self) -> Result<(), String> {
+        for combatant in self.combatants.iter_mut() {
+            combatant.roll_initiative()?;
+            combatant.status = TurnStatus::Normal;
+        }
+        self.sort();
+        self.round = 1;
+        self.turn = 0;
+        Ok(())
+    }
+
+    /// Sorts by initiative, breaking ties with the initiative bonus (dexterity).
+    pub fn sort(&mut self) {
+        let active = self.active().map(|combatant| combatant.id);
+        self.combatants.sort_by(|a, b| {
+            b.initiative
+                .cmp(&a.initiative)
+                .then(b.initiative_bonus.cmp(&a.initiative_bonus))
+        });
+        if let Some(id) = active {
+            self.turn = self.position(id).unwrap_or(0);
+        }
+    }
+
+    pub fn active(&self) -> Option<&Combatant> {
+        if self.round == 0 {
+            return None;
+        }
+        self.combatants.get(self.turn)
+    }
+
+    pub fn position(&self, id: usize) -> Option<usize> {
+        self.combatants
+            .iter()
+            .position(|combatant| combatant.id == id)
+    }
+
+    /// Moves on to the next combatant that isn't delaying, starting a new round when the order
+    /// wraps.
+    /// effects on the combatants count down as their turns end and start.
+    pub fn next_turn(&mut self) {
+        if self.combatants.is_empty() {
+            return;
+        }
+        self.reminders.clear();
+        if self.round == 0 {
+            self.round = 1;
+            self.turn = 0;
+        } else {
+            self.tick(self.turn, Tick::EndOfTurn);
+            for _ in 0..self.combatants.len() {
+                self.turn += 1;
+                if self.turn >= self.combatants.len() {
+                    self.turn = 0;
+                    self.round += 1;
+                }
+                if self.combatants[self.turn].status != TurnStatus::Delaying {
+                    break;
+                }
+            }
+        }
+        // a readied action lasts until the start of the combatant's next turn
+        if let TurnStatus::Readied(_) = self.combatants[self.turn].status {
+            self.combatants[self.turn].status = TurnStatus::Normal;
+        }
+        self.tick(self.turn, Tick::StartOfTurn);
+    }
+
+    fn tick(&mut self, index: usize, tick: Tick) {
+        if let Some(combatant) = self.combatants.get_mut(index) {
+            let reminders = tick_effects(&combatant.name, &mut combatant.effects, tick);
+            self.reminders.extend(reminders);
+        }
+    }
+
+    /// Lets a delaying combatant rejoin the order straight after the active one.
+    pub fn resume(&mut self, id: usize) {
+        let index = match self.position(id) {
+            Some(index) => index,
+            None => return,
+        };
+        let mut combatant = self.combatants.remove(index);
+        if index < self.turn {
+            self.turn -= 1;
+        }
+        combatant.status = TurnStatus::Normal;
+        let insert_at = if self.combatants.is_empty() {
+            0
+        } else {
+            combatant.initiative = self.combatants[self.turn].initiative;
+            self.turn + 1
+        };
+        self.combatants.insert(insert_at, combatant);
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        if let Some(index) = self.position(id) {
+            self.combatants.remove(index);
+            if index < self.turn || self.turn >= self.combatants.len() {
+                self.turn = self.turn.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Rolls `formula` and deals the result as damage to a combatant, after its damage modifier.
+    pub fn damage(&mut self, id: usize, formula: &str) -> Result<(), String> {
+        let rolled = roll(formula)? as i32;
+        let round = self.round;
+        let combatant = self.get_mut(id)?;
+        let damage = combatant.damage_modifier.apply(rolled.max(0));
+        let before = combatant.hit_points.clone();
+        combatant.take_damage(damage);
+        let mut reminder = None;
+        if combatant.concentrating && damage > 0 {
+            if combatant.hit_points.status == LifeStatus::Conscious {
+                reminder = Some(format!(
+                    "{} makes a DC {} constitution save to keep concentrating",
+                    combatant.name,
+                    concentration_dc(damage)
+                ));
+            } else {
+                combatant.concentrating = false;
+                reminder = Some(format!("{} loses concentration", combatant.name));
+            }
+        }
+        let description = format!(
+            "{} took {} damage ({} rolled {}{})",
+            combatant.name,
+            damage,
+            formula,
+            rolled,
+            match combatant.damage_modifier {
+                DamageModifier::Normal => "",
+                DamageModifier::Resistant => ", resistant",
+                DamageModifier::Vulnerable => ", vulnerable",
+                DamageModifier::Immune => ", immune",
+            }
+        );
+        self.log.push(HpChange {
+            combatant_id: id,
+            round,
+            description,
+            before,
+        });
+        self.reminders.extend(reminder);
+        Ok(())
+    }
+
+    /// Rolls `formula` and heals a combatant by the result.
+    pub fn heal(&mut self, id: usize, formula: &str) -> Result<(), String> {
+        let rolled = roll(formula)? as i32;
+        let round = self.round;
+        let combatant = self.get_mut(id)?;
+        let before = combatant.hit_points.clone();
+        combatant.heal(rolled.max(0));
+        let description = format!("{} healed {} ({})", combatant.name, rolled, formula);
+        self.log.push(HpChange {
+            combatant_id: id,
+            round,
+            description,
+            before,
+        });
+        Ok(())
+    }
+
+    /// Rolls `formula` and gives a combatant that many temporary hit points. temporary hit points
+    /// don't stack, the higher amount is kept.
+    pub fn add_temp_hp(&mut self, id: usize, formula: &str) -> Result<(), String> {
+        let rolled = roll(formula)? as i32;
+        let round = self.round;
+        let combatant = self.get_mut(id)?;
+        let before = combatant.hit_points.clone();
+        combatant.hit_points.temp = combatant.hit_points.temp.max(rolled);
+        let description = format!(
+            "{} has {} temporary hp ({})",
+            combatant.name, combatant.hit_points.temp, formula
+        );
+        self.log.push(HpChange {
+            combatant_id: id,
+            round,
+            description,
+            before,
+        });
+        Ok(())
+    }
+
+    /// Reverts the latest hit point change.
+    pub fn undo(&mut self) {
+        if let Some(change) = self.log.pop() {
+            if let Ok(combatant) = self.get_mut(change.combatant_id) {
+                combatant.hit_points = change.before;
+            }
+        }
+    }
+
+    fn get_mut(&mut self, id: usize) -> Result<&mut Combatant, String> {
+        self.combatants
+            .iter_mut()
+            .find(|combatant| combatant.id == id)
+            .ok_or(format!("No combatant with id {}", id))
+    }
+
+    /// Casts `spell`, spending mana for creatures and a spell slot for characters. the rolled
+    /// damage and the save go in the reminders.
+    pub fn cast(&mut self, id: usize, spell: &SpellData) -> Result<(), String> {
+        let combatant = self.get_mut(id)?;
+        let summary = spell.cast_summary(combatant.spell_save_dc)?;
+        let slot = match &mut combatant.creature {
+            Some(creature) => {
+                creature.cast(spell)?;
+                spell.level
+            }
+            None => spend_slot(&mut combatant.spell_slots, spell.level)?,
+        };
+        if spell.concentration {
+            combatant.concentrating = true;
+        }
+        let upcast = if slot > spell.level {
+            format!(" with a level {} slot", slot)
+        } else {
+            String::new()
+        };
+        let reminder = format!("{} casts {}{}", combatant.name, summary, upcast);
+        self.reminders.push(reminder);
+        Ok(())
+    }
+
+    /// Everyone gets their spell slots and mana back.
+    pub fn rest(&mut self) {
+        for combatant in self.combatants.iter_mut() {
+            for slot in combatant.spell_slots.iter_mut() {
+                slot.used = 0;
+            }
+            if let Some(creature) = &mut combatant.creature {
+                creature.rest();
+            }
+        }
+    }
+
+    /// Ends combat, keeping the combatants for next time. characters get the hit points and spell
+    /// slots their combatants ended on.
+    pub fn end(&mut self, characters: &mut [PlayerCharacter]) {
+        self.round = 0;
+        self.turn = 0;
+        for combatant in self.combatants.iter_mut() {
+            combatant.status = TurnStatus::Normal;
+            let character = characters
+                .iter_mut()
+                .find(|character| Some(character.id) == combatant.character_id);
+            if let Some(character) = character {
+                character.current_hp = combatant.hit_points.current;
+                character.temp_hp = combatant.hit_points.temp;
+                character.spell_slots = combatant.spell_slots.clone();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_turn_order() {
+    let mut tracker = CombatTracker::default();
+    for (id, (initiative, bonus)) in [(12, 1), (18, 0), (12, 3)].into_iter().enumerate() {
+        let mut combatant = Combatant::player(id, id.to_string(), bonus, 10);
+        combatant.initiative = initiative;
+        tracker.combatants.push(combatant);
+    }
+    tracker.sort();
+    let order: Vec<usize> = tracker.combatants.iter().map(|c| c.id).collect();
+    assert_eq!(order, vec![1, 2, 0]);
+
+    // a goblin hits at +4 with a proficiency bonus of 2, and speed in feet doesn't count
+    let goblin = Creature {
+        speed: 30,
+        attack_bonus: 4,
+        hp: 7,
+        ..Default::default()
+    };
+    assert_eq!(Combatant::from_creature(9, goblin).initiative_bonus, 2);
+    let giant = Creature {
+        speed: 100,
+        attack_bonus: 40,
+        ..Default::default()
+    };
+    assert_eq!(Combatant::from_creature(9, giant).initiative_bonus, 5);
+
+    tracker.next_turn();
+    assert_eq!((tracker.round, tracker.active().unwrap().id), (1, 1));
+
+    // the delaying combatant is skipped until it resumes
+    tracker.combatants[1].status = TurnStatus::Delaying;
+    tracker.next_turn();
+    assert_eq!(tracker.active().unwrap().id, 0);
+    tracker.resume(2);
+    tracker.next_turn();
+    assert_eq!((tracker.round, tracker.active().unwrap().id), (1, 2));
+    tracker.next_turn();
+    assert_eq!((tracker.round, tracker.active().unwrap().id), (2, 1));
+    tracker.next_turn();
+    assert_eq!(tracker.active().unwrap().id, 0);
+}
+
+#[test]
+fn test_damage_and_undo() {
+    let mut tracker = CombatTracker::default();
+    tracker
+        .combatants
+        .push(Combatant::player(1, "player".to_string(), 0, 20));
+    tracker.combatants.push(Combatant::from_creature(
+        2,
+        Creature {
+            hp: 10,
+            ..Default::default()
+        },
+    ));
+
+    tracker.add_temp_hp(1, "5").unwrap();
+    tracker.damage(1, "8").unwrap();
+    assert_eq!(tracker.combatants[0].hit_points.temp, 0);
+    assert_eq!(tracker.combatants[0].hit_points.current, 17);
+
+    tracker.damage(1, "17").unwrap();
+    assert_eq!(
+        tracker.combatants[0].hit_points.status,
+        LifeStatus::Unconscious
+    );
+    tracker.heal(1, "1d4").unwrap();
+    assert_eq!(
+        tracker.combatants[0].hit_points.status,
+        LifeStatus::Conscious
+    );
+
+    tracker.combatants[1].damage_modifier = DamageModifier::Resistant;
+    tracker.damage(2, "9").unwrap();
+    assert_eq!(tracker.combatants[1].hit_points.current, 6);
+    tracker.damage(2, "40").unwrap();
+    assert_eq!(tracker.combatants[1].hit_points.status, LifeStatus::Dead);
+
+    tracker.undo();
+    tracker.undo();
+    assert_eq!(tracker.combatants[1].hit_points, HitPoints::new(10));
+    assert_eq!(tracker.log.len(), 4);
+
+    // combatants saved before hit points were tracked still load
+    let saved: Combatant = serde_json::from_value(serde_json::json!({
+        "id": 1,
+        "name": "Grik",
+        "initiative_bonus": 2,
+        "initiative": 14,
+        "status": "Normal",
+        "creature": null
+    }))
+    .unwrap();
+    assert_eq!(saved.hit_points, HitPoints::default());
+    assert_eq!(saved.damage_modifier, DamageModifier::Normal);
+}
+
+#[test]
+fn test_spellcasting() {
+    let spells = crate::spells::SpellCompendium::default();
+    let mut tracker = CombatTracker::default();
+    tracker.combatants.push(Combatant::from_creature(
+        1,
+        Creature {
+            name: "Cult fanatic".to_string(),
+            mana: 3,
+            ..Default::default()
+        },
+    ));
+    let mut wizard = PlayerCharacter::new(7, "Mialee");
+    wizard.spell_slots = vec![
+        SpellSlot {
+            level: 1,
+            max: 1,
+            used: 0,
+        },
+        SpellSlot {
+            level: 2,
+            max: 1,
+            used: 0,
+        },
+    ];
+    tracker
+        .combatants
+        .push(Combatant::from_character(2, &wizard));
+
+    tracker.cast(1, spells.get("Hold Person").unwrap()).unwrap();
+    assert!(tracker.combatants[0].concentrating);
+    assert_eq!(tracker.combatants[0].spell_resources(), "mana 1/3");
+    assert!(tracker.cast(1, spells.get("Hold Person").unwrap()).is_err());
+    tracker
+        .cast(1, spells.get("Sacred Flame").unwrap())
+        .unwrap();
+
+    let missile = spells.get("Magic Missile").unwrap();
+    tracker.cast(2, missile).unwrap();
+    tracker.cast(2, missile).unwrap();
+    assert!(tracker.reminders[2].starts_with("Mialee casts Magic Missile: "));
+    assert!(tracker.reminders[3].ends_with("with a level 2 slot"));
+    assert!(tracker.cast(2, missile).is_err());
+
+    let mut characters = vec![wizard];
+    tracker.end(&mut characters);
+    assert_eq!(characters[0].spell_slots[1].used, 1);
+    tracker.rest();
+    assert_eq!(tracker.combatants[0].spell_resources(), "mana 3/3");
+    assert_eq!(
+        tracker.combatants[1].spell_resources(),
+        "slots level 1 1/1, level 2 1/1"
+    );
+}
@@ -0,0 +1,170 @@
+// conditions and other timed effects on combatants
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The standard 5e conditions, plus anything else the DM wants to keep track of.
+pub enum Condition {
+    Blinded,
+    Charmed,
+    Deafened,
+    Exhaustion,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Stunned,
+    Unconscious,
+    Custom(String),
+}
+
+impl Condition {
+    pub const STANDARD: [Condition; 15] = [
+        Condition::Blinded,
+        Condition::Charmed,
+        Condition::Deafened,
+        Condition::Exhaustion,
+        Condition::Frightened,
+        Condition::Grappled,
+        Condition::Incapacitated,
+        Condition::Invisible,
+        Condition::Paralyzed,
+        Condition::Petrified,
+        Condition::Poisoned,
+        Condition::Prone,
+        Condition::Restrained,
+        Condition::Stunned,
+        Condition::Unconscious,
+    ];
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Custom(name) => write!(f, "{}", name),
+            condition => write!(f, "{:?}", condition),
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// When during the affected combatant's turn an effect counts down.
+pub enum Tick {
+    StartOfTurn,
+    EndOfTurn,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A condition on a combatant.
+///
+/// Properties:
+///
+/// * `condition`: The condition.
+/// * `rounds`: How many more rounds the effect lasts, `None` until removed by hand.
+/// * `tick`: Whether the effect counts down at the start or the end of the combatant's turn.
+/// * `save`: A save the combatant repeats every tick to end the effect, e.g. `WIS DC 13`. empty
+/// if there is none.
+pub struct Effect {
+    pub condition: Condition,
+    pub rounds: Option<u32>,
+    pub tick: Tick,
+    pub save: String,
+}
+
+impl Default for Effect {
+    fn default() -> Self {
+        Self {
+            condition: Condition::Poisoned,
+            rounds: Some(1),
+            tick: Tick::EndOfTurn,
+            save: String::new(),
+        }
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.condition)?;
+        if let Some(rounds) = self.rounds {
+            write!(f, " ({})", rounds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts down every effect that ticks at `tick`, removing the ones that run out.
+///
+/// Arguments:
+///
+/// * `name`: The name of the affected combatant, used in the reminders.
+/// * `effects`: The effects on the combatant.
+/// * `tick`: Which part of the turn is happening.
+///
+/// Returns:
+///
+/// Reminders for the DM about repeated saves and expired effects.
+pub fn tick_effects(name: &str, effects: &mut Vec<Effect>, tick: Tick) -> Vec<String> {
+    let mut reminders = vec![];
+    for effect in effects.iter_mut().filter(|effect| effect.tick == tick) {
+        if !effect.save.is_empty() {
+            reminders.push(format!(
+                "{} repeats a {} save against {}",
+                name, effect.save, effect.condition
+            ));
+        }
+        if let Some(rounds) = &mut effect.rounds {
+            *rounds = rounds.saturating_sub(1);
+            if *rounds == 0 {
+                reminders.push(format!("{} is no longer {}", name, effect.condition));
+            }
+        }
+    }
+    effects.retain(|effect| effect.rounds != Some(0));
+    reminders
+}
+
+/// The DC of the constitution save to keep concentrating after taking `damage`.
+pub fn concentration_dc(damage: i32) -> i32 {
+    (damage / 2).max(10)
+}
+
+#[test]
+fn test_tick_effects() {
+    let mut effects = vec![
+        Effect {
+            condition: Condition::Frightened,
+            rounds: Some(2),
+            tick: Tick::EndOfTurn,
+            save: "WIS DC 13".to_string(),
+        },
+        Effect {
+            condition: Condition::Custom("Hexed".to_string()),
+            rounds: None,
+            tick: Tick::StartOfTurn,
+            save: String::new(),
+        },
+    ];
+
+    assert!(tick_effects("goblin", &mut effects, Tick::StartOfTurn).is_empty());
+    assert_eq!(
+        tick_effects("goblin", &mut effects, Tick::EndOfTurn),
+        vec!["goblin repeats a WIS DC 13 save against Frightened"]
+    );
+    assert_eq!(
+        tick_effects("goblin", &mut effects, Tick::EndOfTurn).len(),
+        2
+    );
+    assert_eq!(effects.len(), 1);
+    assert_eq!(effects[0].to_string(), "Hexed");
+
+    assert_eq!(concentration_dc(7), 10);
+    assert_eq!(concentration_dc(31), 15);
+}
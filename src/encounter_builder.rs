@@ -0,0 +1,162 @@
+// proposes groups of creatures that fit the xp budget of a target difficulty
+
+use crate::challenge_rating::{Difficulty, EncounterDifficulty, XP_THRESHOLDS};
+use crate::structure::{Creature, EncounterSlot};
+use rand::seq::SliceRandom;
+
+/// How many random mixes are tried before settling for the closest one.
+const PROPOSAL_ATTEMPTS: usize = 50;
+
+/// The adjusted xp range (`min..max`) an encounter needs to land in to have the `target`
+/// difficulty.
+pub fn xp_budget(party_levels: &[i32], target: Difficulty) -> (u32, u32) {
+    let mut thresholds = [0; 4];
+    for level in party_levels {
+        for (threshold, xp) in thresholds
+            .iter_mut()
+            .zip(XP_THRESHOLDS[(*level).clamp(1, 20) as usize - 1])
+        {
+            *threshold += xp;
+        }
+    }
+    match target {
+        Difficulty::Trivial => (0, thresholds[0]),
+        Difficulty::Easy => (thresholds[0], thresholds[1]),
+        Difficulty::Medium => (thresholds[1], thresholds[2]),
+        Difficulty::Hard => (thresholds[2], thresholds[3]),
+        // nothing is too deadly, but don't propose a total party kill
+        Difficulty::Deadly => (thresholds[3], thresholds[3] * 3 / 2),
+    }
+}
+
+/// Rates a list of slots against a party.
+pub fn rate_slots(slots: &[EncounterSlot], party_levels: &[i32]) -> EncounterDifficulty {
+    EncounterDifficulty::calculate(
+        slots
+            .iter()
+            .map(|slot| slot.creature.challenge_rating().overall.xp())
+            .collect(),
+        party_levels.to_vec(),
+    )
+}
+
+/// Builds a new proposal, keeping every locked slot and filling the rest with creatures from the
+/// library until the encounter lands in the xp budget of `target`.
+///
+/// Arguments:
+///
+/// * `slots`: The current proposal. locked slots are kept.
+/// * `library`: The creatures to pick from.
+/// * `party_levels`: The level of every character in the party.
+/// * `target`: The wanted difficulty.
+/// * `max_creatures`: The most creatures the proposal may contain.
+///
+/// Returns:
+///
+/// The proposal whose adjusted xp is closest to the middle of the budget.
+pub fn propose(
+    slots: &[EncounterSlot],
+    library: &[Creature],
+    party_levels: &[i32],
+    target: Difficulty,
+    max_creatures: usize,
+) -> Vec<EncounterSlot> {
+    let mut rng = rand::thread_rng();
+    let (min, max) = xp_budget(party_levels, target);
+    let middle = (min + max) / 2;
+    let locked: Vec<EncounterSlot> = slots.iter().filter(|slot| slot.locked).cloned().collect();
+
+    let mut best = locked.clone();
+    let mut best_distance = u32::MAX;
+    for _ in 0..PROPOSAL_ATTEMPTS {
+        let mut proposal = locked.clone();
+        loop {
+            let adjusted = rate_slots(&proposal, party_levels).adjusted_xp;
+            if adjusted >= min || proposal.len() >= max_creatures {
+                break;
+            }
+            // only creatures that keep the encounter under the top of the budget are candidates
+            let candidates: Vec<&Creature> = library
+                .iter()
+                .filter(|creature| {
+                    let mut with = proposal.clone();
+                    with.push(EncounterSlot::new((*creature).clone()));
+                    rate_slots(&with, party_levels).adjusted_xp < max
+                })
+                .collect();
+            match candidates.choose(&mut rng) {
+                Some(creature) => proposal.push(EncounterSlot::new((*creature).clone())),
+                None => break,
+            }
+        }
+
+        let distance = rate_slots(&proposal, party_levels)
+            .adjusted_xp
+            .abs_diff(middle);
+        if distance < best_distance {
+            best_distance = distance;
+            best = proposal;
+        }
+    }
+    best
+}
+
+/// Replaces the creature in `slots[index]` with a different one from the library, preferring
+/// creatures that keep the encounter at the `target` difficulty.
+pub fn swap(
+    slots: &mut [EncounterSlot],
+    index: usize,
+    library: &[Creature],
+    party_levels: &[i32],
+    target: Difficulty,
+) {
+    let mut rng = rand::thread_rng();
+    let current = slots[index].creature.clone();
+    let others: Vec<&Creature> = library.iter().filter(|c| **c != current).collect();
+    let fitting: Vec<&Creature> = others
+        .iter()
+        .copied()
+        .filter(|creature| {
+            let mut with = slots.to_vec();
+            with[index] = EncounterSlot::new((*creature).clone());
+            rate_slots(&with, party_levels).difficulty == target
+        })
+        .collect();
+
+    let pick = if fitting.is_empty() {
+        others.choose(&mut rng)
+    } else {
+        fitting.choose(&mut rng)
+    };
+    if let Some(creature) = pick {
+        slots[index].creature = (*creature).clone();
+    }
+}
+
+#[test]
+fn test_propose() {
+    let goblin = Creature {
+        name: "goblin".to_string(),
+        hp: 7,
+        ac: 15,
+        damage_per_round: 5,
+        attack_bonus: 4,
+        ..Default::default()
+    };
+    let ogre = Creature {
+        name: "ogre".to_string(),
+        hp: 59,
+        ac: 11,
+        damage_per_round: 13,
+        attack_bonus: 6,
+        ..Default::default()
+    };
+    let party = vec![3; 4];
+    let mut locked = EncounterSlot::new(ogre.clone());
+    locked.locked = true;
+
+    let proposal = propose(&[locked], &[goblin, ogre], &party, Difficulty::Hard, 10);
+    assert!(proposal[0].locked);
+    assert_eq!(proposal[0].creature.name, "ogre");
+    assert_eq!(rate_slots(&proposal, &party).difficulty, Difficulty::Hard);
+}
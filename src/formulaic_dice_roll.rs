@@ -11,6 +11,16 @@ use std::ops::Mul;
 use std::ops::Sub;
 use std::str::FromStr;
 
+/// The most dice a single roll may throw, so a typo like `10000000d6` doesn't hang the app.
+const MAX_DICE: i64 = 10_000;
+
+/// Reads a number out of a formula.
+fn parse_number(number: &str) -> Result<i64, String> {
+    number
+        .parse()
+        .map_err(|_| format!("{} is too big a number", number))
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Copy)]
 pub enum DiceRollEquationToken {
     Number(i64),
@@ -39,7 +49,7 @@ pub fn tokenize_equation(equation: &str) -> Result<Vec<DiceRollEquationToken>, S
                         number.push(c);
                         chars.next();
                     } else if c == 'd' {
-                        first_dice_number_buffer = Some(number.parse().unwrap());
+                        first_dice_number_buffer = Some(parse_number(&number)?);
                         is_prefix = true;
                         break;
                     } else {
@@ -48,7 +58,7 @@ pub fn tokenize_equation(equation: &str) -> Result<Vec<DiceRollEquationToken>, S
                 }
 
                 if !is_prefix {
-                    tokens.push(DiceRollEquationToken::Number(number.parse().unwrap()));
+                    tokens.push(DiceRollEquationToken::Number(parse_number(&number)?));
                 }
             }
             'd' => {
@@ -67,11 +77,11 @@ pub fn tokenize_equation(equation: &str) -> Result<Vec<DiceRollEquationToken>, S
                 if let Some(first_dice_number) = first_dice_number_buffer {
                     tokens.push(DiceRollEquationToken::DiceRoll(
                         first_dice_number,
-                        number.parse().unwrap(),
+                        parse_number(&number)?,
                     ));
                     first_dice_number_buffer = None;
                 } else {
-                    tokens.push(DiceRollEquationToken::DiceRoll(1, number.parse().unwrap()));
+                    tokens.push(DiceRollEquationToken::DiceRoll(1, parse_number(&number)?));
                 }
             }
             '+' => tokens.push(DiceRollEquationToken::Plus),
@@ -100,22 +110,49 @@ pub enum DiceRollEquationNode {
 }
 
 impl DiceRollEquationNode {
-    pub fn evaluate(&self) -> i64 {
+    /// Rolls the dice and works the formula out. dice without sides, too many dice, dividing by
+    /// zero and numbers too big to hold are errors.
+    pub fn evaluate(&self) -> Result<i64, String> {
+        let too_big = || format!("{} is too big to work out", self);
         match self {
-            DiceRollEquationNode::Number(n) => *n,
+            DiceRollEquationNode::Number(n) => Ok(*n),
             DiceRollEquationNode::DiceRoll(num_dice, dice_sides) => {
+                if *dice_sides < 1 {
+                    return Err(format!("{} has dice without sides", self));
+                }
+                if *num_dice > MAX_DICE {
+                    return Err(format!("{} is more than {} dice", self, MAX_DICE));
+                }
                 let mut rng = rand::thread_rng();
-                let mut total = 0;
+                let mut total: i64 = 0;
                 for _ in 0..*num_dice {
-                    total += rng.gen_range(1..=*dice_sides);
+                    total = total
+                        .checked_add(rng.gen_range(1..=*dice_sides))
+                        .ok_or_else(too_big)?;
+                }
+                Ok(total)
+            }
+            DiceRollEquationNode::Plus(a, b) => {
+                a.evaluate()?.checked_add(b.evaluate()?).ok_or_else(too_big)
+            }
+            DiceRollEquationNode::Minus(a, b) => {
+                a.evaluate()?.checked_sub(b.evaluate()?).ok_or_else(too_big)
+            }
+            DiceRollEquationNode::Multiply(a, b) => {
+                a.evaluate()?.checked_mul(b.evaluate()?).ok_or_else(too_big)
+            }
+            DiceRollEquationNode::Divide(a, b) => match b.evaluate()? {
+                0 => Err(format!("{} divides by zero", self)),
+                divisor => a.evaluate()?.checked_div(divisor).ok_or_else(too_big),
+            },
+            DiceRollEquationNode::Power(a, b) => {
+                let exponent = b.evaluate()?;
+                if exponent < 0 {
+                    return Err(format!("{} has a negative power", self));
                 }
-                total
+                let exponent = u32::try_from(exponent).map_err(|_| too_big())?;
+                a.evaluate()?.checked_pow(exponent).ok_or_else(too_big)
             }
-            DiceRollEquationNode::Plus(a, b) => a.evaluate() + b.evaluate(),
-            DiceRollEquationNode::Minus(a, b) => a.evaluate() - b.evaluate(),
-            DiceRollEquationNode::Multiply(a, b) => a.evaluate() * b.evaluate(),
-            DiceRollEquationNode::Divide(a, b) => a.evaluate() / b.evaluate(),
-            DiceRollEquationNode::Power(a, b) => a.evaluate().pow(b.evaluate() as u32),
         }
     }
 }
@@ -154,7 +191,7 @@ pub fn parse_equation(tokens: &[DiceRollEquationToken]) -> Result<DiceRollEquati
                 }
                 tokens.insert(
                     i,
-                    DiceRollEquationToken::Number(num.parse::<i64>().unwrap() * -1),
+                    DiceRollEquationToken::Number(-parse_number(&num)?),
                 );
             } else {
                 i += 1;
@@ -223,6 +260,11 @@ pub fn parse_equation(tokens: &[DiceRollEquationToken]) -> Result<DiceRollEquati
     Ok(nodes.pop().ok_or(format!("No input."))?)
 }
 
+/// Tokenizes, parses and evaluates a formula in one go.
+pub fn roll(equation: &str) -> Result<i64, String> {
+    parse_equation(&tokenize_equation(equation)?)?.evaluate()
+}
+
 #[test]
 fn test_tokenize_equation() {
     assert_eq!(
@@ -284,3 +326,21 @@ fn test_parse_equation() {
 
     println!("{:?}", parse_equation(&tokenize_equation("d20").unwrap()).unwrap());
 }
+
+#[test]
+fn test_roll_errors() {
+    assert_eq!(roll("2d1+3"), Ok(5));
+    assert_eq!(roll("2^3"), Ok(8));
+    for formula in [
+        "1d0",
+        "d0",
+        "5/0",
+        "1/(2-2)",
+        "2^-1",
+        "20000d6",
+        "2^99",
+        "99999999999999999999",
+    ] {
+        assert!(roll(formula).is_err(), "{}", formula);
+    }
+}
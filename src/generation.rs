@@ -0,0 +1,483 @@
+// configurable creature randomization: distributions, point buy, minimums, archetypes and locks
+
+use crate::formulaic_dice_roll::roll;
+use crate::names::NameGenerator;
+use crate::structure::{BatchMenu, Creature, Skill, Spell};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// A field of a creature that can be rerolled, or locked so it survives a reroll.
+pub enum Stat {
+    Name,
+    Size,
+    Danger,
+    Type,
+    Level,
+    HitPoints,
+    ArmourClass,
+    AttackBonus,
+    DamagePerRound,
+    Strength,
+    Speed,
+    Intelligence,
+    Mana,
+    Vitality,
+    Skills,
+    Spells,
+    Notes,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 17] = [
+        Stat::Name,
+        Stat::Size,
+        Stat::Danger,
+        Stat::Type,
+        Stat::Level,
+        Stat::HitPoints,
+        Stat::ArmourClass,
+        Stat::AttackBonus,
+        Stat::DamagePerRound,
+        Stat::Strength,
+        Stat::Speed,
+        Stat::Intelligence,
+        Stat::Mana,
+        Stat::Vitality,
+        Stat::Skills,
+        Stat::Spells,
+        Stat::Notes,
+    ];
+
+    /// The stats level points and point buy budgets are spent on.
+    pub const POINT_STATS: [Stat; 6] = [
+        Stat::HitPoints,
+        Stat::Strength,
+        Stat::Speed,
+        Stat::Intelligence,
+        Stat::Mana,
+        Stat::Vitality,
+    ];
+
+    /// The stats that are kept by default, the ones the old randomize button used to wipe.
+    pub const DEFAULT_LOCKS: [Stat; 3] = [Stat::Name, Stat::Type, Stat::Notes];
+
+    /// The numeric value of a point stat.
+    fn value_mut(self, creature: &mut Creature) -> Option<&mut i32> {
+        match self {
+            Stat::HitPoints => Some(&mut creature.hp),
+            Stat::Strength => Some(&mut creature.strength),
+            Stat::Speed => Some(&mut creature.speed),
+            Stat::Intelligence => Some(&mut creature.int),
+            Stat::Mana => Some(&mut creature.mana),
+            Stat::Vitality => Some(&mut creature.vit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// How rolled stats are spread between the minimum and the maximum.
+pub enum Distribution {
+    /// Every value is as likely.
+    #[default]
+    Uniform,
+    /// Values bunch up around the middle, like 3d6.
+    Normal,
+    /// Values get steadily less likely away from the middle.
+    Triangular,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 3] = [
+        Distribution::Uniform,
+        Distribution::Normal,
+        Distribution::Triangular,
+    ];
+
+    /// Rolls a value between 0 and 1, centred on `mode` for the distributions that have a centre.
+    fn sample(&self, mode: f64, rng: &mut impl Rng) -> f64 {
+        match self {
+            Distribution::Uniform => rng.gen(),
+            Distribution::Normal => {
+                // box-muller, with the range being six standard deviations wide
+                let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (mode + z / 6.0).clamp(0.0, 1.0)
+            }
+            Distribution::Triangular => {
+                let u: f64 = rng.gen();
+                if u < mode {
+                    (u * mode).sqrt()
+                } else {
+                    1.0 - ((1.0 - u) * (1.0 - mode)).sqrt()
+                }
+            }
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// A kind of creature that rolled stats lean towards.
+pub enum Archetype {
+    #[default]
+    None,
+    Brute,
+    Caster,
+    Skirmisher,
+}
+
+impl Archetype {
+    pub const ALL: [Archetype; 4] = [
+        Archetype::None,
+        Archetype::Brute,
+        Archetype::Caster,
+        Archetype::Skirmisher,
+    ];
+
+    /// How much the archetype favours a point stat. 2 is twice as likely to get a point as 1.
+    pub fn weight(&self, stat: Stat) -> u32 {
+        match (self, stat) {
+            (Archetype::Brute, Stat::HitPoints | Stat::Strength | Stat::Vitality) => 3,
+            (Archetype::Brute, Stat::Intelligence | Stat::Mana) => 1,
+            (Archetype::Caster, Stat::Intelligence | Stat::Mana) => 3,
+            (Archetype::Caster, Stat::Strength | Stat::Vitality) => 1,
+            (Archetype::Skirmisher, Stat::Speed) => 4,
+            (Archetype::Skirmisher, Stat::Strength) => 2,
+            (Archetype::Skirmisher, Stat::HitPoints | Stat::Mana) => 1,
+            _ => 2,
+        }
+    }
+
+    /// The skills a creature of the archetype picks from.
+    pub fn skills(&self) -> &'static [&'static str] {
+        match self {
+            Archetype::None => &["Perception", "Survival", "Insight"],
+            Archetype::Brute => &["Athletics", "Intimidation", "Survival"],
+            Archetype::Caster => &["Arcana", "History", "Insight"],
+            Archetype::Skirmisher => &["Acrobatics", "Stealth", "Perception"],
+        }
+    }
+
+    /// The spells a creature of the archetype picks from.
+    pub fn spells(&self) -> &'static [&'static str] {
+        match self {
+            Archetype::Caster => &[
+                "Fire Bolt",
+                "Magic Missile",
+                "Shield",
+                "Sleep",
+                "Misty Step",
+                "Fireball",
+            ],
+            Archetype::Skirmisher => &["Disguise Self", "Expeditious Retreat"],
+            _ => &[],
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
+/// How a creature creation window rolls creatures.
+///
+/// Properties:
+///
+/// * `distribution`: How rolled stats are spread.
+/// * `archetype`: The kind of creature stats lean towards.
+/// * `point_buy`: When set, the point stats share this many points instead of being rolled
+/// separately.
+/// * `minimums`: The lowest value of each point stat. stats without one start at 1.
+/// * `locks`: The stats that survive a reroll.
+pub struct GenerationProfile {
+    pub distribution: Distribution,
+    pub archetype: Archetype,
+    pub point_buy: Option<i32>,
+    pub minimums: BTreeMap<Stat, i32>,
+    pub locks: Vec<Stat>,
+}
+
+impl Default for GenerationProfile {
+    fn default() -> Self {
+        Self {
+            distribution: Distribution::Uniform,
+            archetype: Archetype::None,
+            point_buy: None,
+            minimums: BTreeMap::new(),
+            locks: Stat::DEFAULT_LOCKS.to_vec(),
+        }
+    }
+}
+
+impl GenerationProfile {
+    pub fn is_locked(&self, stat: Stat) -> bool {
+        self.locks.contains(&stat)
+    }
+
+    pub fn minimum(&self, stat: Stat) -> i32 {
+        self.minimums.get(&stat).copied().unwrap_or(1)
+    }
+
+    /// Rolls a value between the stat's minimum and `max_value`. the archetype moves the middle of
+    /// the distribution up or down.
+    fn roll_stat(&self, stat: Stat, max_value: i32, rng: &mut impl Rng) -> i32 {
+        let min = self.minimum(stat).min(max_value);
+        let mode = self.archetype.weight(stat) as f64 / 4.0;
+        let mut sample = self.distribution.sample(mode, rng);
+        if self.distribution == Distribution::Uniform {
+            // uniform has no middle to move, so lean by rolling twice
+            let again = rng.gen::<f64>();
+            sample = match self.archetype.weight(stat) {
+                0..=1 => sample.min(again),
+                2 => sample,
+                _ => sample.max(again),
+            };
+        }
+        min + ((max_value - min) as f64 * sample).round() as i32
+    }
+
+    /// Spreads `points` over the unlocked point stats, starting each from its minimum. stats are
+    /// picked by archetype weight and stop growing at `max_value`.
+    pub fn spend_points(&self, creature: &mut Creature, points: i32, max_value: i32) {
+        let mut rng = rand::thread_rng();
+        let stats: Vec<Stat> = Stat::POINT_STATS
+            .into_iter()
+            .filter(|stat| !self.is_locked(*stat))
+            .collect();
+        for stat in stats.iter() {
+            *stat.value_mut(creature).unwrap() = self.minimum(*stat).min(max_value);
+        }
+        for _ in 0..points.max(0) {
+            let open: Vec<Stat> = stats
+                .iter()
+                .copied()
+                .filter(|stat| *stat.value_mut(creature).unwrap() < max_value)
+                .collect();
+            match open.choose_weighted(&mut rng, |stat| self.archetype.weight(*stat)) {
+                Ok(stat) => *stat.value_mut(creature).unwrap() += 1,
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Rerolls every unlocked field of `creature`.
+    ///
+    /// Arguments:
+    ///
+    /// * `creature`: The creature to reroll. locked fields are left alone.
+    /// * `max_value`: The highest value a rolled stat can have.
+    pub fn reroll(&self, creature: &mut Creature, max_value: i32) {
+        let mut rng = rand::thread_rng();
+        let max_value = max_value.max(1);
+        let rolled = Creature::randomize(max_value);
+        let unlocked = |stat: Stat| !self.is_locked(stat);
+
+        if unlocked(Stat::Name) {
+            creature.name = rolled.name;
+        }
+        if unlocked(Stat::Size) {
+            creature.size = rolled.size;
+        }
+        if unlocked(Stat::Danger) {
+            creature.danger = rolled.danger;
+        }
+        if unlocked(Stat::Type) {
+            creature._type = rolled._type;
+        }
+        if unlocked(Stat::Level) {
+            creature.lv = self.roll_stat(Stat::Level, max_value, &mut rng);
+        }
+        if unlocked(Stat::ArmourClass) {
+            creature.ac = rolled.ac;
+        }
+        if unlocked(Stat::AttackBonus) {
+            creature.attack_bonus = rolled.attack_bonus;
+        }
+        if unlocked(Stat::DamagePerRound) {
+            creature.damage_per_round = self.roll_stat(Stat::DamagePerRound, max_value, &mut rng);
+        }
+        match self.point_buy {
+            Some(points) => self.spend_points(creature, points, max_value),
+            None => {
+                for stat in Stat::POINT_STATS.into_iter().filter(|stat| unlocked(*stat)) {
+                    *stat.value_mut(creature).unwrap() = self.roll_stat(stat, max_value, &mut rng);
+                }
+            }
+        }
+        if unlocked(Stat::Skills) {
+            creature.skills = self
+                .archetype
+                .skills()
+                .choose_multiple(&mut rng, 2)
+                .map(|name| {
+                    let low = rng.gen_range(0..=max_value / 2);
+                    Skill {
+                        name: name.to_string(),
+                        min_max: (low, rng.gen_range(low..=max_value)),
+                    }
+                })
+                .collect();
+        }
+        if unlocked(Stat::Spells) {
+            let count = self.archetype.spells().len().min(3);
+            creature.spells = self
+                .archetype
+                .spells()
+                .choose_multiple(&mut rng, count)
+                .map(|name| {
+                    let low = rng.gen_range(0..=max_value / 2);
+                    Spell {
+                        name: name.to_string(),
+                        min_max: (low, rng.gen_range(low..=max_value)),
+                    }
+                })
+                .collect();
+        }
+        if unlocked(Stat::Notes) {
+            creature.notes = vec![];
+        }
+    }
+}
+
+/// Makes the creatures of a batch menu.
+///
+/// Every creature starts from `Creature::randomize`, gets a level in the batch's range, a size and
+/// danger rating out of the filters, and then has its stats rerolled by the batch's profile, or
+/// spread from its level with `randomise_based_on_lvl`.
+///
+/// Returns:
+///
+/// The creatures, without ids, or an error if the count formula is broken.
+pub fn generate_batch(
+    batch: &BatchMenu,
+    names: &mut NameGenerator,
+) -> Result<Vec<Creature>, String> {
+    let mut rng = rand::thread_rng();
+    let count = roll(&batch.count)?.clamp(0, 1000);
+    let (low, high) = (
+        batch.levels.0.min(batch.levels.1),
+        batch.levels.0.max(batch.levels.1),
+    );
+
+    let mut creatures = vec![];
+    for n in 1..=count {
+        let mut creature = Creature::randomize(batch.max_value.max(1));
+        creature._type = batch._type.clone();
+        creature.lv = rng.gen_range(low..=high);
+        if let Some(size) = batch.sizes.choose(&mut rng) {
+            creature.size = size.clone();
+        }
+        if let Some(danger) = batch.dangers.choose(&mut rng) {
+            creature.danger = danger.clone();
+        }
+        if batch.from_level {
+            creature.randomise_based_on_lvl(batch.max_value, &batch.profile);
+        } else {
+            batch.profile.reroll(&mut creature, batch.max_value);
+        }
+        creature.name = if batch.name.trim().is_empty() {
+            names.name(&[&batch._type]).unwrap_or_default()
+        } else {
+            format!("{} {}", batch.name.trim(), n)
+        };
+        creatures.push(creature);
+    }
+    Ok(creatures)
+}
+
+#[test]
+fn test_profiles() {
+    let mut profile = GenerationProfile {
+        archetype: Archetype::Brute,
+        point_buy: Some(30),
+        ..Default::default()
+    };
+    profile.minimums.insert(Stat::HitPoints, 10);
+    profile.locks.push(Stat::Intelligence);
+
+    let mut creature = Creature {
+        name: "Grug".to_string(),
+        int: 3,
+        notes: vec!["likes rocks".to_string()],
+        ..Default::default()
+    };
+    profile.reroll(&mut creature, 20);
+    assert_eq!(creature.name, "Grug");
+    assert_eq!(creature.notes.len(), 1);
+    assert_eq!(creature.int, 3);
+    assert!(creature.hp >= 10);
+    // 10 for hp's minimum, 1 for the other 4 unlocked stats, and 30 points
+    let total = creature.hp + creature.strength + creature.speed + creature.mana + creature.vit;
+    assert_eq!(total, 44);
+    assert!(creature.spells.is_empty());
+    assert_eq!(creature.skills.len(), 2);
+
+    for distribution in Distribution::ALL {
+        let profile = GenerationProfile {
+            distribution,
+            archetype: Archetype::Caster,
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            profile.reroll(&mut creature, 20);
+            for stat in Stat::POINT_STATS {
+                assert!((1..=20).contains(stat.value_mut(&mut creature).unwrap()));
+            }
+            assert_eq!(creature.spells.len(), 3);
+        }
+    }
+}
+
+#[test]
+fn test_generate_batch() {
+    use crate::structure::{DangerRating, Size};
+
+    let mut batch = BatchMenu::new(1, 2);
+    batch.count = "12".to_string();
+    batch.name = "Bandit".to_string();
+    batch.levels = (3, 4);
+    batch.max_value = 20;
+    batch.sizes = vec![Size::Small, Size::Medium];
+    batch.dangers = vec![DangerRating::Hard];
+
+    let bandits = generate_batch(&batch, &mut NameGenerator::default()).unwrap();
+    assert_eq!(bandits.len(), 12);
+    assert_eq!(bandits[11].name, "Bandit 12");
+    for bandit in bandits.iter() {
+        assert!((3..=4).contains(&bandit.lv));
+        assert!(batch.sizes.contains(&bandit.size));
+        assert_eq!(bandit.danger, DangerRating::Hard);
+        // 6 stats starting at 1, plus the level's worth of points
+        let total =
+            bandit.hp + bandit.strength + bandit.speed + bandit.int + bandit.mana + bandit.vit;
+        assert_eq!(total, 6 + bandit.lv);
+    }
+
+    batch.count = "lots".to_string();
+    assert!(generate_batch(&batch, &mut NameGenerator::default()).is_err());
+}
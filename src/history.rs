@@ -0,0 +1,495 @@
+// undo and redo: every change to the campaign is a command holding the values it changed, so it
+// can be reverted and applied again
+
+use serde_json::{Map, Value};
+
+/// How many steps can be undone.
+const MAX_STEPS: usize = 100;
+
+/// State as a json object, one entry per field.
+pub type State = Map<String, Value>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A step into a json value.
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// A path like `places[2].creatures`, for errors.
+fn path_name(path: &[Step]) -> String {
+    let mut name = String::new();
+    for step in path {
+        match step {
+            Step::Key(key) if name.is_empty() => name.push_str(key),
+            Step::Key(key) => name.push_str(&format!(".{}", key)),
+            Step::Index(i) => name.push_str(&format!("[{}]", i)),
+        }
+    }
+    name
+}
+
+/// The value at `path`, which can't be empty.
+fn value_at<'a>(state: &'a mut State, path: &[Step]) -> Option<&'a mut Value> {
+    let mut value = match path.first()? {
+        Step::Key(key) => state.get_mut(key)?,
+        Step::Index(_) => return None,
+    };
+    for step in &path[1..] {
+        value = match (step, value) {
+            (Step::Key(key), Value::Object(fields)) => fields.get_mut(key)?,
+            (Step::Index(i), Value::Array(items)) => items.get_mut(*i)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// One part of a change.
+enum Edit {
+    /// The value at `path` was replaced. `None` is a field that isn't there.
+    Set {
+        path: Vec<Step>,
+        before: Option<Value>,
+        after: Option<Value>,
+    },
+    /// Items of the list at `path` were replaced, starting at `index`. this is how items are
+    /// added and removed.
+    Splice {
+        path: Vec<Step>,
+        index: usize,
+        before: Vec<Value>,
+        after: Vec<Value>,
+    },
+}
+
+impl Edit {
+    fn path(&self) -> &[Step] {
+        match self {
+            Edit::Set { path, .. } | Edit::Splice { path, .. } => path,
+        }
+    }
+
+    /// Makes the edit, or takes it back when `forwards` is false. fails if the state isn't what
+    /// the edit was made on, leaving the state half changed.
+    fn run(&self, state: &mut State, forwards: bool) -> Result<(), String> {
+        let missing = |path: &[Step]| format!("{} isn't what it was", path_name(path));
+        match self {
+            Edit::Set {
+                path,
+                before,
+                after,
+            } => {
+                let (from, to) = if forwards {
+                    (before, after)
+                } else {
+                    (after, before)
+                };
+                let (last, parent) = path.split_last().ok_or_else(|| missing(path))?;
+                let done = match (last, parent.is_empty()) {
+                    (Step::Key(key), true) => set_field(state, key, from, to),
+                    (Step::Key(key), false) => match value_at(state, parent) {
+                        Some(Value::Object(fields)) => set_field(fields, key, from, to),
+                        _ => false,
+                    },
+                    (Step::Index(i), false) => match (value_at(state, parent), to) {
+                        (Some(Value::Array(items)), Some(value))
+                            if items.get(*i) == from.as_ref() =>
+                        {
+                            items[*i] = value.clone();
+                            true
+                        }
+                        _ => false,
+                    },
+                    (Step::Index(_), true) => false,
+                };
+                if !done {
+                    return Err(missing(path));
+                }
+            }
+            Edit::Splice {
+                path,
+                index,
+                before,
+                after,
+            } => {
+                let (from, to) = if forwards {
+                    (before, after)
+                } else {
+                    (after, before)
+                };
+                match value_at(state, path) {
+                    Some(Value::Array(items))
+                        if items.get(*index..index + from.len()) == Some(from.as_slice()) =>
+                    {
+                        items.splice(*index..index + from.len(), to.iter().cloned());
+                    }
+                    _ => return Err(missing(path)),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets or removes a field that is `from`, returning whether it was.
+fn set_field(fields: &mut State, key: &str, from: &Option<Value>, to: &Option<Value>) -> bool {
+    if fields.get(key) != from.as_ref() {
+        return false;
+    }
+    match to {
+        Some(value) => fields.insert(key.to_string(), value.clone()),
+        None => fields.remove(key),
+    };
+    true
+}
+
+/// Adds the edits that turn `before` into `after` to `edits`. lists that changed length are
+/// spliced where they differ, so only the items that changed are kept.
+fn diff(path: &mut Vec<Step>, before: &Value, after: &Value, edits: &mut Vec<Edit>) {
+    match (before, after) {
+        _ if before == after => {}
+        (Value::Object(old), Value::Object(new)) => diff_fields(path, old, new, edits),
+        (Value::Array(old), Value::Array(new)) => {
+            let start = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+            let end = old[start..]
+                .iter()
+                .rev()
+                .zip(new[start..].iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let old = &old[start..old.len() - end];
+            let new = &new[start..new.len() - end];
+            if old.len() == new.len() {
+                for (i, (a, b)) in old.iter().zip(new).enumerate() {
+                    path.push(Step::Index(start + i));
+                    diff(path, a, b, edits);
+                    path.pop();
+                }
+            } else {
+                edits.push(Edit::Splice {
+                    path: path.clone(),
+                    index: start,
+                    before: old.to_vec(),
+                    after: new.to_vec(),
+                });
+            }
+        }
+        _ => edits.push(Edit::Set {
+            path: path.clone(),
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+    }
+}
+
+fn diff_fields(path: &mut Vec<Step>, old: &State, new: &State, edits: &mut Vec<Edit>) {
+    for (key, value) in new.iter() {
+        path.push(Step::Key(key.clone()));
+        match old.get(key) {
+            Some(before) => diff(path, before, value, edits),
+            None => edits.push(Edit::Set {
+                path: path.clone(),
+                before: None,
+                after: Some(value.clone()),
+            }),
+        }
+        path.pop();
+    }
+    for (key, value) in old.iter().filter(|(key, _)| !new.contains_key(*key)) {
+        path.push(Step::Key(key.clone()));
+        edits.push(Edit::Set {
+            path: path.clone(),
+            before: Some(value.clone()),
+            after: None,
+        });
+        path.pop();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A change to the state.
+///
+/// Properties:
+///
+/// * `label`: What the change was, for the history window.
+/// * `edits`: The values the change replaced, in the order they were replaced.
+pub struct Command {
+    pub label: String,
+    edits: Vec<Edit>,
+}
+
+impl Command {
+    /// The command that turns `before` into `after`, `None` when nothing changed. without a
+    /// label the command is named after the fields it changed.
+    pub fn between(before: &State, after: &State, label: Option<String>) -> Option<Self> {
+        let mut edits = vec![];
+        diff_fields(&mut vec![], before, after, &mut edits);
+        if edits.is_empty() {
+            return None;
+        }
+        let label = label.unwrap_or_else(|| {
+            let mut fields: Vec<String> = vec![];
+            for edit in edits.iter() {
+                if let Some(Step::Key(key)) = edit.path().first() {
+                    let field = key.replace('_', " ");
+                    if !fields.contains(&field) {
+                        fields.push(field);
+                    }
+                }
+            }
+            format!("edit {}", fields.join(", "))
+        });
+        Some(Self { label, edits })
+    }
+
+    pub fn apply(&self, state: &mut State) -> Result<(), String> {
+        self.edits.iter().try_for_each(|edit| edit.run(state, true))
+    }
+
+    pub fn revert(&self, state: &mut State) -> Result<(), String> {
+        self.edits
+            .iter()
+            .rev()
+            .try_for_each(|edit| edit.run(state, false))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+/// The undo and redo stacks.
+///
+/// Properties:
+///
+/// * `undo`: The commands that can be undone, oldest first.
+/// * `redo`: The commands that were undone, the next one to redo last.
+/// * `state`: The state as of the latest command, what the next change is compared to.
+/// * `label`: The name of the next command.
+/// * `open`: Whether the history window is open.
+/// * `error`: Why the last undo or redo couldn't be done.
+pub struct History {
+    pub undo: Vec<Command>,
+    pub redo: Vec<Command>,
+    state: Option<State>,
+    label: Option<String>,
+    pub open: bool,
+    pub error: Option<String>,
+}
+
+impl History {
+    /// Names the next command, e.g. `remove Grik`. the name is dropped if nothing changes.
+    pub fn label(&mut self, label: impl Into<String>) {
+        self.label = Some(label.into());
+    }
+
+    /// Whether there is a state to compare changes to. there isn't until the first call to
+    /// `record`, or after `clear`.
+    pub fn started(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Records whatever changed in `state` since the last call as a command. called once a drag
+    /// or an edit is over, it makes the whole of it one command.
+    ///
+    /// Returns:
+    ///
+    /// Whether anything changed.
+    pub fn record(&mut self, state: State) -> bool {
+        let label = self.label.take();
+        let previous = match &self.state {
+            Some(previous) => previous,
+            None => {
+                self.state = Some(state);
+                return false;
+            }
+        };
+        let command = Command::between(previous, &state, label);
+        let changed = command.is_some();
+        if let Some(command) = command {
+            self.undo.push(command);
+            if self.undo.len() > MAX_STEPS {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+            self.state = Some(state);
+        }
+        changed
+    }
+
+    /// The state with `steps` commands done, without going there.
+    ///
+    /// Returns:
+    ///
+    /// `None` when `steps` commands are done already, or there aren't that many, and an error
+    /// if the commands don't fit the state.
+    pub fn state_at(&self, steps: usize) -> Result<Option<State>, String> {
+        let mut state = match &self.state {
+            Some(state) if steps != self.undo.len() => state.clone(),
+            _ => return Ok(None),
+        };
+        if steps < self.undo.len() {
+            for command in self.undo[steps..].iter().rev() {
+                command.revert(&mut state)?;
+            }
+        } else {
+            let redone = steps - self.undo.len();
+            if redone > self.redo.len() {
+                return Ok(None);
+            }
+            for command in self.redo.iter().rev().take(redone) {
+                command.apply(&mut state)?;
+            }
+        }
+        Ok(Some(state))
+    }
+
+    /// Moves the commands between the stacks once the state from `state_at(steps)` is restored.
+    pub fn moved_to(&mut self, steps: usize, state: State) {
+        while self.undo.len() > steps {
+            if let Some(command) = self.undo.pop() {
+                self.redo.push(command);
+            }
+        }
+        while self.undo.len() < steps {
+            match self.redo.pop() {
+                Some(command) => self.undo.push(command),
+                None => break,
+            }
+        }
+        self.state = Some(state);
+        self.error = None;
+    }
+
+    /// Forgets every command, for when a different campaign is opened.
+    pub fn clear(&mut self) {
+        *self = Self {
+            open: self.open,
+            ..Default::default()
+        };
+    }
+}
+
+#[test]
+fn test_history() {
+    let state = |places: Value, notes: Value| -> State {
+        serde_json::from_value(serde_json::json!({"places": places, "notes": notes})).unwrap()
+    };
+    // goes `steps` commands in, the way the app does
+    let go_to = |history: &mut History, steps: usize| -> Option<State> {
+        let state = history.state_at(steps).unwrap()?;
+        history.moved_to(steps, state.clone());
+        Some(state)
+    };
+    let mut history = History::default();
+    assert!(!history.record(state("[]".into(), "[]".into())));
+    assert!(history.started());
+    assert!(history.undo.is_empty());
+
+    assert!(history.record(state(5.into(), "[]".into())));
+    assert!(!history.record(state(5.into(), "[]".into())));
+    assert_eq!(history.undo.len(), 1);
+    assert_eq!(history.undo[0].label, "edit places");
+
+    history.label("remove a note");
+    history.record(state(5.into(), "gone".into()));
+    // a label without a change is dropped
+    history.label("nothing");
+    history.record(state(5.into(), "gone".into()));
+    history.record(state(6.into(), "gone".into()));
+    let labels: Vec<&str> = history
+        .undo
+        .iter()
+        .map(|command| command.label.as_str())
+        .collect();
+    assert_eq!(labels, vec!["edit places", "remove a note", "edit places"]);
+
+    assert_eq!(go_to(&mut history, 2), Some(state(5.into(), "gone".into())));
+    assert_eq!(go_to(&mut history, 1), Some(state(5.into(), "[]".into())));
+    assert_eq!(go_to(&mut history, 2), Some(state(5.into(), "gone".into())));
+    assert_eq!(
+        go_to(&mut history, 0),
+        Some(state("[]".into(), "[]".into()))
+    );
+    assert_eq!(history.redo.len(), 3);
+    assert_eq!(go_to(&mut history, 3), Some(state(6.into(), "gone".into())));
+    assert_eq!(go_to(&mut history, 4), None);
+
+    // a new change drops what was undone
+    go_to(&mut history, 2);
+    history.record(state(7.into(), "gone".into()));
+    assert!(history.redo.is_empty());
+    assert_eq!(history.undo.len(), 3);
+
+    for value in 0..MAX_STEPS as i64 * 2 {
+        history.record(state(value.into(), "gone".into()));
+    }
+    assert_eq!(history.undo.len(), MAX_STEPS);
+
+    // commands keep the values that changed, not whole fields
+    let place =
+        |name: &str, creatures: Value| serde_json::json!({"name": name, "creatures": creatures});
+    let mut history = History::default();
+    let before = serde_json::json!([
+        place("Hollow", serde_json::json!(["Grik", "Snag"])),
+        place("Grove", serde_json::json!([])),
+    ]);
+    history.record(state(before.clone(), "[]".into()));
+    let moved = serde_json::json!([
+        place("Hollow", serde_json::json!(["Grik"])),
+        place("Grove", serde_json::json!(["Snag"])),
+    ]);
+    history.record(state(moved, "[]".into()));
+    let added = serde_json::json!([
+        place("Hollow", serde_json::json!(["Grik"])),
+        place("Grove", serde_json::json!(["Snag"])),
+        place("Province", serde_json::json!([])),
+    ]);
+    history.record(state(added, "[]".into()));
+    let edits: Vec<Edit> = history
+        .undo
+        .iter()
+        .flat_map(|command| command.edits.clone())
+        .collect();
+    assert_eq!(
+        edits,
+        vec![
+            Edit::Splice {
+                path: vec![
+                    Step::Key("places".to_string()),
+                    Step::Index(0),
+                    Step::Key("creatures".to_string())
+                ],
+                index: 1,
+                before: vec!["Snag".into()],
+                after: vec![],
+            },
+            Edit::Splice {
+                path: vec![
+                    Step::Key("places".to_string()),
+                    Step::Index(1),
+                    Step::Key("creatures".to_string())
+                ],
+                index: 0,
+                before: vec![],
+                after: vec!["Snag".into()],
+            },
+            Edit::Splice {
+                path: vec![Step::Key("places".to_string())],
+                index: 2,
+                before: vec![],
+                after: vec![place("Province", serde_json::json!([]))],
+            },
+        ]
+    );
+    assert_eq!(
+        history.state_at(0).unwrap(),
+        Some(state(before, "[]".into()))
+    );
+
+    // commands that don't fit the state fail without moving anything
+    history.state = Some(state("[]".into(), "[]".into()));
+    let err = history.state_at(0).unwrap_err();
+    assert_eq!(err, "places isn't what it was");
+    assert_eq!(history.undo.len(), 2);
+    assert!(history.redo.is_empty());
+}
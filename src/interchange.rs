@@ -0,0 +1,712 @@
+// exporting and importing whole campaigns, or parts of them, as json, ron or yaml documents
+
+use crate::schema::{self, SCHEMA_VERSION};
+use crate::structure::{is_within, Connection, Creature, DiceMenu, NextId, Note, Place};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// The formats documents are written in.
+pub enum DocumentFormat {
+    #[default]
+    Json,
+    Ron,
+    Yaml,
+}
+
+impl DocumentFormat {
+    pub const ALL: [DocumentFormat; 3] = [
+        DocumentFormat::Json,
+        DocumentFormat::Ron,
+        DocumentFormat::Yaml,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocumentFormat::Json => "json",
+            DocumentFormat::Ron => "ron",
+            DocumentFormat::Yaml => "yaml",
+        }
+    }
+
+    /// The format of a file, from its extension.
+    pub fn of_path(path: &str) -> Option<Self> {
+        let extension = path.trim().rsplit('.').next()?.to_lowercase();
+        match extension.as_str() {
+            "json" => Some(DocumentFormat::Json),
+            "ron" => Some(DocumentFormat::Ron),
+            "yaml" | "yml" => Some(DocumentFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Writes `value` pretty printed, one field per line so documents diff well.
+    pub fn write<T: Serialize>(&self, value: &T) -> Result<String, String> {
+        match self {
+            DocumentFormat::Json => serde_json::to_string_pretty(value)
+                .map(|json| json + "\n")
+                .map_err(|err| err.to_string()),
+            DocumentFormat::Ron => {
+                ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                    .map(|ron| ron + "\n")
+                    .map_err(|err| err.to_string())
+            }
+            DocumentFormat::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Reads a document. documents in every format are migrated from older schema versions
+    /// first, as json values.
+    ///
+    /// Returns:
+    ///
+    /// The value, or what is wrong with the document and where, e.g.
+    /// `places[1].creatures[0].hp: invalid type: string "lots", expected i32`.
+    pub fn read<T: DeserializeOwned>(&self, text: &str) -> Result<T, String> {
+        let mut document = match self {
+            DocumentFormat::Json => serde_json::from_str(text).map_err(|err| err.to_string())?,
+            DocumentFormat::Ron => schema::ron_to_value(text)?,
+            DocumentFormat::Yaml => {
+                yaml_to_json(serde_yaml::from_str(text).map_err(|err| err.to_string())?)
+            }
+        };
+        schema::migrate(&mut document)?;
+        serde_path_to_error::deserialize(document).map_err(with_path)
+    }
+}
+
+/// The json value serde_json would have written for the same data as `yaml`. enum variants with
+/// data are tagged in yaml, `!Variant data`, and become `{"Variant": data}`.
+fn yaml_to_json(yaml: serde_yaml::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match yaml {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(value) => Value::Bool(value),
+        serde_yaml::Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(value), _) => value.into(),
+            (_, Some(value)) => value.into(),
+            _ => number
+                .as_f64()
+                .and_then(serde_json::Number::from_f64)
+                .map_or(Value::Null, Value::Number),
+        },
+        serde_yaml::Value::String(value) => Value::String(value),
+        serde_yaml::Value::Sequence(values) => {
+            Value::Array(values.into_iter().map(yaml_to_json).collect())
+        }
+        // json keys are strings, serde_json reads numbers back out of them
+        serde_yaml::Value::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match yaml_to_json(key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, yaml_to_json(value))
+                })
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => {
+            let mut variant = serde_json::Map::new();
+            variant.insert(
+                tagged.tag.to_string().trim_start_matches('!').to_string(),
+                yaml_to_json(tagged.value),
+            );
+            Value::Object(variant)
+        }
+    }
+}
+
+fn with_path<E: std::fmt::Display>(err: serde_path_to_error::Error<E>) -> String {
+    match err.path().to_string().as_str() {
+        "." => err.inner().to_string(),
+        path => format!("{}: {}", path, err.inner()),
+    }
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// What happens to imported things with the same id as something already in the campaign.
+pub enum ImportMode {
+    /// Both are kept, and the imported one gets a new id.
+    #[default]
+    Merge,
+    /// The imported one replaces what is there. creatures are still given a new id when a
+    /// creature in a place that isn't replaced has theirs.
+    Overwrite,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// Part of a campaign, as it is exported. the fields are named like the fields of a whole
+/// campaign, so a whole campaign can be imported as a part of another.
+///
+/// Properties:
+///
+/// * `schema_version`: The version of the save format the document was written with.
+/// * `places`: The places, with their creatures.
+/// * `connections`: The routes between the places.
+/// * `bestiary`: The creature templates.
+/// * `dice_windows`: The dice rolls, with their formulas and notes.
+/// * `notes`: The notes.
+pub struct CampaignDocument {
+    pub schema_version: u32,
+    pub places: Vec<Place>,
+    pub connections: Vec<Connection>,
+    pub bestiary: Vec<Creature>,
+    pub dice_windows: Vec<DiceMenu>,
+    pub notes: Vec<Note>,
+}
+
+/// The parts of a campaign documents are imported into.
+pub struct CampaignParts<'a> {
+    pub places: &'a mut Vec<Place>,
+    pub connections: &'a mut Vec<Connection>,
+    pub bestiary: &'a mut Vec<Creature>,
+    pub dice_windows: &'a mut Vec<DiceMenu>,
+    pub notes: &'a mut Vec<Note>,
+}
+
+impl CampaignDocument {
+    /// The selected parts of a campaign. places come with the places inside of them and the
+    /// templates of their creatures and random encounters, so the document stands on its own.
+    pub fn select(
+        places: &[Place],
+        connections: &[Connection],
+        bestiary: &[Creature],
+        dice_windows: &[DiceMenu],
+        notes: &[Note],
+        selection: &TransferMenu,
+    ) -> Self {
+        let mut selected: Vec<Place> = places
+            .iter()
+            .filter(|place| {
+                selection
+                    .place_ids
+                    .iter()
+                    .any(|id| is_within(places, place.id, *id))
+            })
+            .cloned()
+            .collect();
+        let place_ids: BTreeSet<usize> = selected.iter().map(|place| place.id).collect();
+        // places whose parent isn't exported become top level places
+        for place in selected.iter_mut() {
+            if !matches!(place.parent_id, Some(parent) if place_ids.contains(&parent)) {
+                place.parent_id = None;
+            }
+        }
+        let template_ids: BTreeSet<usize> = selected
+            .iter()
+            .flat_map(|place| place.creatures.iter())
+            .filter_map(|creature| creature.template_id)
+            .chain(
+                selected
+                    .iter()
+                    .flat_map(|place| place.encounter_table.iter())
+                    .map(|entry| entry.template_id),
+            )
+            .chain(selection.template_ids.iter().copied())
+            .collect();
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            places: selected,
+            connections: connections
+                .iter()
+                .filter(|route| place_ids.contains(&route.from) && place_ids.contains(&route.to))
+                .cloned()
+                .collect(),
+            bestiary: bestiary
+                .iter()
+                .filter(|template| template_ids.contains(&template.id))
+                .cloned()
+                .collect(),
+            dice_windows: dice_windows
+                .iter()
+                .filter(|dice| selection.dice_ids.contains(&dice.id))
+                .cloned()
+                .collect(),
+            notes: notes
+                .iter()
+                .filter(|note| selection.note_ids.contains(&note.id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Checks that ids are unique and that everything the document refers to is in it.
+    ///
+    /// Returns:
+    ///
+    /// Every problem, each with the path of what is wrong, e.g.
+    /// `places[2].parent_id: there is no place 7 in the document`.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        let mut duplicates = |path: &str, ids: Vec<usize>| {
+            let mut seen = BTreeSet::new();
+            for (i, id) in ids.into_iter().enumerate() {
+                if !seen.insert(id) {
+                    errors.push(format!("{}[{}].id: {} is used more than once", path, i, id));
+                }
+            }
+        };
+        duplicates("places", self.places.iter().map(|place| place.id).collect());
+        duplicates("bestiary", self.bestiary.iter().map(|c| c.id).collect());
+        duplicates(
+            "dice_windows",
+            self.dice_windows.iter().map(|d| d.id).collect(),
+        );
+        duplicates("notes", self.notes.iter().map(|note| note.id).collect());
+
+        let place_ids: BTreeSet<usize> = self.places.iter().map(|place| place.id).collect();
+        let template_ids: BTreeSet<usize> = self.bestiary.iter().map(|c| c.id).collect();
+        let mut creature_ids = BTreeSet::new();
+        for (i, place) in self.places.iter().enumerate() {
+            if let Some(parent) = place.parent_id {
+                if !place_ids.contains(&parent) {
+                    errors.push(format!(
+                        "places[{}].parent_id: there is no place {} in the document",
+                        i, parent
+                    ));
+                }
+            }
+            for (j, creature) in place.creatures.iter().enumerate() {
+                if !creature_ids.insert(creature.id) {
+                    errors.push(format!(
+                        "places[{}].creatures[{}].id: {} is used more than once",
+                        i, j, creature.id
+                    ));
+                }
+                if let Some(template) = creature.template_id {
+                    if !template_ids.contains(&template) {
+                        errors.push(format!(
+                            "places[{}].creatures[{}].template_id: there is no template {} in the bestiary",
+                            i, j, template
+                        ));
+                    }
+                }
+            }
+            for (j, entry) in place.encounter_table.iter().enumerate() {
+                if !template_ids.contains(&entry.template_id) {
+                    errors.push(format!(
+                        "places[{}].encounter_table[{}].template_id: there is no template {} in the bestiary",
+                        i, j, entry.template_id
+                    ));
+                }
+            }
+        }
+        for (i, route) in self.connections.iter().enumerate() {
+            for (end, id) in [("from", route.from), ("to", route.to)] {
+                if !place_ids.contains(&id) {
+                    errors.push(format!(
+                        "connections[{}].{}: there is no place {} in the document",
+                        i, end, id
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Adds the document to a campaign, giving new ids where `mode` says to.
+    ///
+    /// Returns:
+    ///
+    /// What was imported, e.g. `imported 2 places, 1 template, 0 dice rolls and 1 note, 1 with a
+    /// new id`.
+    pub fn import(
+        self,
+        parts: CampaignParts<'_>,
+        mode: ImportMode,
+        id_next: &mut NextId,
+    ) -> String {
+        let counts = (
+            self.places.len(),
+            self.bestiary.len(),
+            self.dice_windows.len(),
+            self.notes.len(),
+        );
+        let mut renamed = 0;
+        let overwrite = mode == ImportMode::Overwrite;
+        // `keep` is whether taken ids are kept, so the imported thing replaces what is there
+        let mut ids =
+            |ids: Vec<usize>, taken: BTreeSet<usize>, keep: bool, id_next: &mut NextId| {
+                let mut map = BTreeMap::new();
+                for id in ids {
+                    let new_id = if (taken.contains(&id) && !keep) || id == 0 {
+                        renamed += 1;
+                        id_next.next()
+                    } else {
+                        id
+                    };
+                    id_next.skip_past(new_id);
+                    map.insert(id, new_id);
+                }
+                map
+            };
+
+        let place_map = ids(
+            self.places.iter().map(|place| place.id).collect(),
+            parts.places.iter().map(|place| place.id).collect(),
+            overwrite,
+            id_next,
+        );
+        let template_map = ids(
+            self.bestiary.iter().map(|c| c.id).collect(),
+            parts.bestiary.iter().map(|c| c.id).collect(),
+            overwrite,
+            id_next,
+        );
+        let dice_map = ids(
+            self.dice_windows.iter().map(|d| d.id).collect(),
+            parts.dice_windows.iter().map(|d| d.id).collect(),
+            overwrite,
+            id_next,
+        );
+        let note_map = ids(
+            self.notes.iter().map(|note| note.id).collect(),
+            parts.notes.iter().map(|note| note.id).collect(),
+            overwrite,
+            id_next,
+        );
+        // creatures are never replaced one by one, they keep their ids unless a creature outside
+        // of the places being replaced has it
+        let replaced: Vec<usize> = place_map.values().copied().collect();
+        let creature_map = ids(
+            self.places
+                .iter()
+                .flat_map(|place| place.creatures.iter().map(|c| c.id))
+                .collect(),
+            parts
+                .places
+                .iter()
+                .filter(|place| !overwrite || !replaced.contains(&place.id))
+                .flat_map(|place| place.creatures.iter().map(|c| c.id))
+                .collect(),
+            false,
+            id_next,
+        );
+
+        for mut place in self.places {
+            place.id = place_map[&place.id];
+            place.parent_id = place.parent_id.map(|parent| place_map[&parent]);
+            for creature in place.creatures.iter_mut() {
+                creature.id = creature_map[&creature.id];
+                creature.template_id = creature.template_id.map(|id| template_map[&id]);
+            }
+            for entry in place.encounter_table.iter_mut() {
+                entry.template_id = template_map[&entry.template_id];
+            }
+            upsert(parts.places, place, |place| place.id);
+        }
+        for mut route in self.connections {
+            route.from = place_map[&route.from];
+            route.to = place_map[&route.to];
+            parts
+                .connections
+                .retain(|old| !(old.from == route.from && old.to == route.to));
+            parts.connections.push(route);
+        }
+        for mut template in self.bestiary {
+            template.id = template_map[&template.id];
+            upsert(parts.bestiary, template, |template| template.id);
+        }
+        for mut dice in self.dice_windows {
+            dice.id = dice_map[&dice.id];
+            upsert(parts.dice_windows, dice, |dice| dice.id);
+        }
+        for mut note in self.notes {
+            note.id = note_map[&note.id];
+            upsert(parts.notes, note, |note| note.id);
+        }
+
+        let (places, templates, dice, notes) = counts;
+        format!(
+            "imported {} places, {} templates, {} dice rolls and {} notes, {} with a new id",
+            places, templates, dice, notes, renamed
+        )
+    }
+}
+
+/// Replaces the item with the same id, or adds it when there is none.
+fn upsert<T>(items: &mut Vec<T>, item: T, id: impl Fn(&T) -> usize) {
+    match items.iter_mut().find(|existing| id(existing) == id(&item)) {
+        Some(existing) => *existing = item,
+        None => items.push(item),
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The export and import window.
+///
+/// Properties:
+///
+/// * `open`: Whether the window is showing.
+/// * `format`: The format documents are written and read in.
+/// * `whole_campaign`: Whether to export the whole campaign rather than the selection.
+/// * `place_ids`: The selected places.
+/// * `template_ids`: The selected creature templates.
+/// * `dice_ids`: The selected dice rolls.
+/// * `note_ids`: The selected notes.
+/// * `mode`: How imported ids that are already taken are handled.
+/// * `path`: The file documents are written to and read from.
+/// * `text`: The document.
+/// * `messages`: What happened on the last export or import, or what was wrong with the document.
+pub struct TransferMenu {
+    pub open: bool,
+    pub format: DocumentFormat,
+    pub whole_campaign: bool,
+    pub place_ids: Vec<usize>,
+    pub template_ids: Vec<usize>,
+    pub dice_ids: Vec<usize>,
+    pub note_ids: Vec<usize>,
+    pub mode: ImportMode,
+    pub path: String,
+    #[serde(skip)]
+    pub text: String,
+    pub messages: Vec<String>,
+}
+
+impl TransferMenu {
+    /// Reads the document in the window as part of a campaign.
+    pub fn read_document(&self) -> Result<CampaignDocument, Vec<String>> {
+        let document: CampaignDocument = self.format.read(&self.text).map_err(|err| vec![err])?;
+        let errors = document.validate();
+        if errors.is_empty() {
+            Ok(document)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[test]
+fn test_export_and_import() {
+    use crate::structure::Size;
+
+    let mut id_next = NextId::new();
+    let mut places = vec![
+        Place::new(id_next.next(), "Kingdom"),
+        Place::new(id_next.next(), "Castle"),
+        Place::new(id_next.next(), "Swamp"),
+    ];
+    places[1].parent_id = Some(1);
+    let mut template = Creature {
+        id: id_next.next(),
+        name: "Knight".to_string(),
+        size: Size::Large,
+        ..Default::default()
+    };
+    let mut guard = template.clone();
+    guard.id = id_next.next();
+    guard.template_id = Some(template.id);
+    places[1].creatures.push(guard);
+    places[1]
+        .encounter_table
+        .push(crate::random_encounters::EncounterEntry::new(template.id));
+    let mut connections = vec![Connection {
+        from: 2,
+        to: 3,
+        distance: 10,
+        travel_hours: 4,
+    }];
+    let mut bestiary = vec![template.clone()];
+    let mut dice_windows = vec![];
+    let mut notes = vec![Note {
+        text: "the king is a lizard".to_string(),
+        ..Note::new(id_next.next(), "The king")
+    }];
+    let selection = TransferMenu {
+        place_ids: vec![2],
+        note_ids: vec![6],
+        ..Default::default()
+    };
+    let document = CampaignDocument::select(
+        &places,
+        &connections,
+        &bestiary,
+        &dice_windows,
+        &notes,
+        &selection,
+    );
+    // the castle loses its parent, which isn't exported, and brings the knight template along
+    assert_eq!(document.places.len(), 1);
+    assert_eq!(document.places[0].parent_id, None);
+    assert_eq!(document.bestiary, bestiary);
+    assert!(document.connections.is_empty());
+    assert_eq!(document.notes.len(), 1);
+    assert!(document.validate().is_empty());
+
+    for format in DocumentFormat::ALL {
+        let text = format.write(&document).unwrap();
+        assert_eq!(
+            format.read::<CampaignDocument>(&text).unwrap(),
+            document,
+            "{:?}",
+            format
+        );
+    }
+    let whole = crate::app::DndTool::default();
+    for format in DocumentFormat::ALL {
+        let text = format.write(&whole).unwrap();
+        assert!(
+            format.read::<crate::app::DndTool>(&text).is_ok(),
+            "{:?}",
+            format
+        );
+        assert!(
+            format.read::<CampaignDocument>(&text).is_ok(),
+            "{:?}",
+            format
+        );
+    }
+
+    // merging into the same campaign keeps both copies, under new ids
+    let summary = document.clone().import(
+        CampaignParts {
+            places: &mut places,
+            connections: &mut connections,
+            bestiary: &mut bestiary,
+            dice_windows: &mut dice_windows,
+            notes: &mut notes,
+        },
+        ImportMode::Merge,
+        &mut id_next,
+    );
+    assert_eq!(
+        summary,
+        "imported 1 places, 1 templates, 0 dice rolls and 1 notes, 4 with a new id"
+    );
+    assert_eq!(places.len(), 4);
+    assert_eq!(bestiary.len(), 2);
+    let copy = &places[3];
+    assert_ne!(copy.id, 2);
+    assert_ne!(copy.creatures[0].id, places[1].creatures[0].id);
+    assert_eq!(copy.creatures[0].template_id, Some(bestiary[1].id));
+    assert_eq!(copy.encounter_table[0].template_id, bestiary[1].id);
+    assert!(id_next.next() > copy.creatures[0].id);
+
+    // overwriting replaces what has the same id
+    template.name = "Black Knight".to_string();
+    let mut overwrite = document;
+    overwrite.bestiary[0] = template;
+    overwrite.import(
+        CampaignParts {
+            places: &mut places,
+            connections: &mut connections,
+            bestiary: &mut bestiary,
+            dice_windows: &mut dice_windows,
+            notes: &mut notes,
+        },
+        ImportMode::Overwrite,
+        &mut id_next,
+    );
+    assert_eq!(places.len(), 4);
+    assert_eq!(bestiary[0].name, "Black Knight");
+
+    // a creature whose id is taken by a creature in another place gets a new id when overwriting
+    let mut swamp = Place::new(3, "Swamp");
+    swamp.creatures.push(Creature {
+        id: places[1].creatures[0].id,
+        name: "Bog hag".to_string(),
+        ..Default::default()
+    });
+    let summary = CampaignDocument {
+        places: vec![swamp],
+        ..Default::default()
+    }
+    .import(
+        CampaignParts {
+            places: &mut places,
+            connections: &mut connections,
+            bestiary: &mut bestiary,
+            dice_windows: &mut dice_windows,
+            notes: &mut notes,
+        },
+        ImportMode::Overwrite,
+        &mut id_next,
+    );
+    assert_eq!(
+        summary,
+        "imported 1 places, 0 templates, 0 dice rolls and 0 notes, 1 with a new id"
+    );
+    assert_eq!(places[2].name, "Swamp");
+    assert_ne!(places[2].creatures[0].id, places[1].creatures[0].id);
+
+    let broken = "{\"places\": [{\"name\": \"Moon\", \"creatures\": [{\"hp\": \"lots\"}]}]}";
+    let err = DocumentFormat::Json
+        .read::<CampaignDocument>(broken)
+        .unwrap_err();
+    assert!(err.starts_with("places[0].creatures[0].hp: "), "{}", err);
+    let err = DocumentFormat::Yaml
+        .read::<CampaignDocument>("places:\n  - name: Moon\n    creatures: 3\n")
+        .unwrap_err();
+    assert!(err.starts_with("places[0].creatures: "), "{}", err);
+    let dangling = CampaignDocument {
+        places: vec![Place {
+            parent_id: Some(9),
+            ..Place::new(1, "Moon")
+        }],
+        ..Default::default()
+    };
+    assert_eq!(
+        dangling.validate(),
+        vec!["places[0].parent_id: there is no place 9 in the document"]
+    );
+    assert_eq!(
+        DocumentFormat::of_path("world.YML"),
+        Some(DocumentFormat::Yaml)
+    );
+
+    // documents from older versions are migrated whatever their format
+    let old_ron = "(schema_version: 1, places: [(id: 1, name: \"Forest\", creatures: [], \
+        encounter_table: [(creature: (id: 4, name: \"Wolf\"), quantity: \"1d4\", weight: 1, \
+        time: Night, weather: Any)])])";
+    let old_yaml = "schema_version: 1\nplaces:\n- id: 1\n  name: Forest\n  creatures: []\n  \
+        encounter_table:\n  - creature: {id: 4, name: Wolf}\n    quantity: 1d4\n    weight: 1\n    \
+        time: Night\n    weather: Any\n";
+    for (format, text) in [
+        (DocumentFormat::Ron, old_ron),
+        (DocumentFormat::Yaml, old_yaml),
+    ] {
+        let document = format.read::<CampaignDocument>(text).unwrap();
+        assert_eq!(document.schema_version, SCHEMA_VERSION);
+        assert_eq!(document.bestiary[0].name, "Wolf");
+        assert_eq!(
+            document.places[0].encounter_table[0].template_id,
+            document.bestiary[0].id
+        );
+    }
+    let future = format!("schema_version: {}\n", SCHEMA_VERSION + 1);
+    assert!(DocumentFormat::Yaml
+        .read::<CampaignDocument>(&future)
+        .is_err());
+}
@@ -0,0 +1,469 @@
+// items, coins and carrying them
+
+use crate::formulaic_dice_roll::roll;
+use crate::structure::Size;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// How many copper pieces each denomination is worth, smallest first.
+const DENOMINATIONS: [(&str, u64); 5] =
+    [("cp", 1), ("sp", 10), ("ep", 50), ("gp", 100), ("pp", 1000)];
+
+/// How many coins weigh a pound.
+const COINS_PER_POUND: u64 = 50;
+
+/// How many items can be attuned to at once.
+pub const MAX_ATTUNED: usize = 3;
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+#[serde(default)]
+/// Coins, in every denomination.
+pub struct Currency {
+    pub cp: u64,
+    pub sp: u64,
+    pub ep: u64,
+    pub gp: u64,
+    pub pp: u64,
+}
+
+impl Currency {
+    fn coins(&self) -> [u64; 5] {
+        [self.cp, self.sp, self.ep, self.gp, self.pp]
+    }
+
+    fn coins_mut(&mut self) -> [&mut u64; 5] {
+        [
+            &mut self.cp,
+            &mut self.sp,
+            &mut self.ep,
+            &mut self.gp,
+            &mut self.pp,
+        ]
+    }
+
+    /// `cp` copper pieces worth of gold, silver and copper, with as few coins as possible.
+    pub fn from_cp(cp: u64) -> Self {
+        Self {
+            gp: cp / 100,
+            sp: cp % 100 / 10,
+            cp: cp % 10,
+            ..Default::default()
+        }
+    }
+
+    /// `count` coins of the denomination called `denomination`, e.g. `gp`.
+    pub fn of(denomination: &str, count: u64) -> Result<Self, String> {
+        let index = DENOMINATIONS
+            .iter()
+            .position(|(name, _)| name.eq_ignore_ascii_case(denomination.trim()))
+            .ok_or(format!("there are no coins called {}", denomination))?;
+        let mut coins = Currency::default();
+        *coins.coins_mut()[index] = count;
+        Ok(coins)
+    }
+
+    /// What the coins are worth in copper pieces.
+    pub fn total_cp(&self) -> u64 {
+        self.coins()
+            .iter()
+            .zip(DENOMINATIONS)
+            .map(|(count, (_, rate))| count * rate)
+            .sum()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.coins().iter().sum()
+    }
+
+    pub fn add(&mut self, other: &Currency) {
+        for (coins, added) in self.coins_mut().into_iter().zip(other.coins()) {
+            *coins += added;
+        }
+    }
+
+    /// Pays `cost` copper pieces worth, smallest coins first. when no coins add up to the cost,
+    /// the smallest coin that covers the rest is broken and the change is given back in gold,
+    /// silver and copper.
+    pub fn spend(&mut self, cost: u64) -> Result<(), String> {
+        if self.total_cp() < cost {
+            return Err(format!(
+                "{} costs more than the {} there is",
+                Currency::from_cp(cost),
+                self
+            ));
+        }
+        let mut remaining = cost;
+        for (coins, (_, rate)) in self.coins_mut().into_iter().zip(DENOMINATIONS) {
+            let used = (*coins).min(remaining / rate);
+            *coins -= used;
+            remaining -= used * rate;
+        }
+        while remaining > 0 {
+            let coins = self.coins();
+            let broken = (0..DENOMINATIONS.len())
+                .find(|i| coins[*i] > 0 && DENOMINATIONS[*i].1 >= remaining)
+                .or_else(|| (0..DENOMINATIONS.len()).rev().find(|i| coins[*i] > 0))
+                .ok_or("ran out of coins")?;
+            let rate = DENOMINATIONS[broken].1;
+            *self.coins_mut()[broken] -= 1;
+            if rate >= remaining {
+                self.add(&Currency::from_cp(rate - remaining));
+                remaining = 0;
+            } else {
+                remaining -= rate;
+            }
+        }
+        Ok(())
+    }
+
+    /// Swaps small coins for bigger ones, keeping electrum and platinum as they are.
+    pub fn consolidate(&mut self) {
+        let small = self.cp + self.sp * 10 + self.gp * 100;
+        let consolidated = Currency::from_cp(small);
+        self.cp = consolidated.cp;
+        self.sp = consolidated.sp;
+        self.gp = consolidated.gp;
+    }
+}
+
+impl Display for Currency {
+    /// e.g. `12 gp 5 sp`, or `0 gp` with no coins.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .coins()
+            .iter()
+            .zip(DENOMINATIONS)
+            .rev()
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, (name, _))| format!("{} {}", count, name))
+            .collect();
+        if parts.is_empty() {
+            write!(f, "0 gp")
+        } else {
+            write!(f, "{}", parts.join(" "))
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    VeryRare,
+    Legendary,
+    Artifact,
+}
+
+impl Rarity {
+    pub const ALL: [Rarity; 6] = [
+        Rarity::Common,
+        Rarity::Uncommon,
+        Rarity::Rare,
+        Rarity::VeryRare,
+        Rarity::Legendary,
+        Rarity::Artifact,
+    ];
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// A stack of identical items.
+///
+/// Properties:
+///
+/// * `name`: The name of the item.
+/// * `description`: What the item is and does.
+/// * `quantity`: How many of the item there are.
+/// * `weight`: The weight of one item in tenths of a pound.
+/// * `value`: What one item is worth.
+/// * `rarity`: How rare the item is.
+/// * `requires_attunement`: Whether the item only works for whoever is attuned to it.
+/// * `attuned`: Whether the carrier is attuned to the item.
+/// * `charges`: The charges left.
+/// * `max_charges`: The most charges the item holds, 0 for items without charges.
+/// * `recharge`: A dice formula for the charges regained at dawn, e.g. `1d6+1`.
+/// * `effect`: A dice formula rolled when the item is used, e.g. `2d4+2` for a healing potion.
+pub struct Item {
+    pub name: String,
+    pub description: String,
+    pub quantity: u32,
+    pub weight: u32,
+    pub value: Currency,
+    pub rarity: Rarity,
+    pub requires_attunement: bool,
+    pub attuned: bool,
+    pub charges: u32,
+    pub max_charges: u32,
+    pub recharge: String,
+    pub effect: String,
+}
+
+impl Item {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            quantity: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The weight of the whole stack in tenths of a pound.
+    pub fn total_weight(&self) -> u64 {
+        self.weight as u64 * self.quantity as u64
+    }
+
+    /// Uses the item, spending a charge if it has charges and rolling its effect if it has one.
+    ///
+    /// Returns:
+    ///
+    /// What happened, e.g. `Wand of Magic Missiles: 7 (3d4+3), 6 charges left`.
+    pub fn use_item(&mut self) -> Result<String, String> {
+        if self.requires_attunement && !self.attuned {
+            return Err(format!("{} needs attunement", self.name));
+        }
+        if self.max_charges > 0 && self.charges == 0 {
+            return Err(format!("{} has no charges left", self.name));
+        }
+        let mut used = self.name.clone();
+        if !self.effect.trim().is_empty() {
+            used.push_str(&format!(": {} ({})", roll(&self.effect)?, self.effect));
+        }
+        if self.max_charges > 0 {
+            self.charges -= 1;
+            used.push_str(&format!(", {} charges left", self.charges));
+        }
+        Ok(used)
+    }
+
+    /// Rolls the recharge formula, up to the max charges.
+    pub fn recharge(&mut self) -> Result<u32, String> {
+        let regained = if self.recharge.trim().is_empty() {
+            self.max_charges
+        } else {
+            roll(&self.recharge)?.max(0) as u32
+        };
+        let before = self.charges;
+        self.charges = (self.charges + regained).min(self.max_charges);
+        Ok(self.charges - before)
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// How weighed down a carrier is, from the variant encumbrance rules.
+pub enum Encumbrance {
+    Unencumbered,
+    /// Carrying more than 5 times strength in pounds: speed drops by 10 feet.
+    Encumbered,
+    /// Carrying more than 10 times strength: speed drops by 20 feet and strength, dexterity and
+    /// constitution rolls have disadvantage.
+    HeavilyEncumbered,
+    /// Carrying more than the carrying capacity of 15 times strength.
+    OverCapacity,
+}
+
+/// How much more a creature of `size` carries than a medium one.
+pub fn size_multiplier(size: &Size) -> u64 {
+    match size {
+        Size::Tiny => 1,
+        Size::Small | Size::Medium => 2,
+        Size::Large => 4,
+        Size::Huge => 8,
+        Size::Gargantuan => 16,
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The items and coins a creature, character or place has.
+///
+/// Properties:
+///
+/// * `items`: The stacks of items.
+/// * `coins`: The coins.
+pub struct Inventory {
+    pub items: Vec<Item>,
+    pub coins: Currency,
+}
+
+impl Inventory {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.coins.count() == 0
+    }
+
+    /// The weight of everything, coins included, in tenths of a pound.
+    pub fn total_weight(&self) -> u64 {
+        let coins = self.coins.count() * 10 / COINS_PER_POUND;
+        self.items.iter().map(Item::total_weight).sum::<u64>() + coins
+    }
+
+    /// What the items and coins are worth.
+    pub fn total_value(&self) -> Currency {
+        let items: u64 = self
+            .items
+            .iter()
+            .map(|item| item.value.total_cp() * item.quantity as u64)
+            .sum();
+        Currency::from_cp(items + self.coins.total_cp())
+    }
+
+    pub fn attuned(&self) -> usize {
+        self.items.iter().filter(|item| item.attuned).count()
+    }
+
+    /// Attunes to the item at `index`, as long as fewer than `MAX_ATTUNED` items are attuned.
+    pub fn attune(&mut self, index: usize) -> Result<(), String> {
+        if self.attuned() >= MAX_ATTUNED {
+            return Err(format!("already attuned to {} items", MAX_ATTUNED));
+        }
+        let item = self.items.get_mut(index).ok_or("no such item")?;
+        if !item.requires_attunement {
+            return Err(format!("{} doesn't need attunement", item.name));
+        }
+        item.attuned = true;
+        Ok(())
+    }
+
+    /// How weighed down a carrier with `strength` and `size` is by the inventory.
+    pub fn encumbrance(&self, strength: i32, size: &Size) -> Encumbrance {
+        // 5 times strength for a medium creature, in tenths of a pound
+        let step = strength.max(0) as u64 * 5 * size_multiplier(size) / 2 * 10;
+        match self.total_weight() {
+            weight if weight > step * 3 => Encumbrance::OverCapacity,
+            weight if weight > step * 2 => Encumbrance::HeavilyEncumbered,
+            weight if weight > step => Encumbrance::Encumbered,
+            _ => Encumbrance::Unencumbered,
+        }
+    }
+
+    /// Adds an item, onto the stack of the same item if there is one.
+    pub fn add_item(&mut self, item: Item) {
+        let same = self.items.iter_mut().find(|existing| {
+            Item {
+                quantity: item.quantity,
+                ..(*existing).clone()
+            } == item
+        });
+        match same {
+            Some(existing) => existing.quantity += item.quantity,
+            None => self.items.push(item),
+        }
+    }
+
+    /// Moves every item and coin out of `other` into this inventory, stacking items that are the
+    /// same. items stop being attuned when they change hands.
+    pub fn take_all(&mut self, other: &mut Inventory) {
+        self.coins.add(&other.coins);
+        other.coins = Currency::default();
+        for mut item in other.items.drain(..) {
+            item.attuned = false;
+            self.add_item(item);
+        }
+    }
+}
+
+#[test]
+fn test_inventory() {
+    let mut purse = Currency {
+        cp: 3,
+        sp: 0,
+        ep: 0,
+        gp: 2,
+        pp: 1,
+    };
+    assert_eq!(purse.total_cp(), 1203);
+    assert_eq!(purse.to_string(), "1 pp 2 gp 3 cp");
+    // 3 cp are paid, then a gold piece is broken for the other 47
+    purse.spend(50).unwrap();
+    assert_eq!(purse.to_string(), "1 pp 1 gp 5 sp 3 cp");
+    purse.spend(1100).unwrap();
+    assert_eq!(purse.total_cp(), 53);
+    assert!(purse.spend(54).is_err());
+    let mut pile = Currency {
+        cp: 250,
+        sp: 31,
+        ..Default::default()
+    };
+    pile.consolidate();
+    assert_eq!(pile.to_string(), "5 gp 6 sp");
+    assert_eq!(Currency::of("EP", 3).unwrap().total_cp(), 150);
+    assert!(Currency::of("doubloons", 3).is_err());
+
+    let mut pack = Inventory::default();
+    let mut rope = Item::new("Hempen rope");
+    rope.weight = 100;
+    rope.quantity = 3;
+    pack.items.push(rope);
+    pack.coins.gp = 500;
+    // 30 pounds of rope and 10 of coins
+    assert_eq!(pack.total_weight(), 400);
+    assert_eq!(
+        pack.encumbrance(8, &Size::Medium),
+        Encumbrance::Unencumbered
+    );
+    assert_eq!(pack.encumbrance(7, &Size::Medium), Encumbrance::Encumbered);
+    assert_eq!(
+        pack.encumbrance(3, &Size::Medium),
+        Encumbrance::HeavilyEncumbered
+    );
+    assert_eq!(
+        pack.encumbrance(3, &Size::Small),
+        Encumbrance::HeavilyEncumbered
+    );
+    assert_eq!(pack.encumbrance(3, &Size::Tiny), Encumbrance::OverCapacity);
+
+    let mut wand = Item::new("Wand of Magic Missiles");
+    wand.effect = "3d4+3".to_string();
+    wand.max_charges = 7;
+    wand.charges = 1;
+    wand.recharge = "1d6+1".to_string();
+    wand.requires_attunement = true;
+    assert!(wand.use_item().is_err());
+    wand.attuned = true;
+    assert!(wand.use_item().unwrap().ends_with(", 0 charges left"));
+    assert!(wand.use_item().is_err());
+    assert!((2..=7).contains(&wand.recharge().unwrap()));
+
+    let mut goblin = Inventory::default();
+    goblin.items.push(wand.clone());
+    goblin.items.push(Item::new("Hempen rope"));
+    goblin.items[1].weight = 100;
+    goblin.coins.sp = 12;
+    pack.take_all(&mut goblin);
+    assert!(goblin.is_empty());
+    assert_eq!(pack.items.len(), 2);
+    assert_eq!(pack.items[0].quantity, 4);
+    assert!(!pack.items[1].attuned);
+    assert_eq!(pack.coins.to_string(), "500 gp 12 sp");
+    assert!(pack.attune(0).is_err());
+    pack.attune(1).unwrap();
+}
@@ -0,0 +1,432 @@
+// levelling up player characters, with a history that can be undone
+
+use crate::character::{Ability, ClassLevel, Feature, PlayerCharacter};
+use crate::formulaic_dice_roll::roll;
+
+/// The classes that come with the app, see `data/classes.json` for the format.
+const BUILT_IN_CLASSES: &str = include_str!("../data/classes.json");
+
+/// The highest an ability score improvement can raise a score.
+const MAX_SCORE: i32 = 20;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A feature a class gains at a level.
+///
+/// Properties:
+///
+/// * `level`: The class level the feature unlocks at.
+/// * `name`: The name of the feature.
+/// * `description`: What the feature does.
+pub struct FeatureUnlock {
+    pub level: i32,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// How a class progresses.
+///
+/// Properties:
+///
+/// * `name`: The name of the class.
+/// * `hit_die`: The size of the class' hit die.
+/// * `asi_levels`: The class levels that give an ability score improvement.
+/// * `features`: The features the class unlocks.
+pub struct ClassData {
+    pub name: String,
+    pub hit_die: i32,
+    pub asi_levels: Vec<i32>,
+    pub features: Vec<FeatureUnlock>,
+}
+
+impl ClassData {
+    pub fn features_at(&self, level: i32) -> impl Iterator<Item = &FeatureUnlock> {
+        self.features
+            .iter()
+            .filter(move |feature| feature.level == level)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
+/// The classes characters can level up in, and where they were loaded from.
+///
+/// Properties:
+///
+/// * `path`: The path of a class data file to load instead of the built in classes.
+/// * `classes`: The loaded classes.
+/// * `error`: Why the last load failed.
+pub struct ClassLibrary {
+    pub path: String,
+    pub classes: Vec<ClassData>,
+    pub error: Option<String>,
+}
+
+impl Default for ClassLibrary {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            classes: parse_classes(BUILT_IN_CLASSES).unwrap_or_default(),
+            error: None,
+        }
+    }
+}
+
+impl ClassLibrary {
+    /// Loads the classes from `path`, or the built in classes when the path is empty.
+    pub fn load(&mut self) {
+        let loaded = if self.path.trim().is_empty() {
+            parse_classes(BUILT_IN_CLASSES)
+        } else {
+            std::fs::read_to_string(self.path.trim())
+                .map_err(|err| err.to_string())
+                .and_then(|json| parse_classes(&json))
+        };
+        match loaded {
+            Ok(classes) => {
+                self.classes = classes;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ClassData> {
+        self.classes
+            .iter()
+            .find(|class| class.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Whether reaching `level` in `class` gives an ability score improvement.
+    pub fn improves_at(&self, class: &str, level: i32) -> bool {
+        matches!(self.get(class), Some(data) if data.asi_levels.contains(&level))
+    }
+}
+
+/// Reads a list of classes from json.
+pub fn parse_classes(json: &str) -> Result<Vec<ClassData>, String> {
+    serde_json::from_str(json).map_err(|err| format!("class data: {}", err))
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// How the hit points of a new level are decided.
+pub enum HpMethod {
+    /// Roll the hit die with the dice engine.
+    Roll,
+    /// Take the rounded up average of the hit die.
+    #[default]
+    Average,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// One level gained, with everything needed to undo it.
+///
+/// Properties:
+///
+/// * `level`: The character level reached.
+/// * `class`: The class the level was taken in.
+/// * `new_class`: Whether the level added the class, rather than raising it.
+/// * `hp_gained`: The max hp gained, including constitution changes to earlier levels.
+/// * `hp_roll`: How the hit points were decided, e.g. `rolled 7 on 1d10`.
+/// * `increases`: The abilities raised by 1, an ability twice for +2.
+/// * `features`: The names of the features unlocked.
+/// * `proficiency_bonus`: The proficiency bonus at the new level.
+pub struct LevelRecord {
+    pub level: i32,
+    pub class: String,
+    pub new_class: bool,
+    pub hp_gained: i32,
+    pub hp_roll: String,
+    pub increases: Vec<Ability>,
+    pub features: Vec<String>,
+    pub proficiency_bonus: i32,
+}
+
+impl LevelRecord {
+    /// e.g. `Level 5: Fighter, +9 hp (rolled 7 on 1d10), +1 STR +1 CON, Extra Attack, proficiency +3`
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            self.class.clone(),
+            format!("+{} hp ({})", self.hp_gained, self.hp_roll),
+        ];
+        if !self.increases.is_empty() {
+            let increases: Vec<String> = self
+                .increases
+                .iter()
+                .map(|ability| format!("+1 {}", ability.short()))
+                .collect();
+            parts.push(increases.join(" "));
+        }
+        parts.extend(self.features.iter().cloned());
+        parts.push(format!("proficiency {:+}", self.proficiency_bonus));
+        format!("Level {}: {}", self.level, parts.join(", "))
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The state of a level up window.
+///
+/// Properties:
+///
+/// * `id`: The id of the window.
+/// * `character_id`: The id of the character levelling up.
+/// * `class`: The class to take the level in.
+/// * `hp_method`: Whether to roll or average the hit points.
+/// * `increases`: The two abilities to raise by 1 if the level gives an ability score improvement.
+/// * `error`: Why the last level up failed.
+pub struct LevelUpMenu {
+    pub id: usize,
+    pub character_id: usize,
+    pub class: String,
+    pub hp_method: HpMethod,
+    pub increases: [Ability; 2],
+    pub error: Option<String>,
+}
+
+impl LevelUpMenu {
+    /// A level up window for `character`, levelling their first class.
+    pub fn new(id: usize, character: &PlayerCharacter) -> Self {
+        Self {
+            id,
+            character_id: character.id,
+            class: character
+                .classes
+                .first()
+                .map_or(String::new(), |class| class.class.clone()),
+            hp_method: HpMethod::Average,
+            increases: [Ability::Strength, Ability::Constitution],
+            error: None,
+        }
+    }
+}
+
+/// The class level `character` would reach by levelling up in `class`.
+pub fn next_class_level(character: &PlayerCharacter, class: &str) -> i32 {
+    character
+        .classes
+        .iter()
+        .find(|existing| existing.class.eq_ignore_ascii_case(class.trim()))
+        .map_or(1, |existing| existing.level + 1)
+}
+
+/// Gives `character` a level in `class`.
+///
+/// Arguments:
+///
+/// * `character`: The character to level up. the level is added to its history.
+/// * `library`: Where the hit die, ability score improvements and features of the class come from.
+/// * `class`: The class to take the level in, either one from the library or one the character has.
+/// * `hp_method`: Whether to roll or average the hit points.
+/// * `increases`: The abilities to raise if the level gives an ability score improvement.
+pub fn level_up(
+    character: &mut PlayerCharacter,
+    library: &ClassLibrary,
+    class: &str,
+    hp_method: HpMethod,
+    increases: &[Ability],
+) -> Result<(), String> {
+    let data = library.get(class);
+    let existing = character
+        .classes
+        .iter()
+        .position(|existing| existing.class.eq_ignore_ascii_case(class.trim()));
+    let hit_die = match (existing, data) {
+        (Some(i), _) => character.classes[i].hit_die,
+        (None, Some(data)) => data.hit_die,
+        (None, None) => return Err(format!("no class called {}", class)),
+    };
+    let class_level = next_class_level(character, class);
+    if class_level > 20 || character.level() >= 20 {
+        return Err(format!("{} is already level 20", character.name));
+    }
+    let increases: Vec<Ability> = if library.improves_at(class, class_level) {
+        if increases.len() != 2 {
+            return Err("an ability score improvement raises two abilities by 1".to_string());
+        }
+        for ability in Ability::ALL {
+            let raised = increases
+                .iter()
+                .filter(|increase| **increase == ability)
+                .count() as i32;
+            if character.abilities.score(ability) + raised > MAX_SCORE {
+                return Err(format!("{} can't go above {}", ability.short(), MAX_SCORE));
+            }
+        }
+        increases.to_vec()
+    } else {
+        vec![]
+    };
+
+    let formula = format!("1d{}", hit_die);
+    let (die_hp, mut hp_roll) = match hp_method {
+        HpMethod::Roll => {
+            let rolled = roll(&formula)? as i32;
+            (rolled, format!("rolled {} on {}", rolled, formula))
+        }
+        HpMethod::Average => (hit_die / 2 + 1, format!("average of {}", formula)),
+    };
+
+    let old_constitution = character.abilities.modifier(Ability::Constitution);
+    for ability in increases.iter() {
+        *character.abilities.score_mut(*ability) += 1;
+    }
+    let class_name = match existing {
+        Some(i) => {
+            character.classes[i].level += 1;
+            character.classes[i].class.clone()
+        }
+        None => {
+            let name = data.map_or(class.trim().to_string(), |data| data.name.clone());
+            character.classes.push(ClassLevel {
+                class: name.clone(),
+                level: 1,
+                hit_die,
+            });
+            name
+        }
+    };
+
+    // a higher constitution modifier counts for every level, not just the new one
+    let constitution = character.abilities.modifier(Ability::Constitution);
+    let retroactive = (constitution - old_constitution) * (character.level() - 1);
+    if retroactive != 0 {
+        hp_roll.push_str(&format!(", {:+} from constitution", retroactive));
+    }
+    let hp_gained = (die_hp + constitution).max(1) + retroactive;
+    character.max_hp += hp_gained;
+    character.current_hp += hp_gained;
+
+    let mut features = vec![];
+    for feature in data
+        .into_iter()
+        .flat_map(|data| data.features_at(class_level))
+    {
+        features.push(feature.name.clone());
+        character.features.push(Feature {
+            name: feature.name.clone(),
+            description: feature.description.clone(),
+        });
+    }
+
+    character.history.push(LevelRecord {
+        level: character.level(),
+        class: class_name,
+        new_class: existing.is_none(),
+        hp_gained,
+        hp_roll,
+        increases,
+        features,
+        proficiency_bonus: character.proficiency_bonus(),
+    });
+    Ok(())
+}
+
+/// Takes back the latest level in `character`'s history.
+pub fn undo_level(character: &mut PlayerCharacter) -> Result<(), String> {
+    let record = character
+        .history
+        .pop()
+        .ok_or(format!("{} has no levels to undo", character.name))?;
+
+    let class = character
+        .classes
+        .iter()
+        .position(|class| class.class == record.class);
+    if let Some(i) = class {
+        if record.new_class {
+            character.classes.remove(i);
+        } else {
+            character.classes[i].level -= 1;
+        }
+    }
+    for ability in record.increases.iter() {
+        *character.abilities.score_mut(*ability) -= 1;
+    }
+    character.max_hp = (character.max_hp - record.hp_gained).max(1);
+    character.current_hp = character.current_hp.min(character.max_hp);
+    character.hit_dice_used = character.hit_dice_used.min(character.level());
+    for name in record.features.iter().rev() {
+        let unlocked = character
+            .features
+            .iter()
+            .rposition(|feature| &feature.name == name);
+        if let Some(i) = unlocked {
+            character.features.remove(i);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_level_up() {
+    let library = ClassLibrary::default();
+    assert_eq!(library.classes.len(), 12);
+    assert!(library.get("fighter").is_some());
+
+    let mut character = PlayerCharacter::new(1, "Tordek");
+    *character.abilities.score_mut(Ability::Constitution) = 15;
+    character.max_hp = 12;
+    character.current_hp = 12;
+    let before = character.clone();
+
+    level_up(&mut character, &library, "Fighter", HpMethod::Average, &[]).unwrap();
+    assert_eq!((character.level(), character.max_hp), (2, 20));
+    assert_eq!(character.features.last().unwrap().name, "Action Surge");
+    level_up(&mut character, &library, "Fighter", HpMethod::Roll, &[]).unwrap();
+    let rolled = character.history[1].hp_gained;
+    assert!((3..=12).contains(&rolled));
+
+    // level 4 raises constitution to 16, which is worth 1 hp for each of the 3 earlier levels too
+    let increases = [Ability::Constitution, Ability::Strength];
+    level_up(
+        &mut character,
+        &library,
+        "Fighter",
+        HpMethod::Average,
+        &increases,
+    )
+    .unwrap();
+    assert_eq!(character.abilities.score(Ability::Constitution), 16);
+    assert_eq!(character.history[2].hp_gained, 6 + 3 + 3);
+
+    level_up(
+        &mut character,
+        &library,
+        "wizard",
+        HpMethod::Average,
+        &increases,
+    )
+    .unwrap();
+    assert_eq!(character.to_string(), "Tordek, Human Fighter 4 / Wizard 1");
+    assert_eq!(character.proficiency_bonus(), 3);
+    // no improvement at wizard level 1
+    assert_eq!(character.abilities.score(Ability::Strength), 11);
+    assert!(character.history[3]
+        .summary()
+        .starts_with("Level 5: Wizard, +7 hp"));
+
+    assert!(level_up(&mut character, &library, "Jester", HpMethod::Average, &[]).is_err());
+    let mut capped = character.clone();
+    capped.classes[0].level = 5;
+    *capped.abilities.score_mut(Ability::Wisdom) = 20;
+    let wisdom = [Ability::Wisdom, Ability::Wisdom];
+    assert!(level_up(&mut capped, &library, "Fighter", HpMethod::Average, &wisdom).is_err());
+    assert!(level_up(&mut capped, &library, "Fighter", HpMethod::Average, &[]).is_err());
+
+    while !character.history.is_empty() {
+        undo_level(&mut character).unwrap();
+    }
+    assert_eq!(character, before);
+    assert!(undo_level(&mut character).is_err());
+}
@@ -1,7 +1,27 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+mod campaign;
+mod challenge_rating;
+mod character;
+mod combat;
+mod conditions;
+mod encounter_builder;
 mod formulaic_dice_roll;
+mod generation;
+mod history;
+mod interchange;
+mod inventory;
+mod level_up;
+mod loot;
+mod names;
+mod notes;
+mod random_encounters;
+mod random_tables;
+mod schema;
+mod spells;
+mod srd_import;
+mod stat_block;
 mod structure;
 
 pub use app::DndTool;
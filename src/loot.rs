@@ -0,0 +1,342 @@
+// treasure generated by challenge rating or danger, from editable loot tables
+
+use crate::challenge_rating::CreatureRating;
+use crate::formulaic_dice_roll::roll;
+use crate::inventory::{Currency, Inventory, Item, Rarity};
+use crate::random_tables::{self, RandomTable, RowRoll};
+use crate::structure::{Creature, DangerRating};
+
+/// The loot tables that come with the app, see `data/loot.json` for the format.
+const BUILT_IN_LOOT: &str = include_str!("../data/loot.json");
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// Which treasure table to roll on.
+pub enum LootKind {
+    /// The coins a single creature carries.
+    Individual,
+    /// The treasure a group of creatures has gathered in its lair.
+    Hoard,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// What picks the tier of treasure.
+pub enum LootLevel {
+    ChallengeRating(u32),
+    Danger(DangerRating),
+}
+
+impl LootLevel {
+    /// The overall challenge rating, rounded down.
+    pub fn of_rating(rating: &CreatureRating) -> Self {
+        LootLevel::ChallengeRating(rating.overall.row().value as u32)
+    }
+
+    pub fn of_creature(creature: &Creature) -> Self {
+        Self::of_rating(&CreatureRating::new(creature))
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// Coins rolled with the dice engine.
+///
+/// Properties:
+///
+/// * `denomination`: The coins rolled, `cp`, `sp`, `ep`, `gp` or `pp`.
+/// * `formula`: How many coins there are, e.g. `6d6*100`.
+pub struct CoinRoll {
+    pub denomination: String,
+    pub formula: String,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// Items rolled on a random table.
+///
+/// Properties:
+///
+/// * `table`: The name of the table each item is rolled on.
+/// * `count`: How many items are rolled, e.g. `2d6`.
+/// * `value`: What each item is worth, unless it is in the item catalog.
+/// * `rarity`: How rare each item is, unless it is in the item catalog.
+pub struct ItemRoll {
+    pub table: String,
+    pub count: String,
+    pub value: Currency,
+    pub rarity: Rarity,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A row of a treasure table.
+///
+/// Properties:
+///
+/// * `roll`: When the row is picked, like the rows of a random table.
+/// * `coins`: The coins the row gives.
+/// * `items`: The items the row gives.
+pub struct LootRow {
+    pub roll: RowRoll,
+    #[serde(default)]
+    pub coins: Vec<CoinRoll>,
+    #[serde(default)]
+    pub items: Vec<ItemRoll>,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// A treasure table.
+///
+/// Properties:
+///
+/// * `coins`: The coins that are always there.
+/// * `rows`: The rows, one of which is picked.
+pub struct TreasureTable {
+    pub coins: Vec<CoinRoll>,
+    pub rows: Vec<LootRow>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The treasure for a band of challenge ratings.
+///
+/// Properties:
+///
+/// * `name`: e.g. `CR 5-10`.
+/// * `max_cr`: The highest challenge rating the tier is for.
+/// * `dangers`: The danger ratings the tier is for.
+/// * `individual`: The treasure of a single creature.
+/// * `hoard`: The treasure of a hoard.
+pub struct LootTier {
+    pub name: String,
+    pub max_cr: u32,
+    #[serde(default)]
+    pub dangers: Vec<DangerRating>,
+    pub individual: TreasureTable,
+    pub hoard: TreasureTable,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+struct LootData {
+    tiers: Vec<LootTier>,
+    tables: Vec<RandomTable>,
+    items: Vec<Item>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
+/// The loot tables, and where they were loaded from.
+///
+/// Properties:
+///
+/// * `path`: The path of a loot data file to load instead of the built in loot.
+/// * `tiers`: The treasure tiers, lowest first.
+/// * `tables`: The tables gems, art objects and magic items are rolled on.
+/// * `items`: Items rolled on the tables that have more to them than a name, like potions.
+/// * `error`: Why the last load failed.
+pub struct LootLibrary {
+    pub path: String,
+    pub tiers: Vec<LootTier>,
+    pub tables: Vec<RandomTable>,
+    pub items: Vec<Item>,
+    pub error: Option<String>,
+}
+
+impl Default for LootLibrary {
+    fn default() -> Self {
+        let data = parse_loot(BUILT_IN_LOOT).unwrap_or_default();
+        Self {
+            path: String::new(),
+            tiers: data.tiers,
+            tables: data.tables,
+            items: data.items,
+            error: None,
+        }
+    }
+}
+
+impl LootLibrary {
+    /// Loads the loot from `path`, or the built in loot when the path is empty.
+    pub fn load(&mut self) {
+        let loaded = if self.path.trim().is_empty() {
+            parse_loot(BUILT_IN_LOOT)
+        } else {
+            std::fs::read_to_string(self.path.trim())
+                .map_err(|err| err.to_string())
+                .and_then(|json| parse_loot(&json))
+        };
+        match loaded {
+            Ok(data) => {
+                self.tiers = data.tiers;
+                self.tables = data.tables;
+                self.items = data.items;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    /// The tier for a level: the first one that reaches the challenge rating, or the first one
+    /// listing the danger rating.
+    pub fn tier(&self, level: &LootLevel) -> Result<&LootTier, String> {
+        match level {
+            LootLevel::ChallengeRating(cr) => self
+                .tiers
+                .iter()
+                .find(|tier| tier.max_cr >= *cr)
+                .or_else(|| self.tiers.last()),
+            LootLevel::Danger(danger) => {
+                self.tiers.iter().find(|tier| tier.dangers.contains(danger))
+            }
+        }
+        .ok_or(format!("there is no loot tier for {:?}", level))
+    }
+
+    /// Rolls treasure.
+    ///
+    /// Arguments:
+    ///
+    /// * `kind`: Whether to roll on the individual or the hoard table.
+    /// * `level`: What picks the tier.
+    /// * `extra_tables`: Campaign random tables, checked before the loot tables to replace them.
+    ///
+    /// Returns:
+    ///
+    /// The coins and items, stacked.
+    pub fn generate(
+        &self,
+        kind: LootKind,
+        level: &LootLevel,
+        extra_tables: &[RandomTable],
+    ) -> Result<Inventory, String> {
+        let tier = self.tier(level)?;
+        let table = match kind {
+            LootKind::Individual => &tier.individual,
+            LootKind::Hoard => &tier.hoard,
+        };
+        let row = random_tables::pick_row(&table.rows, |row| row.roll)
+            .map_err(|err| format!("{} {:?} treasure {}", tier.name, kind, err))?;
+
+        let mut loot = Inventory::default();
+        for coins in table.coins.iter().chain(row.coins.iter()) {
+            let count = roll(&coins.formula)?.max(0) as u64;
+            loot.coins.add(&Currency::of(&coins.denomination, count)?);
+        }
+
+        let tables: Vec<RandomTable> = extra_tables
+            .iter()
+            .chain(self.tables.iter())
+            .cloned()
+            .collect();
+        for items in row.items.iter() {
+            let count = if items.count.trim().is_empty() {
+                1
+            } else {
+                roll(&items.count)?.max(0)
+            };
+            for _ in 0..count {
+                let name = random_tables::roll_on(&tables, &items.table)?;
+                let item = match self.items.iter().find(|item| item.name == name) {
+                    Some(item) => Item {
+                        quantity: 1,
+                        ..item.clone()
+                    },
+                    None => Item {
+                        value: items.value,
+                        rarity: items.rarity,
+                        ..Item::new(&name)
+                    },
+                };
+                loot.add_item(item);
+            }
+        }
+        Ok(loot)
+    }
+}
+
+/// Reads loot tiers, tables and items from json.
+fn parse_loot(json: &str) -> Result<LootData, String> {
+    serde_json::from_str(json).map_err(|err| format!("loot data: {}", err))
+}
+
+#[test]
+fn test_loot() {
+    let library = LootLibrary::default();
+    assert_eq!(library.tiers.len(), 4);
+    assert_eq!(
+        library.tier(&LootLevel::ChallengeRating(0)).unwrap().name,
+        "CR 0-4"
+    );
+    assert_eq!(
+        library.tier(&LootLevel::ChallengeRating(7)).unwrap().name,
+        "CR 5-10"
+    );
+    assert_eq!(
+        library.tier(&LootLevel::ChallengeRating(99)).unwrap().name,
+        "CR 17+"
+    );
+    assert_eq!(
+        library
+            .tier(&LootLevel::Danger(DangerRating::Dangerous))
+            .unwrap()
+            .name,
+        "CR 11-16"
+    );
+
+    for _ in 0..20 {
+        let individual = library
+            .generate(LootKind::Individual, &LootLevel::ChallengeRating(2), &[])
+            .unwrap();
+        assert!(individual.items.is_empty());
+        assert!((1..=30).contains(&individual.coins.count()));
+
+        // the hoard coins are always there, and every item is a gem, art object or magic item
+        let hoard = library
+            .generate(LootKind::Hoard, &LootLevel::Danger(DangerRating::Easy), &[])
+            .unwrap();
+        assert!(hoard.coins.cp >= 600 && hoard.coins.sp >= 300 && hoard.coins.gp >= 20);
+        for item in hoard.items.iter() {
+            let rows = library.tables.iter().flat_map(|table| table.rows.iter());
+            assert!(
+                rows.clone().any(|row| row.result == item.name),
+                "{}",
+                item.name
+            );
+        }
+    }
+
+    // a campaign table replaces the built in one with the same name
+    let gems = RandomTable::from_csv(1, "Gems 10 gp", "Glass bead").unwrap();
+    let tier = &library.tiers[0];
+    let row = tier
+        .hoard
+        .rows
+        .iter()
+        .find(|row| matches!(row.items.first(), Some(items) if items.table == "Gems 10 gp"))
+        .unwrap();
+    let only_gems = LootLibrary {
+        tiers: vec![LootTier {
+            hoard: TreasureTable {
+                coins: vec![],
+                rows: vec![LootRow {
+                    roll: RowRoll::Weight(1),
+                    ..row.clone()
+                }],
+            },
+            ..tier.clone()
+        }],
+        ..library.clone()
+    };
+    let hoard = only_gems
+        .generate(LootKind::Hoard, &LootLevel::ChallengeRating(1), &[gems])
+        .unwrap();
+    assert_eq!(hoard.items.len(), 1);
+    assert_eq!(hoard.items[0].name, "Glass bead");
+    assert!((2..=12).contains(&hoard.items[0].quantity));
+    assert_eq!(hoard.items[0].value.gp, 10);
+    assert!(parse_loot("{\"tiers\": [{\"name\": \"broken\"}]}").is_err());
+}
@@ -0,0 +1,335 @@
+// procedural names for creatures and places, learnt from word lists
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+/// How many names are generated before settling for one that is too long or already in the list.
+const ATTEMPTS: usize = 50;
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// How a name list makes new names.
+pub enum NameModel {
+    /// Each letter is picked by the letters before it, learnt from the list.
+    #[default]
+    Markov,
+    /// Names are glued together from the starts, middles and ends of names in the list.
+    Syllable,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A list of example names for a culture or creature type.
+///
+/// Properties:
+///
+/// * `id`: The id of the list.
+/// * `culture`: The culture, creature type or place kind the list names, e.g. `Elvish` or `Town`.
+/// * `model`: How new names are made from the list.
+/// * `order`: How many letters the markov model looks back.
+/// * `words`: The example names.
+pub struct NameList {
+    pub id: usize,
+    pub culture: String,
+    pub model: NameModel,
+    pub order: usize,
+    pub words: Vec<String>,
+}
+
+impl NameList {
+    pub fn new(id: usize, culture: &str, model: NameModel, words: &[&str]) -> Self {
+        Self {
+            id,
+            culture: culture.to_string(),
+            model,
+            order: 2,
+            words: words.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+
+    /// Makes a new name, preferring ones that aren't already in the list.
+    pub fn generate(&self, rng: &mut impl Rng) -> Option<String> {
+        let words: Vec<String> = self
+            .words
+            .iter()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let longest = words.iter().map(|word| word.chars().count()).max()?;
+
+        let mut name = String::new();
+        for _ in 0..ATTEMPTS {
+            name = match self.model {
+                NameModel::Markov => markov(&words, self.order.max(1), longest + 2, rng),
+                NameModel::Syllable => syllables(&words, rng),
+            };
+            let length = name.chars().count();
+            if length >= 2 && length <= longest + 2 && !words.contains(&name) {
+                break;
+            }
+        }
+        Some(capitalize(&name))
+    }
+}
+
+/// A name from a markov chain over the letters of `words`.
+fn markov(words: &[String], order: usize, max_length: usize, rng: &mut impl Rng) -> String {
+    // `^` pads the start of words, `$` marks their end
+    let mut chain: BTreeMap<String, Vec<char>> = BTreeMap::new();
+    for word in words {
+        let letters: Vec<char> = "^"
+            .repeat(order)
+            .chars()
+            .chain(word.chars())
+            .chain(['$'])
+            .collect();
+        for window in letters.windows(order + 1) {
+            let context: String = window[..order].iter().collect();
+            chain.entry(context).or_default().push(window[order]);
+        }
+    }
+
+    let mut name: Vec<char> = "^".repeat(order).chars().collect();
+    while name.len() - order < max_length {
+        let context: String = name[name.len() - order..].iter().collect();
+        match chain.get(&context).and_then(|next| next.choose(rng)) {
+            Some('$') | None => break,
+            Some(letter) => name.push(*letter),
+        }
+    }
+    name[order..].iter().collect()
+}
+
+/// Splits a word into syllables: consonants followed by vowels, with trailing consonants kept on
+/// the last syllable.
+fn split_syllables(word: &str) -> Vec<String> {
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut syllables: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut seen_vowel = false;
+    for c in word.chars() {
+        if seen_vowel && !is_vowel(c) {
+            syllables.push(std::mem::take(&mut current));
+            seen_vowel = false;
+        }
+        seen_vowel |= is_vowel(c);
+        current.push(c);
+    }
+    if !seen_vowel && !syllables.is_empty() {
+        syllables.last_mut().unwrap().push_str(&current);
+    } else if !current.is_empty() {
+        syllables.push(current);
+    }
+    syllables
+}
+
+/// A name made of the first syllable of one word, the middle syllables of others and the last
+/// syllable of another, as long as a random word from the list.
+fn syllables(words: &[String], rng: &mut impl Rng) -> String {
+    let split: Vec<Vec<String>> = words.iter().map(|word| split_syllables(word)).collect();
+    let pick = |rng: &mut dyn rand::RngCore, part: fn(&Vec<String>) -> Option<&String>| {
+        let candidates: Vec<&String> = split.iter().filter_map(part).collect();
+        candidates
+            .choose(rng)
+            .map(|syllable| syllable.to_string())
+            .unwrap_or_default()
+    };
+    let length = split
+        .choose(rng)
+        .map_or(2, |syllables| syllables.len())
+        .max(2);
+
+    let mut name = pick(rng, |syllables| {
+        syllables.first().filter(|_| syllables.len() > 1)
+    });
+    for _ in 2..length {
+        name.push_str(&pick(rng, |syllables| {
+            syllables.get(1).filter(|_| syllables.len() > 2)
+        }));
+    }
+    name.push_str(&pick(rng, |syllables| {
+        syllables.last().filter(|_| syllables.len() > 1)
+    }));
+    name
+}
+
+fn capitalize(name: &str) -> String {
+    let mut letters = name.chars();
+    match letters.next() {
+        Some(first) => first.to_uppercase().chain(letters).collect(),
+        None => String::new(),
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
+/// Every name list, and the seed names are generated from.
+///
+/// Properties:
+///
+/// * `lists`: The name lists.
+/// * `seed`: When set, the same seed always gives the same names in the same order.
+/// * `rolls`: How many names have been generated from the seed.
+pub struct NameGenerator {
+    pub lists: Vec<NameList>,
+    pub seed: Option<u64>,
+    pub rolls: u64,
+}
+
+impl Default for NameGenerator {
+    fn default() -> Self {
+        Self {
+            lists: vec![
+                NameList::new(
+                    0,
+                    "Humanoid",
+                    NameModel::Markov,
+                    &[
+                        "Aldric", "Bran", "Cedric", "Daria", "Elena", "Garrett", "Helena",
+                        "Isolde", "Jorah", "Kara", "Lucan", "Mira", "Nolan", "Osric", "Petra",
+                        "Roland", "Selene", "Tomas", "Ulric", "Wren",
+                    ],
+                ),
+                NameList::new(
+                    0,
+                    "Elvish",
+                    NameModel::Syllable,
+                    &[
+                        "Aerendil",
+                        "Caelynn",
+                        "Elandor",
+                        "Faelar",
+                        "Galinndan",
+                        "Ilphas",
+                        "Lianna",
+                        "Mialee",
+                        "Naivara",
+                        "Sariel",
+                        "Thamior",
+                        "Valanthe",
+                    ],
+                ),
+                NameList::new(
+                    0,
+                    "Dwarvish",
+                    NameModel::Syllable,
+                    &[
+                        "Baern", "Bruenor", "Dagnal", "Eberk", "Gardain", "Harbek", "Kildrak",
+                        "Orsik", "Rurik", "Thorin", "Vondal", "Helja", "Kathra",
+                    ],
+                ),
+                NameList::new(
+                    0,
+                    "Orcish",
+                    NameModel::Markov,
+                    &[
+                        "Dench", "Feng", "Gell", "Henk", "Holg", "Krusk", "Ront", "Shump", "Thokk",
+                        "Baggi", "Emen", "Ovak", "Sutha", "Vola",
+                    ],
+                ),
+                NameList::new(
+                    0,
+                    "Places",
+                    NameModel::Markov,
+                    &[
+                        "Ashford",
+                        "Blackwater",
+                        "Briarwood",
+                        "Coldharbour",
+                        "Dunmere",
+                        "Eastmarch",
+                        "Frostholm",
+                        "Greywatch",
+                        "Highcliff",
+                        "Ironvale",
+                        "Kingsbridge",
+                        "Millbrook",
+                        "Oakheart",
+                        "Ravenmoor",
+                        "Stonebridge",
+                        "Thornbury",
+                        "Westfall",
+                        "Wolfden",
+                    ],
+                ),
+            ],
+            seed: None,
+            rolls: 0,
+        }
+    }
+}
+
+impl NameGenerator {
+    /// The first list whose culture matches one of `cultures` (ignoring case), or else the first
+    /// list.
+    pub fn list_for(&self, cultures: &[&str]) -> Option<&NameList> {
+        cultures
+            .iter()
+            .find_map(|culture| {
+                self.lists
+                    .iter()
+                    .find(|list| list.culture.trim().eq_ignore_ascii_case(culture.trim()))
+            })
+            .or_else(|| self.lists.first())
+    }
+
+    /// Generates a name from the list for the first of `cultures` that has one.
+    pub fn name(&mut self, cultures: &[&str]) -> Option<String> {
+        let list = self.list_for(cultures)?;
+        match self.seed {
+            Some(seed) => {
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(self.rolls));
+                let name = list.generate(&mut rng);
+                self.rolls += 1;
+                name
+            }
+            None => list.generate(&mut rand::thread_rng()),
+        }
+    }
+}
+
+#[test]
+fn test_names() {
+    assert_eq!(split_syllables("bruenor"), vec!["brue", "nor"]);
+    assert_eq!(split_syllables("thorin"), vec!["tho", "rin"]);
+
+    let mut seeded = NameGenerator {
+        seed: Some(42),
+        ..Default::default()
+    };
+    let mut again = seeded.clone();
+    for culture in ["humanoid", "elvish", "dwarvish", "orcish", "places"] {
+        let name = seeded.name(&[culture]).unwrap();
+        assert_eq!(again.name(&[culture]).unwrap(), name);
+        assert!(name.len() >= 2, "{}", name);
+        assert!(name.chars().next().unwrap().is_uppercase());
+    }
+    // the seed moves on, so the next name comes from a different rng
+    assert_eq!(seeded.rolls, 5);
+
+    let letters: String = seeded
+        .list_for(&["Orcish"])
+        .unwrap()
+        .words
+        .concat()
+        .to_lowercase();
+    let name = seeded.name(&["Orcish"]).unwrap().to_lowercase();
+    assert!(name.chars().all(|c| letters.contains(c)));
+
+    assert_eq!(
+        seeded.list_for(&["Undead", "Places"]).unwrap().culture,
+        "Places"
+    );
+    assert_eq!(seeded.list_for(&["Undead"]).unwrap().culture, "Humanoid");
+}
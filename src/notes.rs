@@ -0,0 +1,348 @@
+// notes written in markdown, with tags, pins and `[[Name]]` links to places and creatures
+
+use crate::structure::{Note, Place};
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// How a piece of text in a note looks.
+pub enum SpanStyle {
+    Plain,
+    /// `**bold**`
+    Bold,
+    /// `*italic*` or `_italic_`
+    Italic,
+    /// `` `code` ``
+    Code,
+    /// `[[Name]]`, a link to the place or creature called `Name`.
+    Link,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A piece of text in a line of a note, all in one style.
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A line of a note.
+pub enum Block {
+    /// `# Heading`, with the number of `#`s.
+    Heading(usize, Vec<Span>),
+    /// `- item` or `* item`.
+    Bullet(Vec<Span>),
+    /// `1. item`, with its number.
+    Numbered(u32, Vec<Span>),
+    /// `> quote`
+    Quote(Vec<Span>),
+    /// `---`
+    Rule,
+    Paragraph(Vec<Span>),
+    /// A blank line.
+    Gap,
+}
+
+/// Reads the markdown of a note, a line at a time.
+pub fn parse_markdown(text: &str) -> Vec<Block> {
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim();
+            let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+            if trimmed.is_empty() {
+                Block::Gap
+            } else if trimmed.len() >= 3 && trimmed.chars().all(|c| c == '-') {
+                Block::Rule
+            } else if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+                Block::Heading(hashes, parse_spans(&trimmed[hashes..]))
+            } else if let Some(rest) = trimmed
+                .strip_prefix("- ")
+                .or_else(|| trimmed.strip_prefix("* "))
+            {
+                Block::Bullet(parse_spans(rest))
+            } else if let Some(rest) = trimmed.strip_prefix('>') {
+                Block::Quote(parse_spans(rest))
+            } else {
+                match trimmed.split_once(". ") {
+                    Some((number, rest)) if number.parse::<u32>().is_ok() => {
+                        Block::Numbered(number.parse().unwrap_or_default(), parse_spans(rest))
+                    }
+                    _ => Block::Paragraph(parse_spans(trimmed)),
+                }
+            }
+        })
+        .collect()
+}
+
+/// Splits a line into styled spans. markers without a closing marker are left as they are.
+pub fn parse_spans(line: &str) -> Vec<Span> {
+    let markers = [
+        ("[[", "]]", SpanStyle::Link),
+        ("**", "**", SpanStyle::Bold),
+        ("`", "`", SpanStyle::Code),
+        ("*", "*", SpanStyle::Italic),
+        ("_", "_", SpanStyle::Italic),
+    ];
+    let mut spans: Vec<Span> = vec![];
+    let mut plain = String::new();
+    let mut rest = line.trim();
+    'outer: while let Some(c) = rest.chars().next() {
+        for (open, close, style) in markers {
+            let inner = match rest.strip_prefix(open) {
+                Some(inner) => inner,
+                None => continue,
+            };
+            match inner.find(close) {
+                Some(end) if end > 0 => {
+                    if !plain.is_empty() {
+                        spans.push(Span {
+                            text: std::mem::take(&mut plain),
+                            style: SpanStyle::Plain,
+                        });
+                    }
+                    spans.push(Span {
+                        text: inner[..end].trim().to_string(),
+                        style,
+                    });
+                    rest = &inner[end + close.len()..];
+                    continue 'outer;
+                }
+                _ => {}
+            }
+        }
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if !plain.is_empty() {
+        spans.push(Span {
+            text: plain,
+            style: SpanStyle::Plain,
+        });
+    }
+    spans
+}
+
+/// The names of everything a note links to, in order.
+pub fn links(text: &str) -> Vec<String> {
+    parse_markdown(text)
+        .into_iter()
+        .flat_map(|block| match block {
+            Block::Heading(_, spans)
+            | Block::Bullet(spans)
+            | Block::Numbered(_, spans)
+            | Block::Quote(spans)
+            | Block::Paragraph(spans) => spans,
+            Block::Rule | Block::Gap => vec![],
+        })
+        .filter(|span| span.style == SpanStyle::Link)
+        .map(|span| span.text)
+        .collect()
+}
+
+/// The notes that link to `name`, ignoring case, pinned notes first.
+pub fn backlinks<'a>(notes: &'a [Note], name: &str) -> Vec<&'a Note> {
+    let mut linking: Vec<&Note> = notes
+        .iter()
+        .filter(|note| {
+            links(&note.text)
+                .iter()
+                .any(|link| link.eq_ignore_ascii_case(name.trim()))
+        })
+        .collect();
+    linking.sort_by_key(|note| !note.pinned);
+    linking
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// What a link leads to.
+pub enum LinkTarget {
+    Place(usize),
+    /// A creature, and the place it is in.
+    Creature {
+        place_id: usize,
+        creature_id: usize,
+    },
+}
+
+/// Finds the place or creature called `name`, ignoring case. places come first.
+pub fn resolve(places: &[Place], name: &str) -> Option<LinkTarget> {
+    let name = name.trim();
+    if let Some(place) = places
+        .iter()
+        .find(|place| place.name.eq_ignore_ascii_case(name))
+    {
+        return Some(LinkTarget::Place(place.id));
+    }
+    places.iter().find_map(|place| {
+        place
+            .creatures
+            .iter()
+            .find(|creature| creature.name.eq_ignore_ascii_case(name))
+            .map(|creature| LinkTarget::Creature {
+                place_id: place.id,
+                creature_id: creature.id,
+            })
+    })
+}
+
+/// Whether a note has every word of `query` in its title, text or tags, ignoring case. words
+/// starting with `#` only match tags.
+pub fn matches(note: &Note, query: &str) -> bool {
+    let title = note.title.to_lowercase();
+    let text = note.text.to_lowercase();
+    query.split_whitespace().all(|word| {
+        let word = word.to_lowercase();
+        match word.strip_prefix('#') {
+            Some(tag) => note.tags.iter().any(|other| other.to_lowercase() == tag),
+            None => {
+                title.contains(&word)
+                    || text.contains(&word)
+                    || note
+                        .tags
+                        .iter()
+                        .any(|tag| tag.to_lowercase().contains(&word))
+            }
+        }
+    })
+}
+
+/// Every tag used by any note, sorted, without repeats.
+pub fn all_tags(notes: &[Note]) -> Vec<String> {
+    let mut tags: Vec<String> = notes
+        .iter()
+        .flat_map(|note| note.tags.iter().cloned())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The state of the notes interface.
+///
+/// Properties:
+///
+/// * `selected_id`: The id of the note being read or edited.
+/// * `search`: Words the listed notes have to contain, `#tag` for a tag.
+/// * `tag`: The tag the listed notes have to have.
+/// * `editing`: Whether the selected note shows its markdown instead of rendering it.
+pub struct NotesMenu {
+    pub selected_id: Option<usize>,
+    pub search: String,
+    pub tag: Option<String>,
+    pub editing: bool,
+}
+
+impl NotesMenu {
+    /// The notes to list, pinned notes first and then by name.
+    pub fn listed<'a>(&self, notes: &'a [Note]) -> Vec<&'a Note> {
+        let mut listed: Vec<&Note> = notes
+            .iter()
+            .filter(|note| matches(note, &self.search))
+            .filter(|note| match &self.tag {
+                Some(tag) => note.tags.contains(tag),
+                None => true,
+            })
+            .collect();
+        listed.sort_by_key(|note| (!note.pinned, note.name().to_lowercase()));
+        listed
+    }
+}
+
+#[test]
+fn test_notes() {
+    let blocks = parse_markdown(
+        "# The **Rusty** Tankard\n- run by [[Grik]]\n2. `1d6` rats\n> _quiet_ here\n---\n\nplain * text",
+    );
+    let plain = |text: &str| Span {
+        text: text.to_string(),
+        style: SpanStyle::Plain,
+    };
+    let styled = |text: &str, style| Span {
+        text: text.to_string(),
+        style,
+    };
+    assert_eq!(
+        blocks,
+        vec![
+            Block::Heading(
+                1,
+                vec![
+                    plain("The "),
+                    styled("Rusty", SpanStyle::Bold),
+                    plain(" Tankard")
+                ]
+            ),
+            Block::Bullet(vec![plain("run by "), styled("Grik", SpanStyle::Link)]),
+            Block::Numbered(2, vec![styled("1d6", SpanStyle::Code), plain(" rats")]),
+            Block::Quote(vec![styled("quiet", SpanStyle::Italic), plain(" here")]),
+            Block::Rule,
+            Block::Gap,
+            Block::Paragraph(vec![plain("plain * text")]),
+        ]
+    );
+    assert_eq!(
+        parse_markdown("#hashtag")[0],
+        Block::Paragraph(vec![plain("#hashtag")])
+    );
+
+    let mut places = vec![Place::new(1, "The Rusty Tankard")];
+    places[0].creatures.push(crate::structure::Creature {
+        id: 2,
+        name: "Grik".to_string(),
+        ..Default::default()
+    });
+    assert_eq!(
+        resolve(&places, "the rusty tankard"),
+        Some(LinkTarget::Place(1))
+    );
+    assert_eq!(
+        resolve(&places, "GRIK"),
+        Some(LinkTarget::Creature {
+            place_id: 1,
+            creature_id: 2
+        })
+    );
+    assert_eq!(resolve(&places, "Nobody"), None);
+
+    let rumours = Note {
+        text: "[[Grik]] owes money to [[The Rusty Tankard]] and [[grik]]'s cousin".to_string(),
+        tags: vec!["rumour".to_string()],
+        ..Note::new(3, "Rumours")
+    };
+    let debts = Note {
+        text: "nothing about goblins".to_string(),
+        tags: vec!["money".to_string(), "rumour".to_string()],
+        pinned: true,
+        ..Note::new(4, "Debts")
+    };
+    assert_eq!(
+        links(&rumours.text),
+        vec!["Grik", "The Rusty Tankard", "grik"]
+    );
+    let notes = vec![rumours, debts];
+    assert_eq!(backlinks(&notes, "grik").len(), 1);
+    assert!(backlinks(&notes, "Debts").is_empty());
+
+    assert!(matches(&notes[0], "OWES grik"));
+    assert!(matches(&notes[1], "#money"));
+    assert!(!matches(&notes[0], "#money"));
+    assert!(!matches(&notes[1], "goblin dragon"));
+    assert_eq!(all_tags(&notes), vec!["money", "rumour"]);
+
+    let menu = NotesMenu {
+        tag: Some("rumour".to_string()),
+        ..Default::default()
+    };
+    let listed: Vec<&str> = menu
+        .listed(&notes)
+        .iter()
+        .map(|note| note.title.as_str())
+        .collect();
+    assert_eq!(listed, vec!["Debts", "Rumours"]);
+}
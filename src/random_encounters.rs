@@ -0,0 +1,248 @@
+// weighted random encounter tables that places roll on
+
+use crate::formulaic_dice_roll::roll;
+use crate::structure::{find_template, Creature};
+use rand::Rng;
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// The time of day an encounter can happen at.
+pub enum TimeOfDay {
+    #[default]
+    Any,
+    Day,
+    Night,
+}
+
+impl TimeOfDay {
+    pub const ALL: [TimeOfDay; 3] = [TimeOfDay::Any, TimeOfDay::Day, TimeOfDay::Night];
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// The weather an encounter can happen in.
+pub enum Weather {
+    #[default]
+    Any,
+    Clear,
+    Rain,
+    Storm,
+    Fog,
+    Snow,
+}
+
+impl Weather {
+    pub const ALL: [Weather; 6] = [
+        Weather::Any,
+        Weather::Clear,
+        Weather::Rain,
+        Weather::Storm,
+        Weather::Fog,
+        Weather::Snow,
+    ];
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A row of a random encounter table.
+///
+/// Properties:
+///
+/// * `template_id`: The id of the bestiary template that shows up. every rolled creature is a
+/// fresh instance of it.
+/// * `quantity`: A dice formula for how many show up, e.g. `1d4+1`.
+/// * `weight`: How likely the row is compared to the other rows.
+/// * `time`: The time of day the row can be rolled at.
+/// * `weather`: The weather the row can be rolled in.
+pub struct EncounterEntry {
+    pub template_id: usize,
+    pub quantity: String,
+    pub weight: u32,
+    pub time: TimeOfDay,
+    pub weather: Weather,
+}
+
+impl EncounterEntry {
+    pub fn new(template_id: usize) -> Self {
+        Self {
+            template_id,
+            quantity: "1".to_string(),
+            weight: 1,
+            time: TimeOfDay::Any,
+            weather: Weather::Any,
+        }
+    }
+
+    /// Whether the row can be rolled at `time` in `weather`.
+    pub fn matches(&self, time: TimeOfDay, weather: Weather) -> bool {
+        (self.time == TimeOfDay::Any || time == TimeOfDay::Any || self.time == time)
+            && (self.weather == Weather::Any || weather == Weather::Any || self.weather == weather)
+    }
+}
+
+/// The rows of the table whose template was deleted from the bestiary, by index.
+pub fn missing_templates(table: &[EncounterEntry], bestiary: &[Creature]) -> Vec<usize> {
+    table
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| find_template(bestiary, entry.template_id).is_none())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Picks a row of the table, weighted by `weight`, out of the rows that match the time and weather.
+pub fn pick_entry(
+    table: &[EncounterEntry],
+    time: TimeOfDay,
+    weather: Weather,
+) -> Option<&EncounterEntry> {
+    let candidates: Vec<&EncounterEntry> = table
+        .iter()
+        .filter(|entry| entry.weight > 0 && entry.matches(time, weather))
+        .collect();
+    let total: u32 = candidates.iter().map(|entry| entry.weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = rand::thread_rng().gen_range(0..total);
+    for entry in candidates {
+        if pick < entry.weight {
+            return Some(entry);
+        }
+        pick -= entry.weight;
+    }
+    None
+}
+
+/// An instance of `template` with its stats shifted by up to a fifth either way, so a pack of
+/// wolves isn't a pack of clones.
+pub fn fresh_copy(template: &Creature) -> Creature {
+    let mut rng = rand::thread_rng();
+    let mut creature = template.instantiate(0);
+    for stat in [
+        &mut creature.hp,
+        &mut creature.strength,
+        &mut creature.speed,
+        &mut creature.int,
+        &mut creature.mana,
+        &mut creature.vit,
+    ] {
+        let spread = *stat / 5;
+        if spread > 0 {
+            *stat += rng.gen_range(-spread..=spread);
+        }
+    }
+    creature
+}
+
+/// Rolls an encounter on a table.
+///
+/// Arguments:
+///
+/// * `table`: The rows of the table.
+/// * `bestiary`: The templates the rows refer to.
+/// * `time`: The current time of day.
+/// * `weather`: The current weather.
+///
+/// Returns:
+///
+/// The creatures that show up, numbered when there is more than one, without ids. an empty `Vec`
+/// when no row matches, or an error if a row's template was deleted or the quantity formula of the
+/// rolled row is broken.
+pub fn roll_encounter(
+    table: &[EncounterEntry],
+    bestiary: &[Creature],
+    time: TimeOfDay,
+    weather: Weather,
+) -> Result<Vec<Creature>, String> {
+    let missing = missing_templates(table, bestiary);
+    if !missing.is_empty() {
+        let rows: Vec<String> = missing.iter().map(|i| (i + 1).to_string()).collect();
+        return Err(format!(
+            "the template of row {} was deleted from the bestiary",
+            rows.join(", ")
+        ));
+    }
+    let entry = match pick_entry(table, time, weather) {
+        Some(entry) => entry,
+        None => return Ok(vec![]),
+    };
+    let template = find_template(bestiary, entry.template_id)
+        .ok_or_else(|| format!("there is no template {}", entry.template_id))?;
+    let quantity = roll(&entry.quantity)?.max(0);
+    Ok((1..=quantity)
+        .map(|n| {
+            let mut creature = fresh_copy(template);
+            if quantity > 1 {
+                creature.name = format!("{} {}", creature.name, n);
+            }
+            creature
+        })
+        .collect())
+}
+
+#[test]
+fn test_roll_encounter() {
+    let goblin = Creature {
+        id: 1,
+        name: "goblin".to_string(),
+        hp: 10,
+        ..Default::default()
+    };
+    let owlbear = Creature {
+        id: 2,
+        name: "owlbear".to_string(),
+        hp: 59,
+        ..Default::default()
+    };
+    let mut bestiary = vec![goblin, owlbear];
+    let mut goblins = EncounterEntry::new(1);
+    goblins.quantity = "1d4+1".to_string();
+    goblins.time = TimeOfDay::Night;
+    let mut owlbears = EncounterEntry::new(2);
+    owlbears.weather = Weather::Rain;
+    let table = vec![goblins, owlbears];
+
+    for _ in 0..20 {
+        let rolled = roll_encounter(&table, &bestiary, TimeOfDay::Night, Weather::Clear).unwrap();
+        assert!((2..=5).contains(&rolled.len()));
+        assert!(rolled[0].name.starts_with("goblin "));
+        assert!((8..=12).contains(&rolled[0].hp));
+
+        let rolled = roll_encounter(&table, &bestiary, TimeOfDay::Day, Weather::Rain).unwrap();
+        assert_eq!(rolled.len(), 1);
+        assert_eq!(rolled[0].name, "owlbear");
+    }
+    assert!(
+        roll_encounter(&table, &bestiary, TimeOfDay::Day, Weather::Snow)
+            .unwrap()
+            .is_empty()
+    );
+
+    // rows whose template was deleted are reported instead of rolled
+    bestiary.remove(1);
+    assert_eq!(missing_templates(&table, &bestiary), vec![1]);
+    assert_eq!(
+        roll_encounter(&table, &bestiary, TimeOfDay::Night, Weather::Clear),
+        Err("the template of row 2 was deleted from the bestiary".to_string())
+    );
+}
@@ -0,0 +1,349 @@
+// user defined random tables, with dice ranges, weights and rolls on other tables
+
+use crate::formulaic_dice_roll::roll;
+use rand::Rng;
+
+/// How many tables deep a roll may go before it is treated as a loop.
+const MAX_DEPTH: usize = 16;
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// How a row of a table is picked.
+pub enum RowRoll {
+    /// The row is picked when the die lands from the first to the second number, inclusive.
+    Range(u32, u32),
+    /// The row is picked with a chance proportional to the weight.
+    Weight(u32),
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A row of a random table.
+///
+/// Properties:
+///
+/// * `roll`: When the row is picked.
+/// * `result`: The text of the row. `{1d6}` is replaced by a dice roll and `[Table]` by a roll on
+/// the table called `Table`.
+pub struct TableRow {
+    pub roll: RowRoll,
+    pub result: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A random table.
+///
+/// Properties:
+///
+/// * `id`: The id of the table.
+/// * `name`: The name other tables refer to the table by.
+/// * `rows`: The rows of the table. when any row has a range the table is rolled with a die as big
+/// as the highest range, otherwise it is picked from by weight.
+pub struct RandomTable {
+    #[serde(default)]
+    pub id: usize,
+    pub name: String,
+    pub rows: Vec<TableRow>,
+}
+
+impl RandomTable {
+    pub fn new(id: usize, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            rows: vec![],
+        }
+    }
+
+    /// The size of the die the table is rolled with, `None` for weighted tables.
+    pub fn die(&self) -> Option<u32> {
+        die(self.rows.iter().map(|row| row.roll))
+    }
+
+    /// Picks a row of the table, without resolving its result.
+    pub fn pick(&self) -> Result<&TableRow, String> {
+        pick_row(&self.rows, |row| row.roll).map_err(|err| format!("{} {}", self.name, err))
+    }
+
+    /// The table as CSV, one row per line after a `d100,name` or `weight,name` header.
+    pub fn to_csv(&self) -> String {
+        let mut csv = match self.die() {
+            Some(die) => format!("d{},{}\n", die, quote(&self.name)),
+            None => format!("weight,{}\n", quote(&self.name)),
+        };
+        // pad ranges so they line up the way printed tables do, e.g. `01-15`
+        let width = self
+            .die()
+            .map_or(1, |die| (die.max(10) - 1).to_string().len());
+        for row in self.rows.iter() {
+            let roll = match row.roll {
+                RowRoll::Range(low, high) if low == high => format!("{:0w$}", low, w = width),
+                RowRoll::Range(low, high) => format!("{:0w$}-{:0w$}", low, high, w = width),
+                RowRoll::Weight(weight) => weight.to_string(),
+            };
+            csv.push_str(&format!("{},{}\n", roll, quote(&row.result)));
+        }
+        csv
+    }
+
+    /// Reads a table from CSV or a pasted plain text table.
+    ///
+    /// Every line is a roll followed by `,`, `;`, `:` or a tab and the result, like
+    /// `01-15: Bandits` or `3,a silver spoon`. an optional `d100,name` or `weight,name` header
+    /// names the table and says whether single numbers are dice rolls or weights. lines without a
+    /// roll have a weight of 1.
+    ///
+    /// Arguments:
+    ///
+    /// * `id`: The id of the new table.
+    /// * `name`: The name of the table, used when there is no header.
+    /// * `text`: The text to read.
+    pub fn from_csv(id: usize, name: &str, text: &str) -> Result<Self, String> {
+        let mut table = RandomTable::new(id, name);
+        let mut lines = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .peekable();
+
+        let mut dice = None;
+        if let Some(header) = lines.peek() {
+            let (first, rest) = split_field(header);
+            let first = first.to_lowercase();
+            if first == "weight" || (first.starts_with('d') && first[1..].parse::<u32>().is_ok()) {
+                dice = Some(first != "weight");
+                if let Some(rest) = rest.filter(|rest| !rest.is_empty()) {
+                    table.name = unquote(rest);
+                }
+                lines.next();
+            }
+        }
+        // without a header, any range means the numbers are dice rolls
+        let dice = dice.unwrap_or_else(|| {
+            text.lines().any(|line| {
+                matches!(
+                    parse_roll(split_field(line).0, true),
+                    Some(RowRoll::Range(low, high)) if low != high
+                )
+            })
+        });
+
+        for (number, line) in lines.enumerate() {
+            let (first, rest) = split_field(line);
+            let row = match (parse_roll(first, dice), rest) {
+                (Some(roll), Some(result)) => TableRow {
+                    roll,
+                    result: unquote(result),
+                },
+                (Some(_), None) => {
+                    return Err(format!("line {} has no result: {}", number + 1, line))
+                }
+                (None, _) => TableRow {
+                    roll: RowRoll::Weight(1),
+                    result: unquote(line.trim()),
+                },
+            };
+            table.rows.push(row);
+        }
+        Ok(table)
+    }
+}
+
+/// Splits a line at the first separator into the first field and the rest.
+fn split_field(line: &str) -> (&str, Option<&str>) {
+    match line.find([',', ';', ':', '\t']) {
+        Some(at) => (line[..at].trim(), Some(line[at + 1..].trim())),
+        None => (line.trim(), None),
+    }
+}
+
+/// Reads `01-15`, `00` (which is 100 on a d100) or a weight.
+fn parse_roll(field: &str, dice: bool) -> Option<RowRoll> {
+    let number = |text: &str| -> Option<u32> {
+        let text = text.trim();
+        match text.parse::<u32>().ok()? {
+            0 if text.len() > 1 => Some(10u32.pow(text.len() as u32)),
+            number => Some(number),
+        }
+    };
+    // both `–` and `-` show up in copied tables
+    match field.split_once(['-', '–']) {
+        Some((low, high)) => Some(RowRoll::Range(number(low)?, number(high)?)),
+        None if dice => number(field).map(|n| RowRoll::Range(n, n)),
+        None => number(field).map(RowRoll::Weight),
+    }
+}
+
+fn quote(text: &str) -> String {
+    if text.contains([',', ';', ':', '\t', '"']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        text[1..text.len() - 1].replace("\"\"", "\"")
+    } else {
+        text.to_string()
+    }
+}
+
+/// The size of the die rows with these rolls are rolled with, `None` when they are all weighted.
+fn die(rolls: impl Iterator<Item = RowRoll>) -> Option<u32> {
+    rolls
+        .filter_map(|roll| match roll {
+            RowRoll::Range(_, high) => Some(high),
+            RowRoll::Weight(_) => None,
+        })
+        .max()
+}
+
+/// Picks one of `rows` the way a table picks a row, with `roll` saying how each row is picked.
+/// when any row has a range a die as big as the highest range is rolled, otherwise the rows are
+/// picked from by weight.
+pub fn pick_row<T>(rows: &[T], roll: impl Fn(&T) -> RowRoll) -> Result<&T, String> {
+    let mut rng = rand::thread_rng();
+    if let Some(die) = die(rows.iter().map(&roll)) {
+        let rolled = rng.gen_range(1..=die.max(1));
+        return rows
+            .iter()
+            .find(|row| {
+                matches!(roll(row), RowRoll::Range(low, high) if (low..=high).contains(&rolled))
+            })
+            .ok_or(format!("has no row for a roll of {}", rolled));
+    }
+
+    let total: u32 = rows
+        .iter()
+        .map(|row| match roll(row) {
+            RowRoll::Weight(weight) => weight,
+            RowRoll::Range(..) => 0,
+        })
+        .sum();
+    if total == 0 {
+        return Err("has no rows".to_string());
+    }
+    let mut pick = rng.gen_range(0..total);
+    for row in rows.iter() {
+        if let RowRoll::Weight(weight) = roll(row) {
+            if pick < weight {
+                return Ok(row);
+            }
+            pick -= weight;
+        }
+    }
+    unreachable!()
+}
+
+/// Rolls on the table called `name` (ignoring case) and resolves the result.
+pub fn roll_on(tables: &[RandomTable], name: &str) -> Result<String, String> {
+    roll_on_depth(tables, name, 0)
+}
+
+fn roll_on_depth(tables: &[RandomTable], name: &str, depth: usize) -> Result<String, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!(
+            "tables nest more than {} deep, is there a loop?",
+            MAX_DEPTH
+        ));
+    }
+    let table = tables
+        .iter()
+        .find(|table| table.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or(format!("there is no table called {}", name))?;
+    resolve_depth(tables, &table.pick()?.result, depth + 1)
+}
+
+/// Replaces every `{formula}` in `text` with a dice roll and every `[Table]` with a roll on that
+/// table, e.g. `{1d6} gold and a roll on [Trinkets]`.
+pub fn resolve(tables: &[RandomTable], text: &str) -> Result<String, String> {
+    resolve_depth(tables, text, 0)
+}
+
+fn resolve_depth(tables: &[RandomTable], text: &str, depth: usize) -> Result<String, String> {
+    let mut resolved = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '[']) {
+        resolved.push_str(&rest[..start]);
+        let close = if rest[start..].starts_with('{') {
+            '}'
+        } else {
+            ']'
+        };
+        let end = start
+            + rest[start..]
+                .find(close)
+                .ok_or(format!("missing {} in {}", close, text))?;
+        let inner = &rest[start + 1..end];
+        if close == '}' {
+            resolved.push_str(&roll(inner)?.to_string());
+        } else {
+            resolved.push_str(&roll_on_depth(tables, inner, depth)?);
+        }
+        rest = &rest[end + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The random tables, plus the state of the tables interface.
+///
+/// Properties:
+///
+/// * `tables`: Every random table.
+/// * `selected_id`: The id of the table being edited.
+/// * `text`: The CSV being imported or exported.
+/// * `formula`: Text to resolve by hand, like `{1d6} gold and a roll on [Trinkets]`.
+/// * `results`: The results of the latest rolls, newest last.
+/// * `error`: The error of the latest roll or import.
+pub struct TableLibrary {
+    pub tables: Vec<RandomTable>,
+    pub selected_id: Option<usize>,
+    pub text: String,
+    pub formula: String,
+    pub results: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[test]
+fn test_import_and_roll() {
+    let trinkets =
+        RandomTable::from_csv(1, "", "weight,Trinkets\n3,a silver spoon\na \"lucky\" coin")
+            .unwrap();
+    assert_eq!(trinkets.name, "Trinkets");
+    assert_eq!(trinkets.rows[0].roll, RowRoll::Weight(3));
+    assert_eq!(trinkets.rows[1].roll, RowRoll::Weight(1));
+    assert_eq!(trinkets.die(), None);
+
+    let loot = RandomTable::from_csv(
+        2,
+        "Loot",
+        "01-50: {2d1+1} gold\n51–99\tnothing\n00, [trinkets]",
+    )
+    .unwrap();
+    assert_eq!(loot.die(), Some(100));
+    assert_eq!(loot.rows[1].roll, RowRoll::Range(51, 99));
+    assert_eq!(loot.rows[2].roll, RowRoll::Range(100, 100));
+    assert_eq!(RandomTable::from_csv(2, "", &loot.to_csv()).unwrap(), loot);
+    assert!(loot.to_csv().starts_with("d100,Loot\n01-50,{2d1+1} gold\n"));
+
+    let tables = vec![trinkets, loot];
+    for _ in 0..20 {
+        let result = roll_on(&tables, "loot").unwrap();
+        assert!(
+            ["3 gold", "nothing", "a silver spoon", "a \"lucky\" coin"].contains(&result.as_str()),
+            "{}",
+            result
+        );
+    }
+
+    let looping = vec![RandomTable::from_csv(3, "Loop", "[loop]").unwrap()];
+    assert!(roll_on(&looping, "Loop").is_err());
+    assert!(resolve(&tables, "[Gems]").is_err());
+}
@@ -0,0 +1,592 @@
+// versioned saves: every save records the version of its format, and older saves are brought up
+// to date by a chain of migrations before they are read
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+
+/// The version of the save format this build writes.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// A step that brings a save from one version to the next.
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// The migrations, oldest first. the one at index `n` takes a save from version `n` to `n + 1`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] =
+    [inventories_from_names, encounter_templates];
+
+/// The version a save was written with, 0 for saves from before versions were recorded.
+pub fn version_of(save: &Value) -> Result<u32, String> {
+    match save.get("schema_version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .map(|version| version as u32)
+            .ok_or(format!("schema_version {} isn't a number", version)),
+    }
+}
+
+/// Runs every migration the save hasn't had yet, and marks it as the current version.
+pub fn migrate(save: &mut Value) -> Result<(), String> {
+    if !save.is_object() {
+        return Err("a save has to be a json object".to_string());
+    }
+    let version = version_of(save)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "saved by a newer version of the app, with schema {} where this one reads up to {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(save).map_err(|err| format!("migrating from schema {}: {}", from, err))?;
+    }
+    save["schema_version"] = json!(SCHEMA_VERSION);
+    Ok(())
+}
+
+/// Migrates a save and then reads it.
+pub fn from_value<T: DeserializeOwned>(mut save: Value) -> Result<T, String> {
+    migrate(&mut save)?;
+    serde_json::from_value(save).map_err(|err| err.to_string())
+}
+
+/// Migrates and reads a save from json.
+pub fn load<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    from_value(serde_json::from_str(json).map_err(|err| err.to_string())?)
+}
+
+/// Migrates and reads a save from ron, the format eframe kept the app state in before saves had
+/// versions.
+pub fn load_ron<T: DeserializeOwned>(ron: &str) -> Result<T, String> {
+    from_value(ron_to_value(ron)?)
+}
+
+/// Reads ron as the json value serde_json would have written for the same data, so ron saves
+/// can be migrated like json ones. structs become objects, `Some(x)` becomes `x`, and enum
+/// variants with data become `{"Variant": data}`.
+pub fn ron_to_value(ron: &str) -> Result<Value, String> {
+    let mut reader = RonReader { text: ron, pos: 0 };
+    reader.skip_attributes();
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if reader.pos < ron.len() {
+        return Err(reader.error("expected the end of the document"));
+    }
+    Ok(value)
+}
+
+/// Reads ron a value at a time. `pos` is the byte the next value starts at, or whitespace before
+/// it.
+struct RonReader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> RonReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(&self, what: &str) -> String {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        format!("{} at line {} of the ron", what, line)
+    }
+
+    /// Skips whitespace and comments, which nest.
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            let bytes = trimmed.as_bytes();
+            if bytes.starts_with(b"//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if bytes.starts_with(b"/*") {
+                let mut depth = 0;
+                let mut i = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        depth += 1;
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                self.pos += i;
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Skips `#![enable(...)]` attributes at the start of a document.
+    fn skip_attributes(&mut self) {
+        loop {
+            self.skip_whitespace();
+            if !self.rest().starts_with("#![") {
+                return;
+            }
+            self.pos += self
+                .rest()
+                .find(']')
+                .map_or(self.rest().len(), |end| end + 1);
+        }
+    }
+
+    /// Skips whitespace and `c`, if `c` comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected `{}`", c))),
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    /// Reads values separated by commas up to `close`, which may follow a trailing comma.
+    fn items(&mut self, close: char) -> Result<Vec<Value>, String> {
+        let mut items = vec![];
+        while !self.eat(close) {
+            items.push(self.value()?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        match self.peek() {
+            None => Err(self.error("expected a value")),
+            Some('[') => {
+                self.pos += 1;
+                Ok(Value::Array(self.items(']')?))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut map = Map::new();
+                while !self.eat('}') {
+                    // json keys are strings, serde_json reads numbers back out of them
+                    let key = match self.value()? {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    self.expect(':')?;
+                    map.insert(key, self.value()?);
+                    if !self.eat(',') {
+                        self.expect('}')?;
+                        break;
+                    }
+                }
+                Ok(Value::Object(map))
+            }
+            Some('(') => self.parens(),
+            Some('"') => {
+                self.pos += 1;
+                self.string('"').map(Value::String)
+            }
+            Some('\'') => {
+                self.pos += 1;
+                self.string('\'').map(Value::String)
+            }
+            Some('r') if rest.starts_with("r\"") || rest.starts_with("r#") => {
+                let hashes = rest[1..].len() - rest[1..].trim_start_matches('#').len();
+                let close = format!("\"{}", "#".repeat(hashes));
+                let start = 1 + hashes + 1;
+                let end = rest[start..]
+                    .find(&close)
+                    .ok_or_else(|| self.error("unterminated raw string"))?;
+                self.pos += start + end + close.len();
+                Ok(Value::String(rest[start..start + end].to_string()))
+            }
+            Some(c) if c.is_ascii_digit() || "+-.".contains(c) => self.number(),
+            Some(c) => {
+                let ident = self
+                    .ident()
+                    .ok_or_else(|| self.error(&format!("unexpected `{}`", c)))?;
+                match ident {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "None" | "inf" | "NaN" => Ok(Value::Null),
+                    "Some" => {
+                        self.expect('(')?;
+                        let value = self.value()?;
+                        self.eat(',');
+                        self.expect(')')?;
+                        Ok(value)
+                    }
+                    variant => {
+                        self.skip_whitespace();
+                        match self.peek() {
+                            Some('(') => Ok(json!({ variant: self.parens()? })),
+                            _ => Ok(Value::String(variant.to_string())),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a struct, a tuple, or the data of an enum variant. a single value in parentheses is
+    /// a newtype, and stands for the value.
+    fn parens(&mut self) -> Result<Value, String> {
+        self.expect('(')?;
+        let start = self.pos;
+        self.skip_whitespace();
+        let named = self.ident().is_some() && {
+            self.skip_whitespace();
+            self.rest().starts_with(':')
+        };
+        self.pos = start;
+        if !named {
+            let mut items = self.items(')')?;
+            return Ok(match items.len() {
+                0 => Value::Null,
+                1 => items.remove(0),
+                _ => Value::Array(items),
+            });
+        }
+        let mut fields = Map::new();
+        while !self.eat(')') {
+            self.skip_whitespace();
+            let name = self
+                .ident()
+                .ok_or_else(|| self.error("expected a field name"))?;
+            self.expect(':')?;
+            fields.insert(name.to_string(), self.value()?);
+            if !self.eat(',') {
+                self.expect(')')?;
+                break;
+            }
+        }
+        Ok(Value::Object(fields))
+    }
+
+    /// Reads a string or char after its opening quote, up to `quote`.
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            let escaped = match c {
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(text);
+                }
+                '\\' => chars.next().map(|(_, c)| c),
+                c => {
+                    text.push(c);
+                    continue;
+                }
+            };
+            let hex = |chars: &mut std::str::CharIndices<'_>, digits: usize| -> Option<char> {
+                let digits: String = chars.take(digits).map(|(_, c)| c).collect();
+                char::from_u32(u32::from_str_radix(&digits, 16).ok()?)
+            };
+            let c = match escaped {
+                Some('n') => Some('\n'),
+                Some('t') => Some('\t'),
+                Some('r') => Some('\r'),
+                Some('0') => Some('\0'),
+                Some('x') => hex(&mut chars, 2),
+                Some('u') if chars.clone().next().map(|(_, c)| c) == Some('{') => {
+                    chars.next();
+                    let digits: String = chars
+                        .by_ref()
+                        .map(|(_, c)| c)
+                        .take_while(|c| *c != '}')
+                        .collect();
+                    u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                }
+                Some('u') => hex(&mut chars, 4),
+                escaped => escaped,
+            };
+            text.push(c.ok_or_else(|| self.error("broken escape in a string"))?);
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let rest = self.rest();
+        let mut len = 0;
+        for (i, c) in rest.char_indices() {
+            let exponent_sign = "+-".contains(c) && (i == 0 || rest[..i].ends_with(['e', 'E']));
+            if !(c.is_alphanumeric() || c == '.' || c == '_' || exponent_sign) {
+                break;
+            }
+            len = i + c.len_utf8();
+        }
+        let number = rest[..len].replace('_', "");
+        self.pos += len;
+        let (negative, digits) = match number.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, number.trim_start_matches('+')),
+        };
+        let radix = match digits.get(..2) {
+            Some("0x") => Some(16),
+            Some("0b") => Some(2),
+            Some("0o") => Some(8),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let value = i64::from_str_radix(&digits[2..], radix)
+                .map_err(|_| self.error(&format!("broken number {}", number)))?;
+            return Ok(json!(if negative { -value } else { value }));
+        }
+        if let Ok(value) = number.parse::<i64>() {
+            return Ok(json!(value));
+        }
+        if let Ok(value) = number.parse::<u64>() {
+            return Ok(json!(value));
+        }
+        match number.parse::<f64>() {
+            Ok(value) => Ok(serde_json::Number::from_f64(value).map_or(Value::Null, Value::Number)),
+            Err(_) => Err(self.error(&format!("broken number {}", number))),
+        }
+    }
+}
+
+/// Version 0 to 1: character inventories were lists of item names, and are now items and coins.
+fn inventories_from_names(save: &mut Value) -> Result<(), String> {
+    let characters = match save.get_mut("characters") {
+        Some(Value::Array(characters)) => characters,
+        _ => return Ok(()),
+    };
+    for character in characters.iter_mut() {
+        let names = match character.get("inventory") {
+            Some(Value::Array(names)) => names,
+            _ => continue,
+        };
+        let items = names
+            .iter()
+            .map(|name| match name.as_str() {
+                Some(name) => Ok(json!({"name": name, "quantity": 1})),
+                None => Err(format!("inventory item {} isn't a name", name)),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        character["inventory"] = json!({ "items": items });
+    }
+    Ok(())
+}
+
+/// Version 1 to 2: rows of random encounter tables held a copy of their bestiary template, and
+/// now hold its id. copies of templates that were deleted since are added back to the bestiary.
+fn encounter_templates(save: &mut Value) -> Result<(), String> {
+    let mut id_next = largest_id(save);
+    let mut bestiary = match save.get("bestiary") {
+        Some(Value::Array(bestiary)) => bestiary.clone(),
+        _ => vec![],
+    };
+    let places = match save.get_mut("places") {
+        Some(Value::Array(places)) => places,
+        _ => return Ok(()),
+    };
+    for place in places.iter_mut() {
+        let entries = match place.get_mut("encounter_table") {
+            Some(Value::Array(entries)) => entries,
+            _ => continue,
+        };
+        for entry in entries.iter_mut() {
+            let mut creature = match entry
+                .as_object_mut()
+                .and_then(|entry| entry.remove("creature"))
+            {
+                Some(creature) => creature,
+                None => continue,
+            };
+            let template = bestiary
+                .iter()
+                .find(|template| template.get("id").is_some() && template["id"] == creature["id"])
+                .or_else(|| {
+                    bestiary
+                        .iter()
+                        .find(|template| template["name"] == creature["name"])
+                });
+            entry["template_id"] = match template {
+                Some(template) => template["id"].clone(),
+                None => {
+                    id_next += 1;
+                    creature["id"] = json!(id_next);
+                    creature["template_id"] = Value::Null;
+                    bestiary.push(creature);
+                    json!(id_next)
+                }
+            };
+        }
+    }
+    save["bestiary"] = Value::Array(bestiary);
+    if save.get("id_next").is_some() {
+        save["id_next"] = json!({ "id": id_next });
+    }
+    Ok(())
+}
+
+/// The largest id anywhere in the save, or the last id handed out if that is larger.
+fn largest_id(save: &Value) -> u64 {
+    match save {
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| match value {
+                Value::Number(id) if key == "id" || key.ends_with("_id") => {
+                    id.as_u64().unwrap_or(0)
+                }
+                value => largest_id(value),
+            })
+            .max()
+            .unwrap_or(0),
+        Value::Array(values) => values.iter().map(largest_id).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Keeps a save that couldn't be read in a file of its own in `dir`, so nothing is lost when the
+/// app starts over.
+///
+/// Returns:
+///
+/// The path of the file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn quarantine(dir: &std::path::Path, save: &str) -> Result<std::path::PathBuf, String> {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let path = dir.join(format!("dnd_tool_quarantine_{}.json", seconds));
+    std::fs::write(&path, save).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+#[test]
+fn test_migrations() {
+    // the fixtures are the state of older builds, written by eframe in ron and by serde_json
+    let baseline_ron = include_str!("../tests/fixtures/save_baseline.ron");
+    let baseline_json = include_str!("../tests/fixtures/save_baseline.json");
+    assert_eq!(
+        ron_to_value(baseline_ron).unwrap(),
+        serde_json::from_str::<Value>(baseline_json).unwrap()
+    );
+    for baseline in [
+        load::<crate::app::DndTool>(baseline_json).unwrap(),
+        load_ron::<crate::app::DndTool>(baseline_ron).unwrap(),
+    ] {
+        let baseline = serde_json::to_value(&baseline).unwrap();
+        assert_eq!(baseline["schema_version"], json!(SCHEMA_VERSION));
+        assert_eq!(baseline["places"][1]["name"], json!("The Rusty Tankard"));
+        assert_eq!(baseline["places"][1]["creatures"][0]["name"], json!("Grik"));
+        assert_eq!(baseline["open_place_windows"], json!([1]));
+        assert_eq!(baseline["dice_windows"][0]["raw_formula"], json!("1d6+2"));
+    }
+
+    let characters_ron = include_str!("../tests/fixtures/save_v0_characters.ron");
+    let mut characters: Value =
+        serde_json::from_str(include_str!("../tests/fixtures/save_v0_characters.json")).unwrap();
+    assert_eq!(ron_to_value(characters_ron).unwrap(), characters);
+    assert!(load_ron::<crate::app::DndTool>(characters_ron).is_ok());
+    assert_eq!(version_of(&characters).unwrap(), 0);
+    migrate(&mut characters).unwrap();
+    assert_eq!(version_of(&characters).unwrap(), SCHEMA_VERSION);
+    let inventory = &characters["characters"][0]["inventory"];
+    assert_eq!(
+        inventory["items"][1],
+        json!({"name": "Torch", "quantity": 1})
+    );
+    let app: crate::app::DndTool = from_value(characters.clone()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&app).unwrap()["characters"][0]["inventory"]["items"][0]["name"],
+        json!("Hempen rope")
+    );
+    let table = &characters["places"][3]["encounter_table"];
+    assert_eq!(table[0]["template_id"], json!(11));
+    assert_eq!(table[1]["template_id"], json!(14));
+    assert_eq!(characters["bestiary"][1]["name"], json!("Dire wolf"));
+    // migrating again changes nothing
+    let mut again = characters.clone();
+    migrate(&mut again).unwrap();
+    assert_eq!(again, characters);
+
+    // encounter rows refer to their template, and copies of deleted templates come back
+    let mut encounters = json!({
+        "schema_version": 1,
+        "id_next": {"id": 6},
+        "bestiary": [{"id": 4, "name": "Wolf"}],
+        "places": [{"id": 1, "name": "Forest", "encounter_table": [
+            {"creature": {"id": 4, "name": "Wolf"}, "quantity": "1d4", "weight": 2, "time": "Night", "weather": "Any"},
+            {"creature": {"id": 5, "name": "Dire wolf", "hp": 37}, "quantity": "1", "weight": 1, "time": "Night", "weather": "Any"}
+        ]}]
+    });
+    migrate(&mut encounters).unwrap();
+    assert_eq!(
+        encounters["places"][0]["encounter_table"][0]["template_id"],
+        json!(4)
+    );
+    assert_eq!(
+        encounters["places"][0]["encounter_table"][1]["template_id"],
+        json!(7)
+    );
+    assert_eq!(encounters["bestiary"][1]["name"], json!("Dire wolf"));
+    assert_eq!(encounters["bestiary"][1]["hp"], json!(37));
+    assert_eq!(encounters["id_next"], json!({"id": 7}));
+    let entries: Vec<crate::random_encounters::EncounterEntry> =
+        serde_json::from_value(encounters["places"][0]["encounter_table"].clone()).unwrap();
+    assert_eq!(entries[1].template_id, 7);
+
+    let ron = "#![enable(implicit_some)]\n// a comment\n\
+        (a: r#\"x \"y\"\"#, /* b /* c */ */ b: {1: 'c', \"k\": -1.5e1},\n\
+        c: \"\\u{e9}\\n\", d: Some(V(1, 2,)), e: (), f: [0x1F, None])";
+    assert_eq!(
+        ron_to_value(ron).unwrap(),
+        json!({
+            "a": "x \"y\"",
+            "b": {"1": "c", "k": -15.0},
+            "c": "\u{e9}\n",
+            "d": {"V": [1, 2]},
+            "e": null,
+            "f": [31, null]
+        })
+    );
+    assert!(ron_to_value("(a: 1").is_err());
+    assert!(ron_to_value("(a: 1) 2").is_err());
+
+    let mut future = json!({"schema_version": SCHEMA_VERSION + 1});
+    assert!(migrate(&mut future).is_err());
+    assert!(load::<crate::app::DndTool>("[1, 2]").is_err());
+    assert!(load::<crate::app::DndTool>("{\"places\": 3}").is_err());
+
+    let dir = std::env::temp_dir().join(format!("dnd_tool_quarantine_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let kept = quarantine(&dir, "{\"places\": 3}").unwrap();
+    assert_eq!(std::fs::read_to_string(kept).unwrap(), "{\"places\": 3}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,201 @@
+// the spell compendium, loaded from data files, and casting spells from it
+
+use crate::character::Ability;
+use crate::formulaic_dice_roll::roll;
+
+/// The spells that come with the app, see `data/spells.json` for the format.
+const BUILT_IN_SPELLS: &str = include_str!("../data/spells.json");
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Default,
+)]
+/// The schools of magic.
+pub enum School {
+    Abjuration,
+    Conjuration,
+    Divination,
+    Enchantment,
+    #[default]
+    Evocation,
+    Illusion,
+    Necromancy,
+    Transmutation,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// A spell in the compendium.
+///
+/// Properties:
+///
+/// * `name`: The name of the spell, which spellbooks refer to it by.
+/// * `level`: The spell level, 0 for cantrips.
+/// * `school`: The school of magic.
+/// * `casting_time`: e.g. `1 action`.
+/// * `range`: e.g. `150 feet`.
+/// * `components`: e.g. `V, S, M (a pinch of sulfur)`.
+/// * `duration`: e.g. `Instantaneous`.
+/// * `concentration`: Whether the caster has to concentrate on the spell.
+/// * `damage`: A dice formula for the damage or healing, empty if the spell has none.
+/// * `save`: The saving throw targets make, if any.
+/// * `description`: What the spell does.
+pub struct SpellData {
+    pub name: String,
+    pub level: u32,
+    pub school: School,
+    pub casting_time: String,
+    pub range: String,
+    pub components: String,
+    pub duration: String,
+    pub concentration: bool,
+    pub damage: String,
+    pub save: Option<Ability>,
+    pub description: String,
+}
+
+impl SpellData {
+    /// e.g. `3rd level Evocation` or `Evocation cantrip`.
+    pub fn level_label(&self) -> String {
+        let suffix = match self.level {
+            1 => "st",
+            2 => "nd",
+            3 => "rd",
+            _ => "th",
+        };
+        match self.level {
+            0 => format!("{:?} cantrip", self.school),
+            level => format!("{}{} level {:?}", level, suffix, self.school),
+        }
+    }
+
+    /// Every detail on a line of its own, then the description, for tooltips.
+    pub fn details(&self) -> String {
+        let mut details = format!(
+            "{}\nCasting time: {}\nRange: {}\nComponents: {}\nDuration: {}",
+            self.level_label(),
+            self.casting_time,
+            self.range,
+            self.components,
+            self.duration
+        );
+        if self.concentration {
+            details.push_str(" (concentration)");
+        }
+        if !self.damage.is_empty() {
+            details.push_str(&format!("\nDamage: {}", self.damage));
+        }
+        if let Some(save) = self.save {
+            details.push_str(&format!("\nSave: {:?}", save));
+        }
+        format!("{}\n\n{}", details, self.description)
+    }
+
+    /// Rolls the spell's damage and describes the cast, e.g.
+    /// `Fireball: 28 (8d6), DC 13 Dexterity save`.
+    pub fn cast_summary(&self, save_dc: i32) -> Result<String, String> {
+        let mut summary = self.name.clone();
+        if !self.damage.is_empty() {
+            summary.push_str(&format!(": {} ({})", roll(&self.damage)?, self.damage));
+        }
+        if let Some(save) = self.save {
+            summary.push_str(&format!(", DC {} {:?} save", save_dc, save));
+        }
+        Ok(summary)
+    }
+}
+
+/// The mana a creature spends on a spell: one per spell level, cantrips are free.
+pub fn mana_cost(level: u32) -> i32 {
+    level as i32
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
+/// Every spell spellbooks can refer to, and where they were loaded from.
+///
+/// Properties:
+///
+/// * `path`: The path of a spell data file to load instead of the built in spells.
+/// * `spells`: The loaded spells.
+/// * `error`: Why the last load failed.
+pub struct SpellCompendium {
+    pub path: String,
+    pub spells: Vec<SpellData>,
+    pub error: Option<String>,
+}
+
+impl Default for SpellCompendium {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            spells: parse_spells(BUILT_IN_SPELLS).unwrap_or_default(),
+            error: None,
+        }
+    }
+}
+
+impl SpellCompendium {
+    /// Loads the spells from `path`, or the built in spells when the path is empty.
+    pub fn load(&mut self) {
+        let loaded = if self.path.trim().is_empty() {
+            parse_spells(BUILT_IN_SPELLS)
+        } else {
+            std::fs::read_to_string(self.path.trim())
+                .map_err(|err| err.to_string())
+                .and_then(|json| parse_spells(&json))
+        };
+        match loaded {
+            Ok(spells) => {
+                self.spells = spells;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SpellData> {
+        self.spells
+            .iter()
+            .find(|spell| spell.name.eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Reads a list of spells from json, sorted by level and then name.
+pub fn parse_spells(json: &str) -> Result<Vec<SpellData>, String> {
+    let mut spells: Vec<SpellData> =
+        serde_json::from_str(json).map_err(|err| format!("spell data: {}", err))?;
+    spells.sort_by(|a, b| a.level.cmp(&b.level).then(a.name.cmp(&b.name)));
+    Ok(spells)
+}
+
+#[test]
+fn test_spells() {
+    let compendium = SpellCompendium::default();
+    assert!(compendium.spells.len() >= 30);
+    assert_eq!(compendium.spells[0].level, 0);
+
+    let fireball = compendium.get("fireball").unwrap();
+    assert_eq!(fireball.level_label(), "3rd level Evocation");
+    assert_eq!(fireball.save, Some(Ability::Dexterity));
+    let summary = fireball.cast_summary(13).unwrap();
+    assert!(summary.starts_with("Fireball: "));
+    assert!(summary.ends_with(" (8d6), DC 13 Dexterity save"));
+    assert!(compendium.get("bless").unwrap().concentration);
+    assert_eq!(
+        compendium.get("shield").unwrap().cast_summary(13).unwrap(),
+        "Shield"
+    );
+
+    assert!(parse_spells("[{\"name\": \"Wish\", \"level\": \"nine\"}]").is_err());
+}
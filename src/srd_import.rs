@@ -0,0 +1,344 @@
+// reads monsters from the open 5e SRD json formats (5e-database / dnd5eapi and open5e)
+
+use crate::structure::{Creature, DangerRating, Size, Skill, Spell};
+use serde_json::{Map, Value};
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+)]
+#[serde(default)]
+/// The state of the SRD import menu.
+///
+/// Properties:
+///
+/// * `path`: The path of the json file to import.
+/// * `report`: What the latest import did, and every field it could not map.
+pub struct SrdImport {
+    pub path: String,
+    pub report: Vec<String>,
+}
+
+#[derive(Debug)]
+/// The creatures read from a file, and what could not be read.
+///
+/// Properties:
+///
+/// * `creatures`: The monsters, without ids.
+/// * `unmapped`: One line per monster listing the fields that have nowhere to go in a `Creature`.
+pub struct ImportReport {
+    pub creatures: Vec<Creature>,
+    pub unmapped: Vec<String>,
+}
+
+/// Reads a json file holding one monster, a list of monsters, or an open5e `{"results": [...]}`
+/// page of monsters.
+pub fn import_monsters(json: &str) -> Result<ImportReport, String> {
+    let value: Value = serde_json::from_str(json).map_err(|err| err.to_string())?;
+    let monsters = match value {
+        Value::Array(monsters) => monsters,
+        Value::Object(mut object) => match object.remove("results") {
+            Some(Value::Array(monsters)) => monsters,
+            _ => vec![Value::Object(object)],
+        },
+        _ => return Err("expected a monster or a list of monsters".to_string()),
+    };
+
+    let mut report = ImportReport {
+        creatures: vec![],
+        unmapped: vec![],
+    };
+    for (i, monster) in monsters.into_iter().enumerate() {
+        let monster = match monster {
+            Value::Object(monster) => monster,
+            _ => return Err(format!("monster {} is not an object", i + 1)),
+        };
+        let (creature, unmapped) = map_monster(monster)?;
+        if !unmapped.is_empty() {
+            report
+                .unmapped
+                .push(format!("{}: {}", creature.name, unmapped.join(", ")));
+        }
+        report.creatures.push(creature);
+    }
+    Ok(report)
+}
+
+/// Maps one monster into a creature, returning the keys that could not be mapped.
+fn map_monster(mut monster: Map<String, Value>) -> Result<(Creature, Vec<String>), String> {
+    let mut take = |key: &str| monster.remove(key).filter(|value| !value.is_null());
+    let name = take("name")
+        .and_then(|name| name.as_str().map(String::from))
+        .ok_or("a monster has no name")?;
+    let mut creature = Creature {
+        name,
+        ..Default::default()
+    };
+    if let Some(size) = take("size") {
+        creature.size = match size.as_str().unwrap_or_default().to_lowercase().as_str() {
+            "tiny" => Size::Tiny,
+            "small" => Size::Small,
+            "large" => Size::Large,
+            "huge" => Size::Huge,
+            "gargantuan" => Size::Gargantuan,
+            _ => Size::Medium,
+        };
+    }
+    creature._type = text(take("type"));
+    let subtype = text(take("subtype"));
+    if !subtype.is_empty() {
+        creature._type = format!("{} ({})", creature._type, subtype);
+    }
+
+    // 5e-database has a list of armour classes, open5e a number
+    creature.ac = match take("armor_class") {
+        Some(Value::Array(classes)) => classes
+            .first()
+            .and_then(|class| number(class.get("value")))
+            .unwrap_or(10),
+        class => number(class.as_ref()).unwrap_or(10),
+    };
+    creature.hp = number(take("hit_points").as_ref()).unwrap_or(1);
+    // the number of hit dice is the closest thing monsters have to a level
+    if let Some(dice) = take("hit_dice") {
+        creature.lv = text(Some(dice))
+            .split('d')
+            .next()
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(1);
+    }
+    take("hit_points_roll");
+    if let Some(speed) = take("speed") {
+        creature.speed = speed
+            .get("walk")
+            .map(|walk| match walk {
+                Value::String(walk) => walk
+                    .trim_end_matches(|c: char| !c.is_ascii_digit())
+                    .parse()
+                    .unwrap_or(0),
+                walk => number(Some(walk)).unwrap_or(0),
+            })
+            .unwrap_or(0);
+    }
+    creature.strength = number(take("strength").as_ref()).unwrap_or(10);
+    creature.int = number(take("intelligence").as_ref()).unwrap_or(10);
+    creature.vit = number(take("constitution").as_ref()).unwrap_or(10);
+
+    let challenge = take("cr").or_else(|| take("challenge_rating"));
+    take("challenge_rating");
+    let challenge = match &challenge {
+        Some(Value::String(cr)) => match cr.split_once('/') {
+            Some((top, bottom)) => {
+                top.parse::<f64>().unwrap_or(0.0) / bottom.parse::<f64>().unwrap_or(1.0)
+            }
+            None => cr.parse().unwrap_or(0.0),
+        },
+        Some(cr) => cr.as_f64().unwrap_or(0.0),
+        None => 0.0,
+    };
+    creature.danger = match challenge {
+        cr if cr < 1.0 => DangerRating::Easy,
+        cr if cr < 5.0 => DangerRating::Normal,
+        cr if cr < 11.0 => DangerRating::Hard,
+        cr if cr < 17.0 => DangerRating::Dangerous,
+        _ => DangerRating::Difficult,
+    };
+
+    // skills are `{"stealth": 6}` in open5e and `Skill: Stealth` proficiencies in 5e-database
+    if let Some(Value::Object(skills)) = take("skills") {
+        for (name, bonus) in skills {
+            let bonus = number(Some(&bonus)).unwrap_or(0);
+            creature.skills.push(Skill {
+                name,
+                min_max: (bonus, bonus),
+            });
+        }
+    }
+    if let Some(Value::Array(proficiencies)) = take("proficiencies") {
+        for proficiency in proficiencies {
+            let bonus = number(proficiency.get("value")).unwrap_or(0);
+            let name = text(proficiency.pointer("/proficiency/name").cloned());
+            creature.skills.push(Skill {
+                name: name.trim_start_matches("Skill: ").to_string(),
+                min_max: (bonus, bonus),
+            });
+        }
+    }
+
+    for key in [
+        "special_abilities",
+        "actions",
+        "reactions",
+        "legendary_actions",
+    ] {
+        if let Some(Value::Array(abilities)) = take(key) {
+            for ability in abilities {
+                map_ability(&mut creature, &ability, key == "actions");
+            }
+        }
+    }
+    // open5e links spells, 5e-database lists them in the spellcasting ability
+    if let Some(Value::Array(spells)) = take("spell_list") {
+        for spell in spells {
+            let name = text(Some(spell));
+            let name = name
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or_default();
+            creature.spells.push(Spell {
+                name: name.replace('-', " "),
+                min_max: (0, 0),
+            });
+        }
+    }
+
+    for key in ["desc", "legendary_desc"] {
+        let desc = text(take(key));
+        if !desc.is_empty() {
+            creature.notes.push(desc);
+        }
+    }
+
+    let mut unmapped: Vec<String> = monster
+        .into_iter()
+        .filter(|(_, value)| !is_empty(value))
+        .map(|(key, _)| key)
+        .collect();
+    unmapped.sort();
+    Ok((creature, unmapped))
+}
+
+/// Adds an ability to the notes, and for attacks, to the attack bonus and damage per round.
+fn map_ability(creature: &mut Creature, ability: &Value, is_action: bool) {
+    let name = text(ability.get("name").cloned());
+    creature
+        .notes
+        .push(format!("{}: {}", name, text(ability.get("desc").cloned())));
+
+    if let Some(Value::Object(spellcasting)) = ability.get("spellcasting") {
+        if let Some(Value::Array(spells)) = spellcasting.get("spells") {
+            for spell in spells {
+                let level = number(spell.get("level")).unwrap_or(0);
+                creature.spells.push(Spell {
+                    name: text(spell.get("name").cloned()),
+                    min_max: (level, level),
+                });
+            }
+        }
+    }
+
+    if !is_action {
+        return;
+    }
+    if let Some(bonus) = number(ability.get("attack_bonus")) {
+        creature.attack_bonus = creature.attack_bonus.max(bonus);
+    }
+    // open5e splits the dice from the bonus, 5e-database puts a list of `1d6+2`s in `damage`
+    let mut damage = average_damage(&text(ability.get("damage_dice").cloned()));
+    damage += number(ability.get("damage_bonus")).unwrap_or(0);
+    if let Some(Value::Array(parts)) = ability.get("damage") {
+        for part in parts {
+            damage += average_damage(&text(part.get("damage_dice").cloned()));
+        }
+    }
+    creature.damage_per_round = creature.damage_per_round.max(damage);
+}
+
+/// The average of a damage formula like `2d6+3`.
+fn average_damage(formula: &str) -> i32 {
+    let formula = formula.replace(' ', "").replace('-', "+-");
+    formula
+        .split('+')
+        .filter(|term| !term.is_empty())
+        .map(|term| match term.split_once('d') {
+            Some((count, size)) => {
+                let count: i32 = count.parse().unwrap_or(1);
+                let size: i32 = size.parse().unwrap_or(0);
+                count * (size + 1) / 2
+            }
+            None => term.parse().unwrap_or(0),
+        })
+        .sum()
+}
+
+fn number(value: Option<&Value>) -> Option<i32> {
+    match value? {
+        Value::Number(number) => number.as_f64().map(|number| number as i32),
+        Value::String(number) => number.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn text(value: Option<Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text,
+        Some(Value::Null) | None => String::new(),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        Value::Object(values) => values.is_empty(),
+        _ => false,
+    }
+}
+
+#[test]
+fn test_import_monsters() {
+    let dnd5eapi = r#"{
+        "index": "goblin",
+        "name": "Goblin",
+        "size": "Small",
+        "type": "humanoid",
+        "subtype": "goblinoid",
+        "armor_class": [{"type": "armor", "value": 15}],
+        "hit_points": 7,
+        "hit_dice": "2d6",
+        "speed": {"walk": "30 ft."},
+        "strength": 8,
+        "constitution": 10,
+        "intelligence": 10,
+        "proficiencies": [{"value": 6, "proficiency": {"name": "Skill: Stealth"}}],
+        "damage_immunities": [],
+        "challenge_rating": 0.25,
+        "senses": {"darkvision": "60 ft."},
+        "actions": [{"name": "Scimitar", "desc": "Melee Weapon Attack", "attack_bonus": 4,
+            "damage": [{"damage_dice": "1d6+2"}]}]
+    }"#;
+    let report = import_monsters(dnd5eapi).unwrap();
+    let goblin = &report.creatures[0];
+    assert_eq!(goblin._type, "humanoid (goblinoid)");
+    assert_eq!(
+        (goblin.size.clone(), goblin.lv, goblin.hp, goblin.ac),
+        (Size::Small, 2, 7, 15)
+    );
+    assert_eq!(
+        (goblin.speed, goblin.attack_bonus, goblin.damage_per_round),
+        (30, 4, 5)
+    );
+    assert_eq!(goblin.skills[0].name, "Stealth");
+    assert_eq!(goblin.danger, DangerRating::Easy);
+    assert_eq!(report.unmapped, vec!["Goblin: index, senses"]);
+
+    let open5e = r#"{"results": [{
+        "name": "Ogre", "size": "Large", "type": "giant", "armor_class": 11, "hit_points": 59,
+        "hit_dice": "7d10+21", "speed": {"walk": 40}, "cr": 2.0, "challenge_rating": "2",
+        "skills": {}, "actions": [{"name": "Greatclub", "desc": "", "attack_bonus": 6,
+            "damage_dice": "2d8", "damage_bonus": 4}],
+        "spell_list": []
+    }]}"#;
+    let report = import_monsters(open5e).unwrap();
+    let ogre = &report.creatures[0];
+    assert_eq!(
+        (ogre.lv, ogre.ac, ogre.speed, ogre.damage_per_round),
+        (7, 11, 40, 13)
+    );
+    assert_eq!(ogre.danger, DangerRating::Normal);
+    assert!(report.unmapped.is_empty());
+
+    assert!(import_monsters("[1]").is_err());
+}
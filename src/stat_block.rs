@@ -0,0 +1,334 @@
+// renders creatures and places as classic stat blocks, for wikis and handouts
+
+use crate::structure::{Creature, Place};
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq,
+)]
+/// The formats stat blocks can be exported as.
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    PlainText,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::PlainText,
+    ];
+
+    /// The file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::PlainText => "txt",
+        }
+    }
+}
+
+/// Everything in a stat block, before it is laid out in a format.
+struct StatBlock {
+    name: String,
+    subtitle: String,
+    defence: Vec<(&'static str, String)>,
+    abilities: Vec<(&'static str, i32)>,
+    offence: Vec<(&'static str, String)>,
+    lists: Vec<(&'static str, Vec<String>)>,
+}
+
+impl StatBlock {
+    fn new(creature: &Creature) -> Self {
+        let rating = creature.challenge_rating().overall;
+        let range = |min_max: (i32, i32)| {
+            if min_max.0 == min_max.1 {
+                min_max.0.to_string()
+            } else {
+                format!("{}-{}", min_max.0, min_max.1)
+            }
+        };
+        Self {
+            name: creature.name.clone(),
+            subtitle: format!(
+                "{:?} {}, {:?} danger",
+                creature.size, creature._type, creature.danger
+            ),
+            defence: vec![
+                ("Armor Class", creature.ac.to_string()),
+                ("Hit Points", creature.hp.to_string()),
+                ("Speed", creature.speed.to_string()),
+            ],
+            abilities: vec![
+                ("STR", creature.strength),
+                ("INT", creature.int),
+                ("VIT", creature.vit),
+                ("MANA", creature.mana),
+            ],
+            offence: vec![
+                ("Level", creature.lv.to_string()),
+                ("Attack Bonus", format!("{:+}", creature.attack_bonus)),
+                ("Damage per Round", creature.damage_per_round.to_string()),
+                (
+                    "Challenge",
+                    format!("{} ({} XP)", rating.label(), rating.xp()),
+                ),
+                (
+                    "Proficiency Bonus",
+                    format!("{:+}", rating.row().proficiency_bonus),
+                ),
+            ],
+            lists: vec![
+                (
+                    "Skills",
+                    creature
+                        .skills
+                        .iter()
+                        .map(|skill| format!("{} {}", skill.name, range(skill.min_max)))
+                        .collect(),
+                ),
+                (
+                    "Spells",
+                    creature
+                        .spells
+                        .iter()
+                        .map(|spell| format!("{} {}", spell.name, range(spell.min_max)))
+                        .collect(),
+                ),
+                ("Notes", creature.notes.clone()),
+            ],
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut text = format!("### {}\n*{}*\n\n---\n", self.name, self.subtitle);
+        for (label, value) in self.defence.iter() {
+            text.push_str(&format!("- **{}** {}\n", label, value));
+        }
+        text.push_str("\n---\n\n|");
+        for (label, _) in self.abilities.iter() {
+            text.push_str(&format!(" {} |", label));
+        }
+        text.push_str("\n|");
+        text.push_str(&":---:|".repeat(self.abilities.len()));
+        text.push_str("\n|");
+        for (_, value) in self.abilities.iter() {
+            text.push_str(&format!(" {} |", value));
+        }
+        text.push_str("\n\n---\n");
+        for (label, value) in self.offence.iter() {
+            text.push_str(&format!("- **{}** {}\n", label, value));
+        }
+        for (heading, items) in self.lists.iter().filter(|(_, items)| !items.is_empty()) {
+            text.push_str(&format!("\n**{}**\n", heading));
+            for item in items {
+                text.push_str(&format!("- {}\n", item));
+            }
+        }
+        text
+    }
+
+    fn html(&self) -> String {
+        let mut html = format!(
+            "<div class=\"stat-block\">\n<h2>{}</h2>\n<p><em>{}</em></p>\n<hr>\n",
+            escape(&self.name),
+            escape(&self.subtitle)
+        );
+        for (label, value) in self.defence.iter() {
+            html.push_str(&format!(
+                "<p><strong>{}</strong> {}</p>\n",
+                label,
+                escape(value)
+            ));
+        }
+        html.push_str("<hr>\n<table>\n<tr>");
+        for (label, _) in self.abilities.iter() {
+            html.push_str(&format!("<th>{}</th>", label));
+        }
+        html.push_str("</tr>\n<tr>");
+        for (_, value) in self.abilities.iter() {
+            html.push_str(&format!("<td>{}</td>", value));
+        }
+        html.push_str("</tr>\n</table>\n<hr>\n");
+        for (label, value) in self.offence.iter() {
+            html.push_str(&format!(
+                "<p><strong>{}</strong> {}</p>\n",
+                label,
+                escape(value)
+            ));
+        }
+        for (heading, items) in self.lists.iter().filter(|(_, items)| !items.is_empty()) {
+            html.push_str(&format!("<h3>{}</h3>\n<ul>\n", heading));
+            for item in items {
+                html.push_str(&format!("<li>{}</li>\n", escape(item)));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</div>\n");
+        html
+    }
+
+    fn plain_text(&self) -> String {
+        let rule = "-".repeat(40);
+        let mut text = format!(
+            "{}\n{}\n{}\n",
+            self.name.to_uppercase(),
+            self.subtitle,
+            rule
+        );
+        for (label, value) in self.defence.iter() {
+            text.push_str(&format!("{} {}\n", label, value));
+        }
+        text.push_str(&format!("{}\n", rule));
+        for (label, _) in self.abilities.iter() {
+            text.push_str(&format!("{:<6}", label));
+        }
+        text.push('\n');
+        for (_, value) in self.abilities.iter() {
+            text.push_str(&format!("{:<6}", value));
+        }
+        text.push_str(&format!("\n{}\n", rule));
+        for (label, value) in self.offence.iter() {
+            text.push_str(&format!("{} {}\n", label, value));
+        }
+        for (heading, items) in self.lists.iter().filter(|(_, items)| !items.is_empty()) {
+            text.push_str(&format!("{}: {}\n", heading, items.join("; ")));
+        }
+        text
+    }
+
+    fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Markdown => self.markdown(),
+            ExportFormat::Html => self.html(),
+            ExportFormat::PlainText => self.plain_text(),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Wraps html stat blocks in a page that needs nothing else to display.
+fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: Georgia, serif; }}\n\
+         .stat-block {{ max-width: 40em; margin: 1em 0; padding: 0.5em 1em; background: #fdf1dc; \
+         border-top: 4px solid #e69a28; border-bottom: 4px solid #e69a28; }}\n\
+         .stat-block h2 {{ color: #7a200d; margin-bottom: 0; font-variant: small-caps; }}\n\
+         .stat-block p {{ margin: 0.2em 0; }}\n\
+         .stat-block hr {{ border: 0; border-top: 2px solid #922610; }}\n\
+         .stat-block table {{ width: 100%; text-align: center; }}\n\
+         </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+/// The stat block of a creature.
+pub fn creature_block(creature: &Creature, format: ExportFormat) -> String {
+    let block = StatBlock::new(creature).render(format);
+    match format {
+        ExportFormat::Html => html_page(&creature.name, &block),
+        _ => block,
+    }
+}
+
+/// The name and description of a place, followed by the stat block of every creature in it.
+pub fn place_block(place: &Place, format: ExportFormat) -> String {
+    let blocks: Vec<String> = place
+        .creatures
+        .iter()
+        .map(|creature| StatBlock::new(creature).render(format))
+        .collect();
+    match format {
+        ExportFormat::Markdown => format!(
+            "## {}\n\n{}\n\n{}",
+            place.name,
+            place.description,
+            blocks.join("\n")
+        ),
+        ExportFormat::Html => html_page(
+            &place.name,
+            &format!(
+                "<h1>{}</h1>\n<p>{}</p>\n{}",
+                escape(&place.name),
+                escape(&place.description),
+                blocks.concat()
+            ),
+        ),
+        ExportFormat::PlainText => format!(
+            "{}\n{}\n\n{}",
+            place.name.to_uppercase(),
+            place.description,
+            blocks.join("\n")
+        ),
+    }
+}
+
+/// A file name for an export of `name`, without characters file systems dislike.
+pub fn file_name(name: &str, format: ExportFormat) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = if name.is_empty() {
+        "export".to_string()
+    } else {
+        name
+    };
+    format!("{}.{}", name, format.extension())
+}
+
+#[test]
+fn test_stat_blocks() {
+    let mut creature = Creature {
+        name: "Goblin <Boss>".to_string(),
+        _type: "humanoid".to_string(),
+        hp: 21,
+        ac: 17,
+        attack_bonus: 4,
+        damage_per_round: 8,
+        ..Default::default()
+    };
+    creature.notes.push("Redirect Attack".to_string());
+
+    let markdown = creature_block(&creature, ExportFormat::Markdown);
+    assert!(markdown.starts_with("### Goblin <Boss>\n*Medium humanoid, Normal danger*"));
+    assert!(markdown.contains("- **Armor Class** 17\n"));
+    assert!(markdown.contains("**Notes**\n- Redirect Attack\n"));
+    assert!(!markdown.contains("**Skills**"));
+
+    let html = creature_block(&creature, ExportFormat::Html);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h2>Goblin &lt;Boss&gt;</h2>"));
+
+    let text = creature_block(&creature, ExportFormat::PlainText);
+    assert!(text.contains("Attack Bonus +4\n"));
+    assert!(text.contains("Notes: Redirect Attack\n"));
+
+    let mut camp = Place::new(1, "Goblin camp");
+    camp.creatures = vec![creature.clone(), creature];
+    assert_eq!(
+        place_block(&camp, ExportFormat::Html)
+            .matches("class=\"stat-block\"")
+            .count(),
+        2
+    );
+    assert_eq!(
+        file_name("Goblin camp", ExportFormat::Markdown),
+        "Goblin_camp.md"
+    );
+}
@@ -1,6 +1,11 @@
 use std::fmt::{Display, Formatter};
 use rand::Rng;
+use crate::challenge_rating::{CreatureRating, Difficulty};
 use crate::formulaic_dice_roll::{DiceRollEquationNode, parse_equation, tokenize_equation};
+use crate::generation::{GenerationProfile, Stat};
+use crate::inventory::Inventory;
+use crate::random_encounters::EncounterEntry;
+use crate::spells::{mana_cost, SpellData};
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
 pub struct NextId {
@@ -16,6 +21,11 @@ impl NextId {
         self.id += 1;
         self.id
     }
+
+    /// Makes sure the next id is above `id`, for ids that came from somewhere else.
+    pub fn skip_past(&mut self, id: usize) {
+        self.id = self.id.max(id);
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
@@ -30,6 +40,7 @@ impl NextId {
 /// * `modifier`: The modifier to add to the roll.
 /// * `note`: This is a string that will be displayed with the results of the roll.
 /// * `rolls`: a roll history
+/// * `roll_error`: Why the last roll failed, like a division by zero. the formula itself is fine.
 pub struct DiceMenu {
     pub amount: usize,
     pub raw_formula: String,
@@ -39,10 +50,13 @@ pub struct DiceMenu {
     // dice_results: Vec<usize>,
     pub note: String,
     pub rolls: Vec<Vec<i64>>,
+    #[serde(default)]
+    pub roll_error: Option<String>,
 }
 
 impl DiceMenu {
     pub fn parse_formula(&mut self) -> Result<(), String> {
+        self.roll_error = None;
         self.formula = Some(parse_equation(&tokenize_equation(&self.raw_formula)?));
         Ok(())
     }
@@ -54,18 +68,55 @@ impl DiceMenu {
 /// Properties:
 ///
 /// * `id`: The id of the note.
-/// * `text`: The text of the note.
+/// * `text`: The text of the note, in markdown. `[[Name]]` links to a place or creature.
 /// * `displayed`: This is a boolean value that indicates whether the note is displayed or not.
+/// * `title`: The title of the note.
+/// * `tags`: Tags to find the note by.
+/// * `pinned`: Whether the note is listed before the others.
 pub struct Note {
     pub id: usize,
     pub text: String,
     pub displayed: bool,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Note {
+    pub fn new(id: usize, title: &str) -> Self {
+        Self {
+            id,
+            text: String::new(),
+            displayed: true,
+            title: title.to_string(),
+            tags: vec![],
+            pinned: false,
+        }
+    }
+
+    /// The title, or the first line of the text for notes without one.
+    pub fn name(&self) -> String {
+        if !self.title.trim().is_empty() {
+            return self.title.clone();
+        }
+        match self.text.lines().map(str::trim).find(|line| !line.is_empty()) {
+            Some(line) => line.trim_start_matches('#').trim().to_string(),
+            None => "untitled note".to_string(),
+        }
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
 pub enum Interface {
     DiceRolling,
     CreatureCreation,
+    Combat,
+    RandomTables,
+    Characters,
+    Notes,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
@@ -73,25 +124,214 @@ pub enum Interface {
 ///
 /// Properties:
 ///
+/// * `id`: The id of the place. stays the same when places are added or removed.
 /// * `name`: A String that holds the name of the place.
 /// * `creatures`: A vector of Creature objects.
+/// * `parent_id`: The id of the place this place is inside of, `None` for top level places.
+/// * `kind`: What sort of place this is.
+/// * `description`: A description of the place.
+/// * `tags`: Tags for finding and grouping places.
+/// * `encounter_table`: The random encounters that can happen in the place.
+/// * `loot`: The items and coins lying around the place.
 pub struct Place {
+    #[serde(default)]
+    pub(crate) id: usize,
     pub(crate) name: String,
     pub(crate) creatures: Vec<Creature>,
+    #[serde(default)]
+    pub(crate) parent_id: Option<usize>,
+    #[serde(default)]
+    pub(crate) kind: PlaceKind,
+    #[serde(default)]
+    pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) encounter_table: Vec<EncounterEntry>,
+    #[serde(default)]
+    pub(crate) loot: Inventory,
+}
+
+impl Place {
+    pub fn new(id: usize, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            creatures: vec![],
+            parent_id: None,
+            kind: PlaceKind::default(),
+            description: String::new(),
+            tags: vec![],
+            encounter_table: vec![],
+            loot: Inventory::default(),
+        }
+    }
+
+    /// Drops the inventory of the creature with `creature_id` into the loot of the place.
+    pub fn drop_inventory(&mut self, creature_id: usize) {
+        let Self {
+            creatures, loot, ..
+        } = self;
+        if let Some(creature) = creatures.iter_mut().find(|creature| creature.id == creature_id) {
+            loot.take_all(&mut creature.inventory);
+        }
+    }
 }
 
 #[derive(
-    serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq, Default,
+    serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Ord, PartialEq, PartialOrd, Eq, Default,
 )]
+/// What sort of place a `Place` is, from biggest to smallest.
+pub enum PlaceKind {
+    World,
+    Region,
+    Town,
+    Building,
+    #[default]
+    Location,
+}
+
+impl PlaceKind {
+    pub const ALL: [PlaceKind; 5] = [
+        PlaceKind::World,
+        PlaceKind::Region,
+        PlaceKind::Town,
+        PlaceKind::Building,
+        PlaceKind::Location,
+    ];
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A route between two places. routes go both ways.
+///
+/// Properties:
+///
+/// * `from`: The id of one end of the route.
+/// * `to`: The id of the other end of the route.
+/// * `distance`: The distance in miles.
+/// * `travel_hours`: How many hours the route takes to travel.
+pub struct Connection {
+    pub from: usize,
+    pub to: usize,
+    pub distance: i32,
+    pub travel_hours: i32,
+}
+
+impl Connection {
+    /// The id of the place at the other end of the route from `id`, if the route touches `id`.
+    pub fn other_end(&self, id: usize) -> Option<usize> {
+        if self.from == id {
+            Some(self.to)
+        } else if self.to == id {
+            Some(self.from)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `ancestor_id` is `id` or one of the places `id` is inside of.
+pub fn is_within(places: &[Place], id: usize, ancestor_id: usize) -> bool {
+    let mut current = Some(id);
+    // the length bound stops a broken parent loop from hanging the app
+    for _ in 0..=places.len() {
+        match current {
+            Some(current_id) if current_id == ancestor_id => return true,
+            Some(current_id) => {
+                current = find_place(places, current_id).and_then(|place| place.parent_id)
+            }
+            None => return false,
+        }
+    }
+    false
+}
+
+/// The names of a place and everything it is inside of, e.g. `World / Sword Coast / Waterdeep`.
+pub fn place_path(places: &[Place], id: usize) -> String {
+    let mut names = vec![];
+    let mut current = find_place(places, id);
+    while let Some(place) = current {
+        if names.len() > places.len() {
+            break;
+        }
+        names.push(place.name.clone());
+        current = place.parent_id.and_then(|parent| find_place(places, parent));
+    }
+    names.reverse();
+    names.join(" / ")
+}
+
+/// Removes a place and every route to it. places inside of it move up to its parent.
+pub fn remove_place(places: &mut Vec<Place>, connections: &mut Vec<Connection>, id: usize) {
+    let parent_id = match find_place(places, id) {
+        Some(place) => place.parent_id,
+        None => return,
+    };
+    for place in places.iter_mut() {
+        if place.parent_id == Some(id) {
+            place.parent_id = parent_id;
+        }
+    }
+    places.retain(|place| place.id != id);
+    connections.retain(|connection| connection.other_end(id).is_none());
+}
+
+/// Finds the place with the given id.
+pub fn find_place(places: &[Place], id: usize) -> Option<&Place> {
+    places.iter().find(|place| place.id == id)
+}
+
+/// Finds the place with the given id.
+pub fn find_place_mut(places: &mut [Place], id: usize) -> Option<&mut Place> {
+    places.iter_mut().find(|place| place.id == id)
+}
+
+/// Finds the creature with the given id, in whichever place it is in.
+pub fn find_creature_mut(places: &mut [Place], id: usize) -> Option<&mut Creature> {
+    places
+        .iter_mut()
+        .flat_map(|place| place.creatures.iter_mut())
+        .find(|creature| creature.id == id)
+}
+
+/// Finds the template with the given id in the bestiary.
+pub fn find_template(bestiary: &[Creature], id: usize) -> Option<&Creature> {
+    bestiary.iter().find(|template| template.id == id)
+}
+
+/// Updates every instance of `template` in every place from it.
+///
+/// Returns:
+///
+/// How many instances were updated.
+pub fn update_instances(places: &mut [Place], template: &Creature) -> usize {
+    let mut updated = 0;
+    for creature in places.iter_mut().flat_map(|place| place.creatures.iter_mut()) {
+        if creature.template_id == Some(template.id) {
+            creature.update_from_template(template);
+            updated += 1;
+        }
+    }
+    updated
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+#[serde(default)]
 /// `Creature` is a struct with 10 fields, one of which is a vector of `Skill`s and another of which is
 /// a vector of `Spell`s. this contains all the information that is needed for a creature
 ///
 /// Properties:
 ///
+/// * `id`: The id of the creature. 0 until the creature is saved into the bestiary or a place.
+/// * `template_id`: The id of the bestiary template a placed creature is an instance of, `None`
+/// for templates and detached creatures.
 /// * `size`: The size of the creature.
 /// * `_type`: The type of creature. This is used to determine what kind of creature it is.
 /// * `lv`: Level
 /// * `hp`: Health points.
+/// * `ac`: Armour class.
+/// * `attack_bonus`: The bonus the creature adds to its attack rolls.
+/// * `damage_per_round`: The average damage the creature deals each round.
 /// * `strength`: How much damage the creature can do with physical attacks.
 /// * `speed`: How fast the creature is.
 /// * `int`: Intelligence
@@ -100,12 +340,20 @@ pub struct Place {
 /// * `name`: The name of the creature.
 /// * `skills`: A vector of Skills that the creature has.
 /// * `spells`: A vector of spells that the creature can cast.
+/// * `spellbook`: The names of the compendium spells the creature can cast with its mana.
+/// * `mana_spent`: The mana spent on spells since the creature last rested.
+/// * `inventory`: The items and coins the creature carries.
 pub struct Creature {
+    pub id: usize,
+    pub template_id: Option<usize>,
     pub size: Size,
     pub danger: DangerRating,
     pub _type: String,
     pub lv: i32,
     pub hp: i32,
+    pub ac: i32,
+    pub attack_bonus: i32,
+    pub damage_per_round: i32,
     pub strength: i32,
     pub speed: i32,
     pub int: i32,
@@ -115,6 +363,9 @@ pub struct Creature {
     pub skills: Vec<Skill>,
     pub spells: Vec<Spell>,
     pub notes: Vec<String>,
+    pub spellbook: Vec<String>,
+    pub mana_spent: i32,
+    pub inventory: Inventory,
 }
 
 #[derive(
@@ -130,6 +381,14 @@ pub enum DangerRating {
 }
 
 impl DangerRating {
+    pub const ALL: [DangerRating; 5] = [
+        DangerRating::Easy,
+        DangerRating::Normal,
+        DangerRating::Hard,
+        DangerRating::Dangerous,
+        DangerRating::Difficult,
+    ];
+
     pub fn randomize() -> Self {
         let mut rng = rand::thread_rng();
         let rand_num = rng.gen_range(0..5);
@@ -145,12 +404,191 @@ impl DangerRating {
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// The adventuring party that encounters are measured against.
+///
+/// Properties:
+///
+/// * `level`: The level of every character in the party.
+/// * `size`: The number of characters in the party.
+pub struct Party {
+    pub level: i32,
+    pub size: i32,
+}
+
+impl Party {
+    /// The level of each character in the party.
+    pub fn levels(&self) -> Vec<i32> {
+        vec![self.level; self.size.max(0) as usize]
+    }
+}
+
+impl Default for Party {
+    fn default() -> Self {
+        Self { level: 1, size: 4 }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// A creature proposed by the encounter builder.
+///
+/// Properties:
+///
+/// * `creature`: The proposed creature.
+/// * `locked`: Locked slots survive rerolls.
+pub struct EncounterSlot {
+    pub creature: Creature,
+    pub locked: bool,
+}
+
+impl EncounterSlot {
+    pub fn new(creature: Creature) -> Self {
+        Self {
+            creature,
+            locked: false,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// Everything needed to render an encounter builder window.
+///
+/// Properties:
+///
+/// * `id`: The id of the menu.
+/// * `place_id`: The id of the place the encounter is committed into.
+/// * `target`: The difficulty the builder aims for.
+/// * `max_creatures`: The most creatures a proposal may contain.
+/// * `slots`: The current proposal.
+pub struct EncounterBuilderMenu {
+    pub id: usize,
+    #[serde(alias = "place_index")]
+    pub place_id: usize,
+    pub target: Difficulty,
+    pub max_creatures: usize,
+    pub slots: Vec<EncounterSlot>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// Everything needed to render a window that generates many creatures into a place at once.
+///
+/// Properties:
+///
+/// * `id`: The id of the menu.
+/// * `place_id`: The id of the place the creatures are committed into.
+/// * `count`: A dice formula for how many creatures to make, e.g. `2d6`.
+/// * `name`: The name the creatures are numbered after. empty to give each a random name.
+/// * `_type`: The type of the creatures.
+/// * `levels`: The inclusive range levels are rolled in.
+/// * `max_value`: The highest value a rolled stat can have.
+/// * `sizes`: The sizes creatures are picked from. empty for any size.
+/// * `dangers`: The danger ratings creatures are picked from. empty for any rating.
+/// * `from_level`: Whether stats are level points spread by the profile, or rolled on their own.
+/// * `profile`: How the stats are rolled.
+/// * `preview`: The creatures made so far, not yet in the place.
+/// * `error`: The error of the latest generation.
+pub struct BatchMenu {
+    pub id: usize,
+    pub place_id: usize,
+    pub count: String,
+    pub name: String,
+    pub _type: String,
+    pub levels: (i32, i32),
+    pub max_value: i32,
+    pub sizes: Vec<Size>,
+    pub dangers: Vec<DangerRating>,
+    pub from_level: bool,
+    pub profile: GenerationProfile,
+    pub preview: Vec<Creature>,
+    pub error: Option<String>,
+}
+
+impl BatchMenu {
+    pub fn new(id: usize, place_id: usize) -> Self {
+        Self {
+            id,
+            place_id,
+            count: "1d6+2".to_string(),
+            name: String::new(),
+            _type: "Humanoid".to_string(),
+            levels: (1, 5),
+            max_value: 100,
+            sizes: vec![],
+            dangers: vec![],
+            from_level: true,
+            profile: GenerationProfile {
+                locks: vec![Stat::Name, Stat::Type, Stat::Size, Stat::Danger, Stat::Level],
+                ..Default::default()
+            },
+            preview: vec![],
+            error: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
+/// Everything needed to render a creature creation window.
+///
+/// Properties:
+///
+/// * `inner`: The creature being made.
+/// * `id`: The id of the menu.
+/// * `max_value`: The highest value the stat sliders go up to, at least the creature's largest.
+/// * `place_id`: The id of the place an instance of the creature is put in by "save and place".
+/// * `editing_id`: The id of the template (or, in old saves, placed creature) being edited, `None`
+/// when making a new one.
+/// * `legacy_editing`: The `(place index, creature index)` pair old saves used instead of
+/// `editing_id`. only read when migrating.
+/// * `profile`: How the window rolls random creatures.
 pub struct CreatureMenu {
     pub inner: Creature,
     pub id: usize,
     pub max_value: i32,
-    pub selected_place_index: usize,
-    pub editing: Option<(usize, usize)>,
+    #[serde(alias = "selected_place_index")]
+    pub place_id: usize,
+    #[serde(default)]
+    pub editing_id: Option<usize>,
+    #[serde(rename = "editing", default, skip_serializing)]
+    pub legacy_editing: Option<(usize, usize)>,
+    #[serde(default)]
+    pub profile: GenerationProfile,
+}
+
+impl CreatureMenu {
+    pub fn new(id: usize, place_id: usize) -> Self {
+        Self {
+            inner: Default::default(),
+            id,
+            max_value: 100,
+            place_id,
+            editing_id: None,
+            legacy_editing: None,
+            profile: GenerationProfile::default(),
+        }
+    }
+
+    pub fn editing(id: usize, place_id: usize, creature: Creature) -> Self {
+        let menu = Self::new(id, place_id);
+        let largest = [
+            creature.lv,
+            creature.hp,
+            creature.damage_per_round,
+            creature.strength,
+            creature.speed,
+            creature.int,
+            creature.mana,
+            creature.vit,
+        ]
+        .into_iter()
+        .chain(creature.skills.iter().map(|skill| skill.min_max.1))
+        .max()
+        .unwrap_or_default();
+        Self {
+            editing_id: Some(creature.id),
+            max_value: menu.max_value.max(largest),
+            inner: creature,
+            ..menu
+        }
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
@@ -192,11 +630,16 @@ impl Creature {
     pub(crate) fn randomize(max_value: i32) -> Creature {
         let mut rng = rand::thread_rng();
         Creature {
+            id: 0,
+            template_id: None,
             size: Size::randomize(),
             danger: DangerRating::randomize(),
             _type: String::from("Humanoid"),
             lv: rng.gen_range(1..=max_value),
             hp: rng.gen_range(1..=max_value),
+            ac: rng.gen_range(10..=20),
+            attack_bonus: rng.gen_range(0..=10),
+            damage_per_round: rng.gen_range(1..=max_value),
             strength: rng.gen_range(1..=max_value),
             speed: rng.gen_range(1..=max_value),
             int: rng.gen_range(1..=max_value),
@@ -206,38 +649,104 @@ impl Creature {
             skills: vec![],
             spells: vec![],
             notes: vec![],
+            spellbook: vec![],
+            mana_spent: 0,
+            inventory: Inventory::default(),
         }
     }
-    // ut in the level range - it then takes the values above 1 and uses it as "points" to add onto the other stats randomly. 1 point = to one value change. So it does all this when I press randomize for the level. I just would need to set the maximum value for the stats first.
-    pub(crate) fn randomise_based_on_lvl(&mut self, max_value: i32)  {
-        let mut rng = rand::thread_rng();
-        let mut points = self.lv.clone();
-        let mut stats = vec![
-            &mut self.hp,
-            &mut self.strength,
-            &mut self.speed,
-            &mut self.int,
-            &mut self.mana,
-            &mut self.vit,
-        ];
-        for stat in stats.iter_mut() {
-            **stat = 1;
+    /// A new instance of this template, to put in a place.
+    pub fn instantiate(&self, id: usize) -> Creature {
+        Creature {
+            id,
+            template_id: Some(self.id),
+            ..self.clone()
         }
-        while points > 0 {
-            let stat = rng.gen_range(0..stats.len());
-            if stats[stat] == &max_value {
-                if stats.iter().all(|x| *x == &max_value) {
-                    break;
-                }
-                continue;
-            }
-            *stats[stat] += 1;
-            points -= 1;
+    }
+
+    /// Copies the stats of `template` into this instance. the id, name, spent mana and inventory
+    /// belong to the instance, so they are kept.
+    pub fn update_from_template(&mut self, template: &Creature) {
+        *self = Creature {
+            id: self.id,
+            name: std::mem::take(&mut self.name),
+            mana_spent: self.mana_spent,
+            inventory: std::mem::take(&mut self.inventory),
+            ..template.instantiate(self.id)
+        };
+    }
+
+    /// Estimates the challenge rating of the creature from its hp, ac, damage and attack bonus.
+    pub fn challenge_rating(&self) -> CreatureRating {
+        CreatureRating::new(self)
+    }
+
+    /// Spends the mana `spell` costs.
+    pub fn cast(&mut self, spell: &SpellData) -> Result<(), String> {
+        let cost = mana_cost(spell.level);
+        if self.mana - self.mana_spent < cost {
+            return Err(format!(
+                "{} needs {} mana for {} but has {} left",
+                self.name,
+                cost,
+                spell.name,
+                self.mana - self.mana_spent
+            ));
+        }
+        self.mana_spent += cost;
+        Ok(())
+    }
+
+    /// Gets all of the creature's mana back.
+    pub fn rest(&mut self) {
+        self.mana_spent = 0;
+    }
+
+    // ut in the level range - it then takes the values above 1 and uses it as "points" to add onto the other stats randomly. 1 point = to one value change. So it does all this when I press randomize for the level. I just would need to set the maximum value for the stats first.
+    /// `profile` decides how the points are spread and which stats keep their values.
+    pub(crate) fn randomise_based_on_lvl(&mut self, max_value: i32, profile: &GenerationProfile) {
+        profile.spend_points(self, self.lv, max_value);
+    }
+}
+
+impl Default for Creature {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            template_id: None,
+            size: Default::default(),
+            danger: Default::default(),
+            _type: String::new(),
+            lv: 0,
+            hp: 0,
+            ac: 10,
+            attack_bonus: 0,
+            damage_per_round: 0,
+            strength: 0,
+            speed: 0,
+            int: 0,
+            mana: 0,
+            vit: 0,
+            name: String::new(),
+            skills: vec![],
+            spells: vec![],
+            notes: vec![],
+            spellbook: vec![],
+            mana_spent: 0,
+            inventory: Inventory::default(),
         }
     }
 }
 
 impl Size {
+    pub const ALL: [Size; 6] = [
+        Size::Tiny,
+        Size::Small,
+        Size::Medium,
+        Size::Large,
+        Size::Huge,
+        Size::Gargantuan,
+    ];
+
     fn randomize() -> Size {
         let mut rng = rand::thread_rng();
         let size = rng.gen_range(0..6);
@@ -266,3 +775,65 @@ impl Display for Size {
         Ok(())
     }
 }
+
+#[test]
+fn test_place_hierarchy() {
+    let mut places = vec![
+        Place::new(1, "World"),
+        Place::new(2, "Sword Coast"),
+        Place::new(3, "Waterdeep"),
+        Place::new(4, "Neverwinter"),
+    ];
+    places[1].parent_id = Some(1);
+    places[2].parent_id = Some(2);
+    places[3].parent_id = Some(2);
+    let mut connections = vec![Connection {
+        from: 3,
+        to: 4,
+        distance: 300,
+        travel_hours: 100,
+    }];
+
+    assert_eq!(place_path(&places, 3), "World / Sword Coast / Waterdeep");
+    assert!(is_within(&places, 3, 1));
+    assert!(!is_within(&places, 1, 3));
+
+    remove_place(&mut places, &mut connections, 2);
+    assert_eq!(place_path(&places, 3), "World / Waterdeep");
+    assert_eq!(connections.len(), 1);
+    remove_place(&mut places, &mut connections, 4);
+    assert!(connections.is_empty());
+}
+
+#[test]
+fn test_template_instances() {
+    let template = Creature {
+        id: 1,
+        name: "wolf".to_string(),
+        hp: 11,
+        ..Default::default()
+    };
+    let mut den = Place::new(2, "Den");
+    den.creatures.push(template.instantiate(3));
+    den.creatures.push(template.instantiate(4));
+    den.creatures[0].name = "Grey".to_string();
+    den.creatures[0].hp = 5;
+    den.creatures[0].mana_spent = 2;
+    den.creatures[0].inventory.add_item(crate::inventory::Item::new("bone"));
+    den.creatures[1].template_id = None;
+    let mut places = vec![den];
+
+    let template = Creature { hp: 13, ..template };
+    assert_eq!(update_instances(&mut places, &template), 1);
+    let grey = &places[0].creatures[0];
+    assert_eq!((grey.id, grey.template_id, grey.name.as_str(), grey.hp), (3, Some(1), "Grey", 13));
+    assert_eq!((grey.mana_spent, grey.inventory.items.len()), (2, 1));
+    assert_eq!(places[0].creatures[1].hp, 11);
+
+    let dragon = Creature {
+        hp: 256,
+        ..template.clone()
+    };
+    assert_eq!(CreatureMenu::editing(5, 2, dragon).max_value, 256);
+    assert_eq!(CreatureMenu::editing(5, 2, template).max_value, 100);
+}